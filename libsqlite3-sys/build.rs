@@ -127,6 +127,9 @@ mod build_bundled {
         if cfg!(feature = "session") {
             cfg.flag("-DSQLITE_ENABLE_SESSION");
         }
+        if cfg!(feature = "scanstatus") {
+            cfg.flag("-DSQLITE_ENABLE_STMT_SCANSTATUS");
+        }
 
         if let Ok(limit) = env::var("SQLITE_MAX_VARIABLE_NUMBER") {
             cfg.flag(&format!("-DSQLITE_MAX_VARIABLE_NUMBER={}", limit));
@@ -390,6 +393,9 @@ mod bindings {
         if cfg!(feature = "session") {
             bindings = bindings.clang_arg("-DSQLITE_ENABLE_SESSION");
         }
+        if cfg!(feature = "scanstatus") {
+            bindings = bindings.clang_arg("-DSQLITE_ENABLE_STMT_SCANSTATUS");
+        }
         if cfg!(all(windows, feature = "winsqlite3")) {
             bindings = bindings
                 .clang_arg("-DBINDGEN_USE_WINSQLITE3")
@@ -0,0 +1,203 @@
+//! `feature = "auto_extension"` Run initialization code on every new
+//! connection, see [Automatically Load Statically Linked Extensions](https://sqlite.org/c3ref/auto_extension.html).
+//!
+//! Registered closures are called, in registration order, each time a
+//! connection is opened in this process (by rusqlite or any other SQLite
+//! user), and can add functions, collations or modules to it. If one of them
+//! fails, opening the connection fails with its error message.
+//!
+//! The [`Connection`] given to the closures does not own the database handle.
+//! Hooks, handlers and `collation_needed` callbacks registered through it are
+//! removed when the closure returns. A closure must not open a connection
+//! itself.
+//!
+//! ```rust
+//! # use rusqlite::{Connection, Result};
+//! # use rusqlite::auto_extension::register_auto_extension;
+//! fn example() -> Result<()> {
+//!     register_auto_extension(|db: &Connection| {
+//!         db.pragma_update(None, "foreign_keys", &true)
+//!     })?;
+//!     let db = Connection::open_in_memory()?;
+//!     let enabled: bool = db.pragma_query_value(None, "foreign_keys", |r| r.get(0))?;
+//!     assert!(enabled);
+//!     Ok(())
+//! }
+//! ```
+use std::mem;
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+
+use lazy_static::lazy_static;
+
+use crate::error::error_from_sqlite_code;
+use crate::ffi;
+use crate::util::SqliteMallocString;
+use crate::{Connection, Error, Result};
+
+type AutoExtension = Arc<dyn Fn(&Connection) -> Result<()> + Send + Sync>;
+
+struct Registry {
+    next_id: u64,
+    extensions: Vec<(AutoExtensionId, AutoExtension)>,
+}
+
+lazy_static! {
+    static ref REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+        next_id: 0,
+        extensions: Vec::new(),
+    });
+}
+
+/// `feature = "auto_extension"` Identifies a closure registered with
+/// [`register_auto_extension`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AutoExtensionId(u64);
+
+/// `feature = "auto_extension"` Register `init` to be called on each new
+/// connection.
+///
+/// # Failure
+///
+/// Will return Err if the SQLite automatic extension cannot be registered.
+pub fn register_auto_extension<F>(init: F) -> Result<AutoExtensionId>
+where
+    F: Fn(&Connection) -> Result<()> + Send + Sync + 'static,
+{
+    let mut registry = REGISTRY.lock().unwrap();
+    // a no-op if the entry point is already registered
+    let r = unsafe { ffi::sqlite3_auto_extension(Some(entry_point_fn())) };
+    if r != ffi::SQLITE_OK {
+        return Err(error_from_sqlite_code(r, None));
+    }
+    let id = AutoExtensionId(registry.next_id);
+    registry.next_id += 1;
+    registry.extensions.push((id, Arc::new(init)));
+    Ok(id)
+}
+
+/// `feature = "auto_extension"` Unregister the closure identified by `id`.
+///
+/// Returns `false` if it was not registered.
+pub fn cancel_auto_extension(id: AutoExtensionId) -> bool {
+    let mut registry = REGISTRY.lock().unwrap();
+    let len = registry.extensions.len();
+    registry.extensions.retain(|(i, _)| *i != id);
+    let found = registry.extensions.len() != len;
+    #[cfg(feature = "modern_sqlite")] // 3.8.7
+    {
+        if found && registry.extensions.is_empty() {
+            unsafe { ffi::sqlite3_cancel_auto_extension(Some(entry_point_fn())) };
+        }
+    }
+    found
+}
+
+/// `feature = "auto_extension"` Unregister all the closures, as well as any
+/// automatic extension registered directly with SQLite.
+pub fn reset_auto_extension() {
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.extensions.clear();
+    unsafe { ffi::sqlite3_reset_auto_extension() };
+}
+
+fn entry_point_fn() -> unsafe extern "C" fn() {
+    // SQLite declares the entry point without parameters.
+    unsafe {
+        mem::transmute::<
+            unsafe extern "C" fn(*mut ffi::sqlite3, *mut *mut c_char, *const c_void) -> c_int,
+            unsafe extern "C" fn(),
+        >(entry_point)
+    }
+}
+
+unsafe extern "C" fn entry_point(
+    db: *mut ffi::sqlite3,
+    pz_err_msg: *mut *mut c_char,
+    _: *const c_void,
+) -> c_int {
+    // Copied so that the lock is not held while the closures run.
+    let extensions: Vec<AutoExtension> = match REGISTRY.lock() {
+        Ok(registry) => registry.extensions.iter().map(|(_, e)| e.clone()).collect(),
+        Err(_) => return ffi::SQLITE_ERROR,
+    };
+    if extensions.is_empty() {
+        return ffi::SQLITE_OK;
+    }
+    let r = catch_unwind(AssertUnwindSafe(|| {
+        let conn = Connection::from_handle(db)?;
+        extensions.iter().try_for_each(|init| init(&conn))
+    }));
+    let (code, msg) = match r {
+        Ok(Ok(())) => return ffi::SQLITE_OK,
+        Ok(Err(Error::SqliteFailure(e, msg))) => {
+            (e.extended_code, msg.unwrap_or_else(|| e.to_string()))
+        }
+        Ok(Err(err)) => (ffi::SQLITE_ERROR, err.to_string()),
+        Err(_) => (ffi::SQLITE_ERROR, "auto extension panicked".to_owned()),
+    };
+    if !pz_err_msg.is_null() {
+        *pz_err_msg = SqliteMallocString::from_str(&msg).into_raw();
+    }
+    code
+}
+
+#[cfg(test)]
+#[cfg(feature = "functions")]
+mod test {
+    use super::{cancel_auto_extension, register_auto_extension};
+    use crate::functions::FunctionFlags;
+    use crate::{Connection, Error, Result};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_auto_extension() -> Result<()> {
+        // other tests may open connections meanwhile, so the extensions only
+        // act on this test's databases
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("auto_extension.db3");
+        let fail_path = temp_dir.path().join("auto_extension_fail.db3");
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let c = calls.clone();
+        let id = register_auto_extension(move |db: &Connection| {
+            let path: String = db.pragma_query_value(None, "database_list", |r| r.get(2))?;
+            match path {
+                ref p if p.ends_with("auto_extension.db3") => {
+                    c.fetch_add(1, Ordering::SeqCst);
+                    db.create_scalar_function("auto_answer", 0, FunctionFlags::SQLITE_UTF8, |_| {
+                        Ok(42)
+                    })
+                }
+                ref p if p.ends_with("auto_extension_fail.db3") => {
+                    Err(Error::UserFunctionError("auto extension failure".into()))
+                }
+                _ => Ok(()),
+            }
+        })?;
+
+        let db = Connection::open(&path)?;
+        let answer: i64 = db.query_row("SELECT auto_answer()", [], |r| r.get(0))?;
+        assert_eq!(42, answer);
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+
+        let err = Connection::open(&fail_path).unwrap_err();
+        assert!(
+            err.to_string().contains("auto extension failure"),
+            "{}",
+            err
+        );
+
+        assert!(cancel_auto_extension(id));
+        assert!(!cancel_auto_extension(id));
+        let db = Connection::open(&path)?;
+        assert!(db
+            .query_row("SELECT auto_answer()", [], |r| r.get::<_, i64>(0))
+            .is_err());
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+        Connection::open(&fail_path)?;
+        Ok(())
+    }
+}
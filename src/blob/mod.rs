@@ -97,6 +97,13 @@
 //! interface is not a perfect fit for [`Blob`], so another small set of
 //! functions is provided as well.
 //!
+//! ## Streaming whole values
+//!
+//! [`Connection::insert_blob_from_reader`] inserts a row whose BLOB is copied
+//! from a reader chunk by chunk, and [`Row::blob_reader`] opens the BLOB of a
+//! query result (when the `column_metadata` feature is enabled), so that
+//! large values never need to fit in a `Vec<u8>`.
+//!
 //! # Example (`std::io`)
 //!
 //! ```rust
@@ -192,7 +199,13 @@ use std::ptr;
 
 use super::ffi;
 use super::types::{ToSql, ToSqlOutput};
+use crate::error::error_from_sqlite_code;
+use crate::pragma::Sql;
 use crate::{Connection, DatabaseName, Result};
+#[cfg(feature = "column_metadata")]
+use crate::{Error, Row, RowIndex};
+#[cfg(feature = "column_metadata")]
+use std::cell::{Ref, RefCell};
 
 mod pos_io;
 
@@ -245,6 +258,243 @@ impl Connection {
             pos: 0,
         })
     }
+
+    /// `feature = "blob"` Insert a row into `table` whose `column` is filled
+    /// with the remaining content of `reader`, and return its rowid.
+    ///
+    /// The BLOB is first inserted as a [`ZeroBlob`] sized from `reader`, then
+    /// written incrementally, so the content is never held in memory as a
+    /// whole. The other columns get their default value. Nothing is inserted
+    /// if copying fails.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if `reader` fails, if its content is larger than
+    /// `i32::MAX` bytes (or the `SQLITE_LIMIT_LENGTH` limit), or if the
+    /// underlying SQLite calls fail.
+    pub fn insert_blob_from_reader<R: io::Read + io::Seek>(
+        &self,
+        db: DatabaseName<'_>,
+        table: &str,
+        column: &str,
+        mut reader: R,
+    ) -> Result<i64> {
+        let len = remaining_len(&mut reader)
+            .map_err(|e| error_from_sqlite_code(ffi::SQLITE_IOERR_READ, Some(e.to_string())))?;
+        self.insert_blob_from_reader_with_len(db, table, column, reader, len)
+    }
+
+    /// `feature = "blob"` Insert a row into `table` whose `column` is filled
+    /// with the next `len` bytes of `reader`, and return its rowid.
+    ///
+    /// Unlike [`insert_blob_from_reader`](Connection::insert_blob_from_reader),
+    /// `reader` does not need to be seekable, e.g. a socket or a decoder.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if `reader` fails or ends before `len` bytes, if
+    /// `len` is larger than `i32::MAX` (or the `SQLITE_LIMIT_LENGTH` limit),
+    /// or if the underlying SQLite calls fail.
+    pub fn insert_blob_from_reader_with_len<R: io::Read>(
+        &self,
+        db: DatabaseName<'_>,
+        table: &str,
+        column: &str,
+        reader: R,
+        len: u64,
+    ) -> Result<i64> {
+        if len > i32::MAX as u64 {
+            return Err(error_from_sqlite_code(ffi::SQLITE_TOOBIG, None));
+        }
+
+        let mut sql = Sql::new();
+        sql.push_keyword("INSERT")?;
+        sql.push_space();
+        sql.push_keyword("INTO")?;
+        sql.push_space();
+        sql.push_schema_name(db);
+        sql.push_dot();
+        sql.push_identifier(table);
+        sql.open_brace();
+        sql.push_identifier(column);
+        sql.close_brace();
+        sql.push_space();
+        sql.push_keyword("VALUES")?;
+        sql.push_space();
+        sql.push_sql("(zeroblob(?))");
+
+        self.execute_batch("SAVEPOINT _rusqlite_blob")?;
+        let copy = || -> Result<i64> {
+            self.execute(&sql, [len as i64])?;
+            let row_id = self.last_insert_rowid();
+            let mut blob = self.blob_open(db, table, column, row_id, false)?;
+            let copied = io::copy(&mut reader.take(len), &mut blob).map_err(|e| {
+                error_from_sqlite_code(ffi::SQLITE_IOERR_WRITE, Some(e.to_string()))
+            })?;
+            if copied < len {
+                return Err(error_from_sqlite_code(
+                    ffi::SQLITE_IOERR_SHORT_READ,
+                    Some(format!("expected {} bytes, read {}", len, copied)),
+                ));
+            }
+            blob.close()?;
+            Ok(row_id)
+        };
+        match copy() {
+            Ok(row_id) => {
+                self.execute_batch("RELEASE _rusqlite_blob")?;
+                Ok(row_id)
+            }
+            Err(e) => {
+                let _ = self.execute_batch("ROLLBACK TO _rusqlite_blob; RELEASE _rusqlite_blob");
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Number of bytes between the current position of `reader` and its end.
+fn remaining_len<R: io::Seek>(reader: &mut R) -> io::Result<u64> {
+    let pos = reader.stream_position()?;
+    let end = reader.seek(io::SeekFrom::End(0))?;
+    reader.seek(io::SeekFrom::Start(pos))?;
+    Ok(end.saturating_sub(pos))
+}
+
+#[cfg(feature = "column_metadata")]
+impl<'stmt> Row<'stmt> {
+    /// `feature = "blob"` and `feature = "column_metadata"` Open a read-only
+    /// handle to the BLOB of column `idx` of this row.
+    ///
+    /// The column must be taken directly from a table, and the query must
+    /// also return the rowid of that table (as `rowid` or as its `INTEGER
+    /// PRIMARY KEY` column), which is used to locate the value:
+    ///
+    /// ```rust,no_run
+    /// # use rusqlite::{Connection, Result};
+    /// # use std::io;
+    /// fn dump(conn: &Connection, out: &mut dyn io::Write) -> Result<()> {
+    ///     let mut stmt = conn.prepare("SELECT rowid, content FROM files")?;
+    ///     let mut rows = stmt.query([])?;
+    ///     while let Some(row) = rows.next()? {
+    ///         io::copy(&mut row.blob_reader(1)?, out).unwrap();
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if `idx` is invalid, if the column or the rowid
+    /// cannot be located, or if the underlying SQLite BLOB open call fails
+    /// (e.g. because the value is not a BLOB or a TEXT).
+    pub fn blob_reader<I: RowIndex>(&self, idx: I) -> Result<Blob<'stmt>> {
+        let stmt = self.stmt;
+        let idx = idx.idx(stmt)?;
+        let origin = stmt.column_origin(idx)?.ok_or_else(|| {
+            error_from_sqlite_code(
+                ffi::SQLITE_MISUSE,
+                Some(format!("column {} is not a table column", idx)),
+            )
+        })?;
+        let db = DatabaseName::Attached(origin.database());
+        let cache = &stmt.stmt.rowid_names;
+        let cached = cache.get_or_insert_with(origin.database(), origin.table(), || {
+            rowid_names(stmt.conn, db, origin.table())
+        })?;
+        let rowid_names = match *cached {
+            Some(ref names) => names,
+            None => {
+                return Err(error_from_sqlite_code(
+                    ffi::SQLITE_MISUSE,
+                    Some(format!("{} has no rowid", origin.table())),
+                ))
+            }
+        };
+        let is_rowid = |name: &str| rowid_names.iter().any(|n| n.eq_ignore_ascii_case(name));
+        for i in 0..stmt.column_count() {
+            match stmt.column_origin(i)? {
+                Some(o)
+                    if o.database() == origin.database()
+                        && o.table() == origin.table()
+                        && is_rowid(o.column()) =>
+                {
+                    let row_id = self.get(i)?;
+                    return stmt
+                        .conn
+                        .blob_open(db, origin.table(), origin.column(), row_id, true);
+                }
+                _ => {}
+            }
+        }
+        Err(Error::InvalidColumnName(String::from("rowid")))
+    }
+}
+
+/// Names of the columns which refer to the rowid of `table`, `None` for a
+/// `WITHOUT ROWID` table.
+#[cfg(feature = "column_metadata")]
+fn rowid_names(
+    conn: &Connection,
+    db: DatabaseName<'_>,
+    table: &str,
+) -> Result<Option<Vec<String>>> {
+    let table = match conn.table_schema(db, table)? {
+        Some(table) if !table.without_rowid => table,
+        _ => return Ok(None),
+    };
+    let mut names: Vec<String> = ["rowid", "oid", "_rowid_"]
+        .iter()
+        .filter(|alias| table.column(alias).is_none())
+        .map(|alias| (*alias).to_owned())
+        .collect();
+    // Only an INTEGER PRIMARY KEY is an alias for the rowid.
+    if let [pk] = table.primary_key().as_slice() {
+        match pk.decl_type {
+            Some(ref t) if t.eq_ignore_ascii_case("INTEGER") => names.push(pk.name.clone()),
+            _ => {}
+        }
+    }
+    Ok(Some(names))
+}
+
+/// Rowid column names of each table read by a statement, so that
+/// [`Row::blob_reader`] does not introspect the schema for every row.
+#[cfg(feature = "column_metadata")]
+#[derive(Debug, Default)]
+pub(crate) struct RowidNamesCache(RefCell<Vec<TableRowidNames>>);
+
+/// Database name, table name and rowid column names
+#[cfg(feature = "column_metadata")]
+type TableRowidNames = (String, String, Option<Vec<String>>);
+
+#[cfg(feature = "column_metadata")]
+impl RowidNamesCache {
+    fn get_or_insert_with<F>(
+        &self,
+        db: &str,
+        table: &str,
+        f: F,
+    ) -> Result<Ref<'_, Option<Vec<String>>>>
+    where
+        F: FnOnce() -> Result<Option<Vec<String>>>,
+    {
+        let pos = self
+            .0
+            .borrow()
+            .iter()
+            .position(|(d, t, _)| d == db && t == table);
+        let pos = match pos {
+            Some(pos) => pos,
+            None => {
+                let names = f()?;
+                let mut cache = self.0.borrow_mut();
+                cache.push((db.to_owned(), table.to_owned(), names));
+                cache.len() - 1
+            }
+        };
+        Ok(Ref::map(self.0.borrow(), |cache| &cache[pos].2))
+    }
 }
 
 impl Blob<'_> {
@@ -420,7 +670,7 @@ impl ToSql for ZeroBlob {
 #[cfg(test)]
 mod test {
     use crate::{Connection, DatabaseName, Result};
-    use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+    use std::io::{BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
 
     fn db_with_test_blob() -> Result<(Connection, i64)> {
         let db = Connection::open_in_memory()?;
@@ -546,4 +796,116 @@ mod test {
             Ok(())
         }
     }
+
+    #[test]
+    fn test_insert_blob_from_reader() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("CREATE TABLE test (name TEXT DEFAULT 'x', content BLOB NOT NULL);")?;
+        let data: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+        let mut reader = Cursor::new(&data[..]);
+        reader.seek(SeekFrom::Start(10)).unwrap();
+
+        let rowid = db.insert_blob_from_reader(DatabaseName::Main, "test", "content", reader)?;
+        let (name, content): (String, Vec<u8>) = db.query_row(
+            "SELECT name, content FROM test WHERE rowid = ?",
+            [rowid],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )?;
+        assert_eq!("x", name);
+        assert_eq!(&data[10..], &content[..]);
+
+        assert!(db
+            .insert_blob_from_reader(DatabaseName::Main, "missing", "content", Cursor::new(b"x"))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_blob_from_failing_reader() -> Result<()> {
+        struct Failing(Cursor<Vec<u8>>);
+        impl Read for Failing {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                if self.0.position() > 0 {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other, "broken"));
+                }
+                self.0.read(&mut buf[..1])
+            }
+        }
+        impl Seek for Failing {
+            fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+                self.0.seek(pos)
+            }
+        }
+
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("CREATE TABLE test (content BLOB);")?;
+        let reader = Failing(Cursor::new(vec![1; 10]));
+        assert!(db
+            .insert_blob_from_reader(DatabaseName::Main, "test", "content", reader)
+            .is_err());
+        let count: i64 = db.query_row("SELECT count(*) FROM test", [], |r| r.get(0))?;
+        assert_eq!(0, count);
+        assert!(db.is_autocommit());
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_blob_from_reader_with_len() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("CREATE TABLE test (content BLOB);")?;
+        let data = [0u8, 1, 2, 0, 3];
+        let rowid = db.insert_blob_from_reader_with_len(
+            DatabaseName::Main,
+            "test",
+            "content",
+            &data[..],
+            4,
+        )?;
+        let content: Vec<u8> =
+            db.query_row("SELECT content FROM test WHERE rowid = ?", [rowid], |r| {
+                r.get(0)
+            })?;
+        assert_eq!(&data[..4], &content[..]);
+
+        // the reader ends too early
+        assert!(db
+            .insert_blob_from_reader_with_len(DatabaseName::Main, "test", "content", &data[..], 6)
+            .is_err());
+        let count: i64 = db.query_row("SELECT count(*) FROM test", [], |r| r.get(0))?;
+        assert_eq!(1, count);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "column_metadata")]
+    fn test_row_blob_reader() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "CREATE TABLE a (content BLOB);
+             INSERT INTO a VALUES (X'01020304');
+             CREATE TABLE b (id INTEGER PRIMARY KEY, content BLOB);
+             INSERT INTO b VALUES (42, X'0506');",
+        )?;
+
+        let mut stmt = db.prepare("SELECT content, rowid AS r FROM a")?;
+        let mut rows = stmt.query([])?;
+        let row = rows.next()?.unwrap();
+        let mut buf = Vec::new();
+        row.blob_reader(0)?.read_to_end(&mut buf).unwrap();
+        assert_eq!(vec![1, 2, 3, 4], buf);
+
+        let mut stmt = db.prepare("SELECT id, content AS c FROM b")?;
+        let mut rows = stmt.query([])?;
+        let row = rows.next()?.unwrap();
+        let mut buf = Vec::new();
+        row.blob_reader("c")?.read_to_end(&mut buf).unwrap();
+        assert_eq!(vec![5, 6], buf);
+
+        let mut stmt = db.prepare("SELECT content, length(content) FROM a")?;
+        let mut rows = stmt.query([])?;
+        let row = rows.next()?.unwrap();
+        assert!(row.blob_reader(0).is_err());
+        assert!(row.blob_reader(1).is_err());
+        Ok(())
+    }
 }
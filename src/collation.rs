@@ -1,13 +1,42 @@
 //! `feature = "collation"` Add, remove, or modify a collation
+//!
+//! Some collations are built in, and registered with
+//! [`Connection::create_builtin_collation`] or lazily with
+//! [`builtin_collation_needed`]:
+//! * `NATURAL_ORDER` compares runs of digits by their numeric value, so that
+//!   `file2` sorts before `file10` (see [`natural_cmp`]),
+//! * `UNICODE_NOCASE` ignores case, using Unicode case folding so that `Maße`
+//!   equals `MASSE` (`feature = "unicode_collations"`),
+//! * `UNICODE_NOACCENT` ignores accents and other combining marks, so that
+//!   `café` equals `cafe` (`feature = "unicode_collations"`),
+//! * `UNICODE_NOCASE_NOACCENT` ignores both (`feature =
+//!   "unicode_collations"`),
+//! * `UCA` sorts with the root [Unicode Collation
+//!   Algorithm](https://www.unicode.org/reports/tr10/), and `UCA_<locale>`
+//!   (e.g. `UCA_de`, `UCA_sv_SE`) with the rules tailored for a locale
+//!   (`feature = "locale_collations"`).
+//!
+//! ```rust
+//! # use rusqlite::{Connection, Result};
+//! # use rusqlite::collation::builtin_collation_needed;
+//! fn example() -> Result<()> {
+//!     let db = Connection::open_in_memory()?;
+//!     db.collation_needed(builtin_collation_needed)?;
+//!     db.execute_batch("CREATE TABLE files(name TEXT COLLATE NATURAL_ORDER)")?;
+//!     Ok(())
+//! }
+//! ```
 use std::cmp::Ordering;
 use std::os::raw::{c_char, c_int, c_void};
-use std::panic::{catch_unwind, UnwindSafe};
+use std::panic::{catch_unwind, RefUnwindSafe, UnwindSafe};
 use std::ptr;
 use std::slice;
 
 use crate::ffi;
 use crate::{str_to_cstring, Connection, InnerConnection, Result};
 
+pub(crate) type CollationNeeded = dyn FnMut(&Connection, &str) -> Result<()> + Send;
+
 // FIXME copy/paste from function.rs
 unsafe extern "C" fn free_boxed_value<T>(p: *mut c_void) {
     drop(Box::from_raw(p as *mut T));
@@ -26,11 +55,17 @@ impl Connection {
     }
 
     /// `feature = "collation"` Collation needed callback
+    ///
+    /// `x_coll_needed` is invoked with the name of any undefined collation
+    /// sequence and may register it with
+    /// [`create_collation`](Connection::create_collation). The callback is
+    /// kept alive until it is replaced or the connection is closed, and a
+    /// panic inside it is caught.
     #[inline]
-    pub fn collation_needed(
-        &self,
-        x_coll_needed: fn(&Connection, &str) -> Result<()>,
-    ) -> Result<()> {
+    pub fn collation_needed<F>(&self, x_coll_needed: F) -> Result<()>
+    where
+        F: FnMut(&Connection, &str) -> Result<()> + Send + RefUnwindSafe + 'static,
+    {
         self.db.borrow_mut().collation_needed(x_coll_needed)
     }
 
@@ -39,6 +74,147 @@ impl Connection {
     pub fn remove_collation(&self, collation_name: &str) -> Result<()> {
         self.db.borrow_mut().remove_collation(collation_name)
     }
+
+    /// `feature = "collation"` Register the built-in collation named
+    /// `collation_name` (case-insensitive), see the [module](self)
+    /// documentation.
+    ///
+    /// Returns `false` if there is no such built-in collation.
+    pub fn create_builtin_collation(&self, collation_name: &str) -> Result<bool> {
+        #[cfg(feature = "locale_collations")]
+        {
+            if let Some(collator) = uca_collator(collation_name) {
+                self.create_collation(collation_name, move |a, b| collator.compare(a, b))?;
+                return Ok(true);
+            }
+        }
+        let compare: fn(&str, &str) -> Ordering = match collation_name.to_ascii_uppercase().as_str()
+        {
+            "NATURAL_ORDER" => natural_cmp,
+            #[cfg(feature = "unicode_collations")]
+            "UNICODE_NOCASE" => unicode_nocase_cmp,
+            #[cfg(feature = "unicode_collations")]
+            "UNICODE_NOACCENT" => unicode_noaccent_cmp,
+            #[cfg(feature = "unicode_collations")]
+            "UNICODE_NOCASE_NOACCENT" => unicode_nocase_noaccent_cmp,
+            _ => return Ok(false),
+        };
+        self.create_collation(collation_name, compare)?;
+        Ok(true)
+    }
+}
+
+/// `feature = "collation"` Collation needed callback registering the
+/// built-in collations.
+///
+/// ```rust,no_run
+/// # use rusqlite::{Connection, Result};
+/// # use rusqlite::collation::builtin_collation_needed;
+/// # fn example(db: &Connection) -> Result<()> {
+/// db.collation_needed(builtin_collation_needed)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn builtin_collation_needed(conn: &Connection, collation_name: &str) -> Result<()> {
+    conn.create_builtin_collation(collation_name).map(|_| ())
+}
+
+/// `feature = "collation"` Compare strings in natural order: runs of ASCII
+/// digits are compared by numeric value, other characters by code point.
+///
+/// Numbers with more leading zeros sort after, so that only identical strings
+/// are equal.
+pub fn natural_cmp(mut a: &str, mut b: &str) -> Ordering {
+    fn split_digits(s: &str) -> (&str, &str) {
+        s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()))
+    }
+    loop {
+        match (a.chars().next(), b.chars().next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (da, ra) = split_digits(a);
+                let (db, rb) = split_digits(b);
+                let (na, nb) = (da.trim_start_matches('0'), db.trim_start_matches('0'));
+                let ord = na
+                    .len()
+                    .cmp(&nb.len())
+                    .then_with(|| na.cmp(nb))
+                    .then_with(|| da.len().cmp(&db.len()));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                a = ra;
+                b = rb;
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a = &a[x.len_utf8()..];
+                b = &b[y.len_utf8()..];
+            }
+        }
+    }
+}
+
+/// `feature = "unicode_collations"` Compare strings ignoring case, using full
+/// Unicode case folding.
+#[cfg(feature = "unicode_collations")]
+pub fn unicode_nocase_cmp(a: &str, b: &str) -> Ordering {
+    unicase::UniCase::new(a).cmp(&unicase::UniCase::new(b))
+}
+
+/// `feature = "unicode_collations"` Compare strings ignoring accents and other
+/// combining marks, after canonical decomposition.
+#[cfg(feature = "unicode_collations")]
+pub fn unicode_noaccent_cmp(a: &str, b: &str) -> Ordering {
+    strip_accents(a).cmp(strip_accents(b))
+}
+
+/// `feature = "unicode_collations"` Compare strings ignoring case and
+/// accents.
+///
+/// Accents are stripped first, then case is folded like
+/// [`unicode_nocase_cmp`].
+#[cfg(feature = "unicode_collations")]
+pub fn unicode_nocase_noaccent_cmp(a: &str, b: &str) -> Ordering {
+    let a: String = strip_accents(a).collect();
+    let b: String = strip_accents(b).collect();
+    unicode_nocase_cmp(&a, &b)
+}
+
+#[cfg(feature = "unicode_collations")]
+fn strip_accents(s: &str) -> impl Iterator<Item = char> + '_ {
+    icu_normalizer::DecomposingNormalizerBorrowed::new_nfd()
+        .normalize_iter(s.chars())
+        .filter(|c| {
+            // Combining Diacritical Marks blocks
+            !matches!(c,
+                '\u{300}'..='\u{36F}'
+                | '\u{1AB0}'..='\u{1AFF}'
+                | '\u{1DC0}'..='\u{1DFF}'
+                | '\u{20D0}'..='\u{20FF}'
+                | '\u{FE20}'..='\u{FE2F}')
+        })
+}
+
+/// Collator for `UCA` or `UCA_<locale>`, `None` for other names or invalid
+/// locales. Locales without tailoring use the root collation.
+#[cfg(feature = "locale_collations")]
+fn uca_collator(collation_name: &str) -> Option<icu_collator::CollatorBorrowed<'static>> {
+    let locale = if collation_name.eq_ignore_ascii_case("UCA") {
+        "und"
+    } else {
+        match collation_name.get(..4) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("UCA_") => &collation_name[4..],
+            _ => return None,
+        }
+    };
+    // `sv_SE` like ICU locale ids, or `sv-SE` like BCP 47 tags
+    let locale = icu_locale_core::Locale::try_from_str(&locale.replace('_', "-")).ok()?;
+    icu_collator::Collator::try_new((&locale).into(), Default::default()).ok()
 }
 
 impl InnerConnection {
@@ -104,17 +280,18 @@ impl InnerConnection {
         res
     }
 
-    fn collation_needed(
-        &mut self,
-        x_coll_needed: fn(&Connection, &str) -> Result<()>,
-    ) -> Result<()> {
-        use std::mem;
-        unsafe extern "C" fn collation_needed_callback(
+    fn collation_needed<F>(&mut self, x_coll_needed: F) -> Result<()>
+    where
+        F: FnMut(&Connection, &str) -> Result<()> + Send + RefUnwindSafe + 'static,
+    {
+        unsafe extern "C" fn collation_needed_callback<F>(
             arg1: *mut c_void,
             arg2: *mut ffi::sqlite3,
             e_text_rep: c_int,
             arg3: *const c_char,
-        ) {
+        ) where
+            F: FnMut(&Connection, &str) -> Result<()>,
+        {
             use std::ffi::CStr;
             use std::str;
 
@@ -123,28 +300,40 @@ impl InnerConnection {
                 return;
             }
 
-            let callback: fn(&Connection, &str) -> Result<()> = mem::transmute(arg1);
             let res = catch_unwind(|| {
+                let boxed_f: *mut F = arg1 as *mut F;
                 let conn = Connection::from_handle(arg2).unwrap();
                 let collation_name = {
                     let c_slice = CStr::from_ptr(arg3).to_bytes();
                     str::from_utf8(c_slice).expect("illegal coallation sequence name")
                 };
-                callback(&conn, collation_name)
+                (*boxed_f)(&conn, collation_name)
             });
             if res.is_err() {
                 return; // FIXME How ?
             }
         }
 
+        // `sqlite3_collation_needed` has no `xDestroy`, so the closure is owned by
+        // `InnerConnection.collation_needed`.
+        let boxed_f = Box::new(x_coll_needed);
         let r = unsafe {
             ffi::sqlite3_collation_needed(
                 self.db(),
-                x_coll_needed as *mut c_void,
-                Some(collation_needed_callback),
+                &*boxed_f as *const F as *mut c_void,
+                Some(collation_needed_callback::<F>),
             )
         };
-        self.decode_result(r)
+        self.decode_result(r)?;
+        self.collation_needed = Some(boxed_f);
+        Ok(())
+    }
+
+    pub(crate) fn remove_collation_needed(&mut self) {
+        if self.collation_needed.is_some() {
+            unsafe { ffi::sqlite3_collation_needed(self.db(), ptr::null_mut(), None) };
+            self.collation_needed = None;
+        }
     }
 
     #[inline]
@@ -210,4 +399,127 @@ mod test {
         db.collation_needed(collation_needed)?;
         collate(db)
     }
+
+    #[test]
+    fn test_collation_needed_closure() -> Result<()> {
+        use std::collections::HashMap;
+        use std::sync::{Arc, Mutex};
+
+        let mut registry: HashMap<String, fn(&str, &str) -> Ordering> = HashMap::new();
+        registry.insert("REVERSE".to_owned(), |a, b| b.cmp(a));
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let db = Connection::open_in_memory()?;
+        {
+            let requested = requested.clone();
+            db.collation_needed(move |db: &Connection, name: &str| {
+                requested.lock().unwrap().push(name.to_owned());
+                match registry.get(name) {
+                    Some(compare) => db.create_collation(name, *compare),
+                    None => panic!("unknown collation {}", name),
+                }
+            })?;
+        }
+        let first: String = db.query_row(
+            "SELECT x FROM (SELECT 'a' AS x UNION SELECT 'b') ORDER BY x COLLATE REVERSE",
+            [],
+            |r| r.get(0),
+        )?;
+        assert_eq!("b", first);
+        // a panicking callback leaves the collation undefined
+        assert!(db
+            .execute_batch("SELECT 1 ORDER BY 'a' COLLATE UNKNOWN")
+            .is_err());
+        assert_eq!(vec!["REVERSE", "UNKNOWN"], *requested.lock().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        use super::natural_cmp;
+        let mut names = vec!["file10", "file2", "file02", "file1b", "file", "a", "file1"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            vec!["a", "file", "file1", "file1b", "file2", "file02", "file10"],
+            names
+        );
+        assert_eq!(Ordering::Equal, natural_cmp("x12y", "x12y"));
+        assert_eq!(
+            Ordering::Less,
+            natural_cmp("99999999999999999999999", "100000000000000000000000")
+        );
+    }
+
+    #[test]
+    fn test_builtin_collation_needed() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.collation_needed(super::builtin_collation_needed)?;
+        db.execute_batch(
+            "CREATE TABLE files (name TEXT COLLATE natural_order);
+             INSERT INTO files VALUES ('v10'), ('v9'), ('v1');",
+        )?;
+        let names = db
+            .prepare("SELECT name FROM files ORDER BY name")?
+            .query_map([], |r| r.get(0))?
+            .collect::<Result<Vec<String>>>()?;
+        assert_eq!(vec!["v1", "v9", "v10"], names);
+        assert!(!db.create_builtin_collation("unknown")?);
+        assert!(db
+            .execute_batch("SELECT 1 ORDER BY 'a' COLLATE unknown")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "unicode_collations")]
+    fn test_unicode_collations() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.collation_needed(super::builtin_collation_needed)?;
+        let eq = |a: &str, b: &str, collation: &str| -> Result<bool> {
+            db.query_row(
+                &format!("SELECT ? = ? COLLATE {}", collation),
+                [a, b],
+                |r| r.get(0),
+            )
+        };
+        assert!(eq("Maße", "MASSE", "UNICODE_NOCASE")?);
+        assert!(eq("ΣΊΣΥΦΟΣ", "σίσυφος", "unicode_nocase")?);
+        assert!(!eq("café", "cafe", "UNICODE_NOCASE")?);
+        assert!(eq("café", "cafe", "UNICODE_NOACCENT")?);
+        assert!(eq("Ångström", "Angstrom", "UNICODE_NOACCENT")?);
+        assert!(!eq("Café", "cafe", "UNICODE_NOACCENT")?);
+        assert!(eq("CAFÉ", "cafe", "UNICODE_NOCASE_NOACCENT")?);
+        assert!(eq("Maße", "MASSE", "UNICODE_NOCASE_NOACCENT")?);
+        assert!(eq("ΣΊΣΥΦΟΣ", "σισυφος", "UNICODE_NOCASE_NOACCENT")?);
+        assert!(!eq("cafe", "cafes", "UNICODE_NOCASE_NOACCENT")?);
+        // both fold case the same way
+        for &(a, b) in &[("STRAẞE", "strasse"), ("ﬁne", "FINE"), ("ǅ", "ǆ")] {
+            assert!(eq(a, b, "UNICODE_NOCASE")?);
+            assert!(eq(a, b, "UNICODE_NOCASE_NOACCENT")?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "locale_collations")]
+    fn test_locale_collations() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.collation_needed(super::builtin_collation_needed)?;
+        db.execute_batch(
+            "CREATE TABLE words (w TEXT); INSERT INTO words VALUES ('zebra'), ('öl'), ('ost');",
+        )?;
+        let sorted = |collation: &str| -> Result<Vec<String>> {
+            db.prepare(&format!(
+                "SELECT w FROM words ORDER BY w COLLATE {}",
+                collation
+            ))?
+            .query_map([], |r| r.get(0))?
+            .collect()
+        };
+        assert_eq!(vec!["öl", "ost", "zebra"], sorted("UCA")?);
+        assert_eq!(vec!["öl", "ost", "zebra"], sorted("UCA_de")?);
+        // in Swedish, ö is a letter sorted after z
+        assert_eq!(vec!["ost", "zebra", "öl"], sorted("uca_sv_SE")?);
+        assert!(!db.create_builtin_collation("UCA_not a locale")?);
+        Ok(())
+    }
 }
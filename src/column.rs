@@ -7,9 +7,11 @@ use crate::{Error, Result, Row, Rows, Statement};
 pub struct Column<'stmt> {
     name: &'stmt str,
     decl_type: Option<&'stmt str>,
+    #[cfg(feature = "column_metadata")]
+    origin: Option<ColumnOrigin<'stmt>>,
 }
 
-impl Column<'_> {
+impl<'stmt> Column<'stmt> {
     /// Returns the name of the column.
     #[inline]
     pub fn name(&self) -> &str {
@@ -21,6 +23,46 @@ impl Column<'_> {
     pub fn decl_type(&self) -> Option<&str> {
         self.decl_type
     }
+
+    /// `feature = "column_metadata"` Returns the table column this result
+    /// column is taken from (`None` for expression).
+    #[inline]
+    #[cfg(feature = "column_metadata")]
+    pub fn origin(&self) -> Option<ColumnOrigin<'stmt>> {
+        self.origin
+    }
+}
+
+/// `feature = "column_metadata"` Table column a result column is taken from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg(feature = "column_metadata")]
+pub struct ColumnOrigin<'stmt> {
+    database: &'stmt str,
+    table: &'stmt str,
+    column: &'stmt str,
+}
+
+#[cfg(feature = "column_metadata")]
+impl<'stmt> ColumnOrigin<'stmt> {
+    /// Returns the name of the database (e.g. `main`, `temp` or the name of
+    /// an attached database).
+    #[inline]
+    pub fn database(&self) -> &'stmt str {
+        self.database
+    }
+
+    /// Returns the name of the table.
+    #[inline]
+    pub fn table(&self) -> &'stmt str {
+        self.table
+    }
+
+    /// Returns the name of the column in the table, which may differ from the
+    /// name of the result column when it is aliased with `AS`.
+    #[inline]
+    pub fn column(&self) -> &'stmt str {
+        self.column
+    }
 }
 
 impl Statement<'_> {
@@ -102,10 +144,45 @@ impl Statement<'_> {
             let decl_type = slice.map(|s| {
                 str::from_utf8(s.to_bytes()).expect("Invalid UTF-8 sequence in column declaration")
             });
-            cols.push(Column { name, decl_type });
+            cols.push(Column {
+                name,
+                decl_type,
+                #[cfg(feature = "column_metadata")]
+                origin: self.column_origin(i).expect("Column out of bounds"),
+            });
         }
         cols
     }
+
+    /// `feature = "column_metadata"` Returns the table column a result
+    /// column is taken from, or `None` if it is an expression.
+    ///
+    /// ## Failure
+    ///
+    /// Returns an `Error::InvalidColumnIndex` if `idx` is outside the valid
+    /// column range for this row.
+    ///
+    /// Panics when a name is not valid UTF-8.
+    #[cfg(feature = "column_metadata")]
+    pub fn column_origin(&self, col: usize) -> Result<Option<ColumnOrigin<'_>>> {
+        if col >= self.column_count() {
+            return Err(Error::InvalidColumnIndex(col));
+        }
+        fn to_str(s: &std::ffi::CStr) -> &str {
+            str::from_utf8(s.to_bytes()).expect("Invalid UTF-8 sequence in column origin")
+        }
+        let database = self.stmt.column_database_name(col);
+        let table = self.stmt.column_table_name(col);
+        let column = self.stmt.column_origin_name(col);
+        Ok(match (database, table, column) {
+            (Some(database), Some(table), Some(column)) => Some(ColumnOrigin {
+                database: to_str(database),
+                table: to_str(table),
+                column: to_str(column),
+            }),
+            _ => None,
+        })
+    }
 }
 
 impl<'stmt> Rows<'stmt> {
@@ -199,6 +276,30 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    #[cfg(feature = "column_metadata")]
+    fn test_column_origin() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "CREATE TABLE foo(x INTEGER, y TEXT);
+             ATTACH DATABASE ':memory:' AS aux;
+             CREATE TABLE aux.bar(z);",
+        )?;
+        let stmt = db.prepare("SELECT y AS renamed, x + 1, z FROM foo, bar")?;
+        let columns = stmt.columns();
+        let origin = columns[0].origin().unwrap();
+        assert_eq!("main", origin.database());
+        assert_eq!("foo", origin.table());
+        assert_eq!("y", origin.column());
+        assert!(columns[1].origin().is_none());
+        // names outlive the columns
+        let table = stmt.columns()[2].origin().map(|origin| origin.table());
+        assert_eq!(Some("bar"), table);
+        assert_eq!("aux", stmt.column_origin(2)?.unwrap().database());
+        assert!(stmt.column_origin(3).is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_column_name_in_error() -> Result<()> {
         use crate::{types::Type, Error};
@@ -8,6 +8,8 @@ use crate::ffi;
 use crate::ffi::sqlite3_context;
 
 use crate::str_for_sqlite;
+#[cfg(feature = "modern_sqlite")]
+use crate::types::pointer::{free_pointer, POINTER_TYPE};
 use crate::types::{ToSqlOutput, ValueRef};
 #[cfg(feature = "array")]
 use crate::vtab::array::{free_array, ARRAY_TYPE};
@@ -34,6 +36,20 @@ pub(super) unsafe fn set_result(ctx: *mut sqlite3_context, result: &ToSqlOutput<
                 Some(free_array),
             );
         }
+        #[cfg(feature = "array")]
+        ToSqlOutput::ArrayData(ref a) => {
+            let (a, kind, destructor) = a.clone().into_raw();
+            return ffi::sqlite3_result_pointer(ctx, a, kind, Some(destructor));
+        }
+        #[cfg(feature = "modern_sqlite")]
+        ToSqlOutput::Pointer(ref p) => {
+            return ffi::sqlite3_result_pointer(
+                ctx,
+                p.clone().into_raw(),
+                POINTER_TYPE,
+                Some(free_pointer),
+            );
+        }
     };
 
     match value {
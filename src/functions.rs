@@ -53,9 +53,14 @@
 //! }
 //! ```
 use std::any::Any;
-use std::os::raw::{c_int, c_void};
-use std::panic::{catch_unwind, RefUnwindSafe, UnwindSafe};
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::os::raw::{c_int, c_uint, c_void};
+use std::panic::{catch_unwind, AssertUnwindSafe, RefUnwindSafe, UnwindSafe};
 use std::ptr;
+#[cfg(feature = "modern_sqlite")]
+use std::rc::Rc;
 use std::slice;
 use std::sync::Arc;
 
@@ -64,10 +69,19 @@ use crate::ffi::sqlite3_context;
 use crate::ffi::sqlite3_value;
 
 use crate::context::set_result;
+#[cfg(feature = "modern_sqlite")]
+use crate::types::pointer::POINTER_TYPE;
+#[cfg(feature = "modern_sqlite")]
+use crate::types::PointerValue;
 use crate::types::{FromSql, FromSqlError, ToSql, ValueRef};
 
 use crate::{str_to_cstring, Connection, Error, InnerConnection, Result};
 
+#[cfg(feature = "regexp")]
+pub mod regexp;
+#[cfg(feature = "stats")]
+pub mod stats;
+
 unsafe fn report_error(ctx: *mut sqlite3_context, err: &Error) {
     // Extended constraint error codes were added in SQLite 3.7.16. We don't have
     // an explicit feature check for that, and this doesn't really warrant one.
@@ -98,6 +112,16 @@ unsafe fn report_error(ctx: *mut sqlite3_context, err: &Error) {
     }
 }
 
+// The subtype must be set after the result value.
+#[cfg(feature = "modern_sqlite")] // 3.9.0
+unsafe fn set_result_subtype(ctx: *mut sqlite3_context, sub_type: Option<c_uint>) {
+    if let Some(sub_type) = sub_type {
+        ffi::sqlite3_result_subtype(ctx, sub_type);
+    }
+}
+#[cfg(not(feature = "modern_sqlite"))]
+unsafe fn set_result_subtype(_: *mut sqlite3_context, _: Option<c_uint>) {}
+
 unsafe extern "C" fn free_boxed_value<T>(p: *mut c_void) {
     drop(Box::from_raw(p as *mut T));
 }
@@ -107,6 +131,7 @@ unsafe extern "C" fn free_boxed_value<T>(p: *mut c_void) {
 pub struct Context<'a> {
     ctx: *mut sqlite3_context,
     args: &'a [*mut sqlite3_value],
+    result_subtype: Cell<Option<c_uint>>,
 }
 
 impl Context<'_> {
@@ -163,6 +188,84 @@ impl Context<'_> {
         unsafe { ValueRef::from_value(arg) }
     }
 
+    /// Returns the `idx`th argument converted to text like SQLite does (e.g.
+    /// `1.0` for the real 1), `None` if it is `NULL`.
+    #[cfg(any(feature = "regexp", feature = "stats"))]
+    pub(crate) fn get_text(&self, idx: usize) -> Result<Option<&str>> {
+        let bytes = match unsafe { ValueRef::text_from_value(self.args[idx])? } {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+        std::str::from_utf8(bytes).map(Some).map_err(|err| {
+            Error::FromSqlConversionFailure(idx, crate::types::Type::Text, Box::new(err))
+        })
+    }
+
+    /// Returns the connection executing the function, for example to run
+    /// other queries.
+    ///
+    /// The returned connection does not own the SQLite handle: dropping it
+    /// finalizes the statements it prepared and unregisters the hooks and
+    /// handlers set through it, but never closes the database. Querying and
+    /// modifying other tables is safe; the statement running the function
+    /// may fail with `SQLITE_LOCKED` or `SQLITE_BUSY` if the schema or this
+    /// function is changed meanwhile (for example by `DROP TABLE` or
+    /// [`create_scalar_function`](Connection::create_scalar_function) with
+    /// the same name).
+    ///
+    /// # Failure
+    ///
+    /// Will return Err if the connection cannot be wrapped.
+    pub fn get_connection(&self) -> Result<ConnectionRef<'_>> {
+        let conn = unsafe { Connection::from_handle(ffi::sqlite3_context_db_handle(self.ctx))? };
+        Ok(ConnectionRef {
+            conn,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Returns the subtype of the `idx`th argument, `0` if it has none.
+    ///
+    /// The function should be registered with
+    /// [`FunctionFlags::SQLITE_SUBTYPE`].
+    ///
+    /// # Failure
+    ///
+    /// Will panic if `idx` is greater than or equal to [`self.len()`](Context::len).
+    #[cfg(feature = "modern_sqlite")] // 3.9.0
+    #[inline]
+    pub fn get_subtype(&self, idx: usize) -> c_uint {
+        let arg = self.args[idx];
+        unsafe { ffi::sqlite3_value_subtype(arg) }
+    }
+
+    /// Sets the subtype of the value returned by a scalar function, for
+    /// example `74` (`'J'`) to have JSON text recognized by the JSON
+    /// functions. Only the lower 8 bits are kept.
+    #[cfg(feature = "modern_sqlite")] // 3.9.0
+    #[inline]
+    pub fn set_result_subtype(&self, sub_type: c_uint) {
+        self.result_subtype.set(Some(sub_type));
+    }
+
+    /// Returns the `idx`th argument as a Rust object passed by pointer (see
+    /// [`PointerValue`](crate::types::PointerValue)).
+    ///
+    /// Returns `None` if the argument is not a pointer with the given `tag`
+    /// to a `T`.
+    ///
+    /// # Failure
+    ///
+    /// Will panic if `idx` is greater than or equal to [`self.len()`](Context::len).
+    #[cfg(feature = "modern_sqlite")] // 3.20.0
+    pub fn get_pointer<T: 'static>(&self, idx: usize, tag: &str) -> Option<Rc<T>> {
+        let arg = self.args[idx];
+        unsafe {
+            let p = ffi::sqlite3_value_pointer(arg, POINTER_TYPE);
+            PointerValue::from_raw(p, tag)
+        }
+    }
+
     /// Fetch or insert the auxilliary data associated with a particular
     /// parameter. This is intended to be an easier-to-use way of fetching it
     /// compared to calling [`get_aux`](Context::get_aux) and [`set_aux`](Context::set_aux) separately.
@@ -224,6 +327,23 @@ impl Context<'_> {
 
 type AuxInner = Arc<dyn Any + Send + Sync + 'static>;
 
+/// `feature = "functions"` Connection executing a user-defined function, see
+/// [`Context::get_connection`].
+pub struct ConnectionRef<'ctx> {
+    // non-owning, from `Connection::from_handle`
+    conn: Connection,
+    phantom: PhantomData<&'ctx Context<'ctx>>,
+}
+
+impl Deref for ConnectionRef<'_> {
+    type Target = Connection;
+
+    #[inline]
+    fn deref(&self) -> &Connection {
+        &self.conn
+    }
+}
+
 /// `feature = "functions"` Aggregate is the callback interface for user-defined
 /// aggregate function.
 ///
@@ -267,6 +387,152 @@ where
     fn inverse(&self, _: &mut Context<'_>, _: &mut A) -> Result<()>;
 }
 
+/// `feature = "functions"` TypedFunction is implemented by closures taking up
+/// to 8 arguments of types implementing [`FromSql`], optionally followed by a
+/// `&[ValueRef<'_>]` receiving any remaining arguments, and returning a
+/// `Result<T>`.
+///
+/// `Args` is the tuple of argument types, ending with [`VariadicArgs`] for
+/// variadic functions. See
+/// [`create_typed_function`](Connection::create_typed_function).
+pub trait TypedFunction<Args, T> {
+    /// Number of arguments, `-1` for a variadic function.
+    const N_ARG: c_int;
+
+    /// Converts the arguments of `ctx` and calls the function.
+    fn call(&mut self, ctx: &Context<'_>) -> Result<T>;
+}
+
+/// `feature = "functions"` AggregateStep is implemented by closures taking
+/// the aggregation state `&mut A` followed by arguments like a
+/// [`TypedFunction`], and returning a `Result<()>`.
+///
+/// See [`create_typed_aggregate`](Connection::create_typed_aggregate).
+pub trait AggregateStep<A, Args> {
+    /// Number of arguments, `-1` for a variadic function.
+    const N_ARG: c_int;
+
+    /// Converts the arguments of `ctx` and calls the step function.
+    fn call(&self, ctx: &Context<'_>, acc: &mut A) -> Result<()>;
+}
+
+/// `feature = "functions"` Marker for the trailing `&[ValueRef<'_>]` argument
+/// of a variadic [`TypedFunction`] or [`AggregateStep`].
+#[derive(Debug)]
+pub enum VariadicArgs {}
+
+macro_rules! typed_function {
+    ($n:literal $(, $a:ident $i:tt)*) => {
+        impl<F, T, $($a,)*> TypedFunction<($($a,)*), T> for F
+        where
+            F: FnMut($($a),*) -> Result<T>,
+            $($a: FromSql,)*
+        {
+            const N_ARG: c_int = $n;
+
+            #[inline]
+            #[allow(unused_variables)]
+            fn call(&mut self, ctx: &Context<'_>) -> Result<T> {
+                self($(ctx.get::<$a>($i)?),*)
+            }
+        }
+
+        impl<F, T, $($a,)*> TypedFunction<($($a,)* VariadicArgs,), T> for F
+        where
+            F: for<'a> FnMut($($a,)* &[ValueRef<'a>]) -> Result<T>,
+            $($a: FromSql,)*
+        {
+            const N_ARG: c_int = -1;
+
+            #[allow(unused_comparisons)]
+            fn call(&mut self, ctx: &Context<'_>) -> Result<T> {
+                if ctx.len() < $n {
+                    return Err(Error::InvalidParameterCount(ctx.len(), $n));
+                }
+                let rest: Vec<ValueRef<'_>> = ($n..ctx.len()).map(|i| ctx.get_raw(i)).collect();
+                self($(ctx.get::<$a>($i)?,)* &rest)
+            }
+        }
+
+        impl<F, Acc, $($a,)*> AggregateStep<Acc, ($($a,)*)> for F
+        where
+            F: Fn(&mut Acc, $($a),*) -> Result<()>,
+            $($a: FromSql,)*
+        {
+            const N_ARG: c_int = $n;
+
+            #[inline]
+            #[allow(unused_variables)]
+            fn call(&self, ctx: &Context<'_>, acc: &mut Acc) -> Result<()> {
+                self(acc, $(ctx.get::<$a>($i)?),*)
+            }
+        }
+
+        impl<F, Acc, $($a,)*> AggregateStep<Acc, ($($a,)* VariadicArgs,)> for F
+        where
+            F: for<'a> Fn(&mut Acc, $($a,)* &[ValueRef<'a>]) -> Result<()>,
+            $($a: FromSql,)*
+        {
+            const N_ARG: c_int = -1;
+
+            #[allow(unused_comparisons)]
+            fn call(&self, ctx: &Context<'_>, acc: &mut Acc) -> Result<()> {
+                if ctx.len() < $n {
+                    return Err(Error::InvalidParameterCount(ctx.len(), $n));
+                }
+                let rest: Vec<ValueRef<'_>> = ($n..ctx.len()).map(|i| ctx.get_raw(i)).collect();
+                self(acc, $(ctx.get::<$a>($i)?,)* &rest)
+            }
+        }
+    };
+}
+
+/// Aggregate built from closures by
+/// [`create_typed_aggregate`](Connection::create_typed_aggregate).
+struct ClosureAggregate<I, S, F, Args> {
+    init: I,
+    step: S,
+    finalize: F,
+    phantom: PhantomData<fn(Args)>,
+}
+
+// A panic may leave the state inconsistent, but it is owned by this invocation
+// and only seen again by `finalize` (called by SQLite to clean up), so this is
+// a logic error at worst.
+impl<A, T, I, S, F, Args> Aggregate<AssertUnwindSafe<A>, T> for ClosureAggregate<I, S, F, Args>
+where
+    I: Fn() -> A,
+    S: AggregateStep<A, Args>,
+    F: Fn(A) -> Result<T>,
+    T: ToSql,
+{
+    fn init(&self) -> AssertUnwindSafe<A> {
+        AssertUnwindSafe((self.init)())
+    }
+
+    fn step(&self, ctx: &mut Context<'_>, acc: &mut AssertUnwindSafe<A>) -> Result<()> {
+        self.step.call(ctx, &mut acc.0)
+    }
+
+    fn finalize(&self, acc: Option<AssertUnwindSafe<A>>) -> Result<T> {
+        let acc = match acc {
+            Some(acc) => acc.0,
+            None => (self.init)(),
+        };
+        (self.finalize)(acc)
+    }
+}
+
+typed_function!(0);
+typed_function!(1, A0 0);
+typed_function!(2, A0 0, A1 1);
+typed_function!(3, A0 0, A1 1, A2 2);
+typed_function!(4, A0 0, A1 1, A2 2, A3 3);
+typed_function!(5, A0 0, A1 1, A2 2, A3 3, A4 4);
+typed_function!(6, A0 0, A1 1, A2 2, A3 3, A4 4, A5 5);
+typed_function!(7, A0 0, A1 1, A2 2, A3 3, A4 4, A5 5, A6 6);
+typed_function!(8, A0 0, A1 1, A2 2, A3 3, A4 4, A5 5, A6 6, A7 7);
+
 bitflags::bitflags! {
     /// Function Flags.
     /// See [sqlite3_create_function](https://sqlite.org/c3ref/create_function.html)
@@ -353,6 +619,55 @@ impl Connection {
             .create_scalar_function(fn_name, n_arg, flags, x_func)
     }
 
+    /// `feature = "functions"` Attach a user-defined scalar function whose
+    /// arguments are converted according to the closure signature.
+    ///
+    /// The number of arguments is inferred from the closure, which may end with
+    /// a `&[ValueRef<'_>]` argument to accept any number of additional
+    /// arguments (see [`TypedFunction`]). An argument which cannot be
+    /// converted is reported as [`Error::InvalidFunctionParameterType`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rusqlite::{Connection, Result};
+    /// # use rusqlite::functions::FunctionFlags;
+    /// # use rusqlite::types::ValueRef;
+    /// fn typed_function_example(db: Connection) -> Result<()> {
+    ///     db.create_typed_function(
+    ///         "repeat",
+    ///         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+    ///         |n: i64, s: Option<String>| Ok(s.map(|s| s.repeat(n as usize))),
+    ///     )?;
+    ///     db.create_typed_function(
+    ///         "count_args",
+    ///         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+    ///         |rest: &[ValueRef<'_>]| Ok(rest.len() as i64),
+    ///     )?;
+    ///
+    ///     let repeated: String = db.query_row("SELECT repeat(2, 'ab')", [], |r| r.get(0))?;
+    ///     assert_eq!(repeated, "abab");
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Failure
+    ///
+    /// Will return Err if the function could not be attached to the connection.
+    #[inline]
+    pub fn create_typed_function<'c, F, Args, T>(
+        &'c self,
+        fn_name: &str,
+        flags: FunctionFlags,
+        mut x_func: F,
+    ) -> Result<()>
+    where
+        F: TypedFunction<Args, T> + Send + UnwindSafe + 'c,
+        T: ToSql,
+    {
+        self.create_scalar_function(fn_name, F::N_ARG, flags, move |ctx| x_func.call(ctx))
+    }
+
     /// `feature = "functions"` Attach a user-defined aggregate function to this
     /// database connection.
     ///
@@ -377,6 +692,74 @@ impl Connection {
             .create_aggregate_function(fn_name, n_arg, flags, aggr)
     }
 
+    /// `feature = "functions"` Attach a user-defined aggregate function built
+    /// from closures.
+    ///
+    /// `init` creates the aggregation state, `step` is called for each row
+    /// with the state and the arguments converted according to its signature
+    /// (see [`AggregateStep`]), and `finalize` computes the result from the
+    /// state, given the result of `init` if there is no row. The state does not
+    /// need to be unwind safe: if a closure panics, the statement fails with
+    /// [`Error::UnwindingPanic`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rusqlite::{Connection, Result};
+    /// # use rusqlite::functions::FunctionFlags;
+    /// fn typed_aggregate_example(db: Connection) -> Result<()> {
+    ///     db.create_typed_aggregate(
+    ///         "product",
+    ///         FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+    ///         || 1i64,
+    ///         |acc: &mut i64, x: i64| {
+    ///             *acc *= x;
+    ///             Ok(())
+    ///         },
+    ///         Ok,
+    ///     )?;
+    ///
+    ///     let product: i64 = db.query_row(
+    ///         "SELECT product(column1) FROM (VALUES (2), (3), (7))",
+    ///         [],
+    ///         |r| r.get(0),
+    ///     )?;
+    ///     assert_eq!(product, 42);
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Failure
+    ///
+    /// Will return Err if the function could not be attached to the connection.
+    #[inline]
+    pub fn create_typed_aggregate<'c, A, I, S, Args, F, T>(
+        &'c self,
+        fn_name: &str,
+        flags: FunctionFlags,
+        init: I,
+        step: S,
+        finalize: F,
+    ) -> Result<()>
+    where
+        I: Fn() -> A + Send + 'c,
+        S: AggregateStep<A, Args> + Send + 'c,
+        F: Fn(A) -> Result<T> + Send + 'c,
+        T: ToSql,
+    {
+        self.create_aggregate_function(
+            fn_name,
+            S::N_ARG,
+            flags,
+            ClosureAggregate {
+                init,
+                step,
+                finalize,
+                phantom: PhantomData,
+            },
+        )
+    }
+
     /// `feature = "window"` Attach a user-defined aggregate window function to
     /// this database connection.
     ///
@@ -442,10 +825,11 @@ impl InnerConnection {
                 let ctx = Context {
                     ctx,
                     args: slice::from_raw_parts(argv, argc as usize),
+                    result_subtype: Cell::new(None),
                 };
-                (*boxed_f)(&ctx)
+                ((*boxed_f)(&ctx), ctx.result_subtype.get())
             });
-            let t = match r {
+            let (t, sub_type) = match r {
                 Err(_) => {
                     report_error(ctx, &Error::UnwindingPanic);
                     return;
@@ -455,7 +839,10 @@ impl InnerConnection {
             let t = t.as_ref().map(|t| ToSql::to_sql(t));
 
             match t {
-                Ok(Ok(ref value)) => set_result(ctx, value),
+                Ok(Ok(ref value)) => {
+                    set_result(ctx, value);
+                    set_result_subtype(ctx, sub_type);
+                }
                 Ok(Err(err)) => report_error(ctx, &err),
                 Err(err) => report_error(ctx, err),
             }
@@ -597,6 +984,7 @@ unsafe extern "C" fn call_boxed_step<A, D, T>(
         let mut ctx = Context {
             ctx,
             args: slice::from_raw_parts(argv, argc as usize),
+            result_subtype: Cell::new(None),
         };
         (*boxed_aggr).step(&mut ctx, &mut **pac)
     });
@@ -640,6 +1028,7 @@ unsafe extern "C" fn call_boxed_inverse<A, W, T>(
         let mut ctx = Context {
             ctx,
             args: slice::from_raw_parts(argv, argc as usize),
+            result_subtype: Cell::new(None),
         };
         (*boxed_aggr).inverse(&mut ctx, &mut **pac)
     });
@@ -752,6 +1141,7 @@ mod test {
     #[cfg(feature = "window")]
     use crate::functions::WindowAggregate;
     use crate::functions::{Aggregate, Context, FunctionFlags};
+    use crate::types::ValueRef;
     use crate::{Connection, Error, Result};
 
     fn half(ctx: &Context<'_>) -> Result<c_double> {
@@ -879,6 +1269,257 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_typed_function() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.create_typed_function("answer", FunctionFlags::SQLITE_UTF8, || Ok(42))?;
+        db.create_typed_function(
+            "repeat",
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |n: i64, s: Option<String>| Ok(s.map(|s| s.repeat(n as usize))),
+        )?;
+        db.create_typed_function(
+            "join_args",
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |sep: String, rest: &[ValueRef<'_>]| {
+                let parts = rest
+                    .iter()
+                    .map(|v| v.as_str().map_err(|e| Error::UserFunctionError(e.into())))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(parts.join(&sep))
+            },
+        )?;
+
+        let answer: i64 = db.query_row("SELECT answer()", [], |r| r.get(0))?;
+        assert_eq!(42, answer);
+        let repeated: Option<String> = db.query_row("SELECT repeat(3, 'ab')", [], |r| r.get(0))?;
+        assert_eq!(Some("ababab".to_owned()), repeated);
+        let repeated: Option<String> = db.query_row("SELECT repeat(3, NULL)", [], |r| r.get(0))?;
+        assert_eq!(None, repeated);
+        let joined: String =
+            db.query_row("SELECT join_args('-', 'a', 'b', 'c')", [], |r| r.get(0))?;
+        assert_eq!("a-b-c", joined);
+        let joined: String = db.query_row("SELECT join_args(',')", [], |r| r.get(0))?;
+        assert_eq!("", joined);
+
+        // arity is enforced by SQLite
+        assert!(db.prepare("SELECT repeat(1)").is_err());
+        let err = db
+            .query_row("SELECT join_args()", [], |r| r.get::<_, String>(0))
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("Wrong number of parameters"),
+            "{}",
+            err
+        );
+        let err = db
+            .query_row("SELECT repeat('x', 'y')", [], |r| r.get::<_, String>(0))
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("Invalid function parameter type"),
+            "{}",
+            err
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "modern_sqlite")]
+    fn test_subtype() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.create_scalar_function("to_json", 1, FunctionFlags::SQLITE_UTF8, |ctx| {
+            ctx.set_result_subtype(74);
+            ctx.get::<String>(0)
+        })?;
+        db.create_scalar_function(
+            "subtype",
+            1,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_SUBTYPE,
+            |ctx| Ok(ctx.get_subtype(0)),
+        )?;
+        let json: String = db.query_row(
+            "SELECT json_array(to_json('{\"a\":1}'), '{\"a\":1}')",
+            [],
+            |r| r.get(0),
+        )?;
+        assert_eq!(r#"[{"a":1},"{\"a\":1}"]"#, json);
+        let sub_types: (u32, u32) =
+            db.query_row("SELECT subtype(to_json('1')), subtype('1')", [], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })?;
+        assert_eq!((74, 0), sub_types);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "modern_sqlite")]
+    fn test_pointer() -> Result<()> {
+        use crate::types::PointerValue;
+        use std::collections::HashSet;
+
+        let db = Connection::open_in_memory()?;
+        db.create_scalar_function("make_set", -1, FunctionFlags::SQLITE_UTF8, |ctx| {
+            let set = (0..ctx.len())
+                .map(|i| ctx.get::<i64>(i))
+                .collect::<Result<HashSet<_>>>()?;
+            Ok(PointerValue::new("set", set))
+        })?;
+        db.create_scalar_function("set_contains", 2, FunctionFlags::SQLITE_UTF8, |ctx| {
+            Ok(ctx
+                .get_pointer::<HashSet<i64>>(0, "set")
+                .map(|set| set.contains(&ctx.get(1).unwrap())))
+        })?;
+        let contains: (Option<bool>, Option<bool>) = db.query_row(
+            "SELECT set_contains(make_set(1, 2, 3), 2), set_contains(make_set(1), 2)",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )?;
+        assert_eq!((Some(true), Some(false)), contains);
+
+        // wrong tag, wrong type or no pointer at all
+        let p = PointerValue::new("other", HashSet::<i64>::new());
+        let contains: Option<bool> =
+            db.query_row("SELECT set_contains(?, 1)", [&p], |r| r.get(0))?;
+        assert_eq!(None, contains);
+        let p = PointerValue::new("set", vec![1i64]);
+        let contains: Option<bool> =
+            db.query_row("SELECT set_contains(?, 1)", [&p], |r| r.get(0))?;
+        assert_eq!(None, contains);
+        let contains: Option<bool> = db.query_row("SELECT set_contains(1, 1)", [], |r| r.get(0))?;
+        assert_eq!(None, contains);
+        let p = PointerValue::new("set", [1i64].iter().cloned().collect::<HashSet<_>>());
+        let contains: Option<bool> =
+            db.query_row("SELECT set_contains(?, 1)", [&p], |r| r.get(0))?;
+        assert_eq!(Some(true), contains);
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_connection() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "CREATE TABLE config (key TEXT PRIMARY KEY, value);
+             INSERT INTO config VALUES ('answer', 42);
+             CREATE TABLE log (key TEXT);",
+        )?;
+        db.create_scalar_function("lookup_config", 1, FunctionFlags::SQLITE_UTF8, |ctx| {
+            let key = ctx.get::<String>(0)?;
+            let conn = ctx.get_connection()?;
+            conn.execute("INSERT INTO log VALUES (?)", [&key])?;
+            let mut stmt = conn.prepare_cached("SELECT value FROM config WHERE key = ?")?;
+            let value = stmt.query_row([&key], |r| r.get::<_, Option<i64>>(0));
+            match value {
+                Err(Error::QueryReturnedNoRows) => Ok(None),
+                value => value,
+            }
+        })?;
+        #[cfg(feature = "hooks")]
+        let committed = {
+            use std::sync::atomic::{AtomicUsize, Ordering};
+            use std::sync::Arc;
+            let committed = Arc::new(AtomicUsize::new(0));
+            let c = committed.clone();
+            db.commit_hook(Some(move || {
+                c.fetch_add(1, Ordering::SeqCst);
+                false
+            }));
+            move || committed.load(Ordering::SeqCst)
+        };
+
+        let values: (Option<i64>, Option<i64>) = db.query_row(
+            "SELECT lookup_config('answer'), lookup_config('missing')",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )?;
+        assert_eq!((Some(42), None), values);
+        let logged: i64 = db.query_row("SELECT count(*) FROM log", [], |r| r.get(0))?;
+        assert_eq!(2, logged);
+        // hooks of the owning connection are kept
+        #[cfg(feature = "hooks")]
+        {
+            let before = committed();
+            db.execute("INSERT INTO log VALUES ('x')", [])?;
+            assert_eq!(before + 1, committed());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_typed_aggregate() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "CREATE TABLE t (x INTEGER, s TEXT);
+             INSERT INTO t VALUES (1, 'a'), (2, NULL), (3, 'c');",
+        )?;
+        let separator = String::from(", ");
+        db.create_typed_aggregate(
+            "join_text",
+            FunctionFlags::SQLITE_UTF8,
+            Vec::new,
+            |acc: &mut Vec<String>, s: Option<String>| {
+                acc.extend(s);
+                Ok(())
+            },
+            move |acc| Ok(acc.join(&separator)),
+        )?;
+        db.create_typed_aggregate(
+            "weighted_sum",
+            FunctionFlags::SQLITE_UTF8,
+            || 0.0,
+            |acc: &mut f64, x: f64, weight: f64| {
+                *acc += x * weight;
+                Ok(())
+            },
+            Ok,
+        )?;
+        db.create_typed_aggregate(
+            "count_args",
+            FunctionFlags::SQLITE_UTF8,
+            || 0,
+            |acc: &mut usize, rest: &[ValueRef<'_>]| {
+                *acc += rest.iter().filter(|v| **v != ValueRef::Null).count();
+                Ok(())
+            },
+            |acc| Ok(acc as i64),
+        )?;
+        db.create_typed_aggregate(
+            "panicking",
+            FunctionFlags::SQLITE_UTF8,
+            || (),
+            |_: &mut (), x: i64| {
+                if x > 1 {
+                    panic!("x > 1");
+                }
+                Ok(())
+            },
+            |_| Ok(0),
+        )?;
+
+        let joined: String = db.query_row("SELECT join_text(s) FROM t", [], |r| r.get(0))?;
+        assert_eq!("a, c", joined);
+        let joined: String =
+            db.query_row("SELECT join_text(s) FROM t WHERE 0", [], |r| r.get(0))?;
+        assert_eq!("", joined);
+        let sum: f64 = db.query_row("SELECT weighted_sum(x, 0.5) FROM t", [], |r| r.get(0))?;
+        assert!((sum - 3.0).abs() < EPSILON);
+        let count: i64 = db.query_row("SELECT count_args(x, s, 1) FROM t", [], |r| r.get(0))?;
+        assert_eq!(8, count);
+        let err = db
+            .query_row("SELECT weighted_sum(s, 1) FROM t", [], |r| {
+                r.get::<_, f64>(0)
+            })
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("Invalid function parameter type"),
+            "{}",
+            err
+        );
+        assert!(db
+            .query_row("SELECT panicking(x) FROM t", [], |r| r.get::<_, i64>(0))
+            .is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_get_aux_type_checking() -> Result<()> {
         let db = Connection::open_in_memory()?;
@@ -0,0 +1,208 @@
+//! `feature = "regexp"` Regular expression and text functions.
+//!
+//! [`register_functions`] adds:
+//! * `regexp(PATTERN, TEXT)`, which implements the `TEXT REGEXP PATTERN`
+//!   operator,
+//! * `regexp_replace(TEXT, PATTERN, REPLACEMENT)`, replacing all matches,
+//!   where `$1` or `${name}` in `REPLACEMENT` refers to a capture group,
+//! * `regexp_capture(TEXT, PATTERN[, GROUP])`, the text matched by the capture
+//!   group `GROUP` (an index or a name, `0` by default for the whole match) in
+//!   the first match, or `NULL`,
+//! * `upper(X)` and `lower(X)`, overriding the built-in functions with
+//!   Unicode case mapping for text values.
+//!
+//! The syntax of patterns is the one of the [`regex`](https://docs.rs/regex)
+//! crate. Compiled patterns are cached while a statement runs, and functions
+//! return `NULL` when `TEXT` or `PATTERN` is `NULL`.
+//!
+//! ```rust
+//! # use rusqlite::{Connection, Result};
+//! # use rusqlite::functions::regexp;
+//! fn example(db: &Connection) -> Result<i64> {
+//!     regexp::register_functions(db)?;
+//!     db.query_row(
+//!         "SELECT count(*) FROM sqlite_master WHERE name REGEXP '^[a-z_]+$'",
+//!         [],
+//!         |r| r.get(0),
+//!     )
+//! }
+//! ```
+use std::os::raw::c_int;
+use std::str;
+use std::sync::Arc;
+
+use regex::Regex;
+
+use super::{Context, FunctionFlags};
+use crate::types::ValueRef;
+use crate::{Connection, Error, Result};
+
+/// `feature = "regexp"` Register the regular expression and text functions.
+pub fn register_functions(conn: &Connection) -> Result<()> {
+    let flags = FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC;
+    conn.create_scalar_function("regexp", 2, flags, |ctx| {
+        Ok(match (regex(ctx, 0)?, ctx.get_text(1)?) {
+            (Some(re), Some(text)) => Some(re.is_match(text)),
+            _ => None,
+        })
+    })?;
+    conn.create_scalar_function("regexp_replace", 3, flags, |ctx| {
+        Ok(match (ctx.get_text(0)?, regex(ctx, 1)?, ctx.get_text(2)?) {
+            (Some(text), Some(re), Some(replacement)) => {
+                Some(re.replace_all(text, replacement).into_owned())
+            }
+            _ => None,
+        })
+    })?;
+    for &n_arg in &[2, 3] {
+        conn.create_scalar_function("regexp_capture", n_arg, flags, regexp_capture)?;
+    }
+    conn.create_scalar_function("upper", 1, flags, |ctx| change_case(ctx, str::to_uppercase))?;
+    conn.create_scalar_function("lower", 1, flags, |ctx| change_case(ctx, str::to_lowercase))
+}
+
+/// Returns the `idx`th argument compiled, `None` if it is `NULL`.
+fn regex(ctx: &Context<'_>, idx: c_int) -> Result<Option<Arc<Regex>>> {
+    if let ValueRef::Null = ctx.get_raw(idx as usize) {
+        return Ok(None);
+    }
+    ctx.get_or_create_aux(
+        idx,
+        |vr| -> Result<_, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(Regex::new(vr.as_str()?)?)
+        },
+    )
+    .map(Some)
+}
+
+fn regexp_capture(ctx: &Context<'_>) -> Result<Option<String>> {
+    let (text, re) = match (ctx.get_text(0)?, regex(ctx, 1)?) {
+        (Some(text), Some(re)) => (text, re),
+        _ => return Ok(None),
+    };
+    let captures = match re.captures(text) {
+        Some(captures) => captures,
+        None => return Ok(None),
+    };
+    let group = if ctx.len() > 2 {
+        ctx.get_raw(2)
+    } else {
+        ValueRef::Integer(0)
+    };
+    let m = match group {
+        ValueRef::Null => return Ok(None),
+        ValueRef::Integer(i) if i >= 0 => captures.get(i as usize),
+        ValueRef::Text(_) => captures.name(group.as_str()?),
+        _ => {
+            return Err(Error::UserFunctionError(
+                "3rd argument to regexp_capture() is not a group index or name".into(),
+            ))
+        }
+    };
+    Ok(m.map(|m| m.as_str().to_owned()))
+}
+
+/// Like the builtin `upper` and `lower`, converts non-`NULL` arguments to
+/// text first.
+fn change_case(ctx: &Context<'_>, f: fn(&str) -> String) -> Result<Option<String>> {
+    Ok(ctx.get_text(0)?.map(f))
+}
+
+#[cfg(test)]
+mod test {
+    use super::register_functions;
+    use crate::{Connection, Result};
+
+    fn query(db: &Connection, sql: &str) -> Result<Option<String>> {
+        db.query_row(sql, [], |r| r.get(0))
+    }
+
+    #[test]
+    fn test_regexp() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        register_functions(&db)?;
+        db.execute_batch(
+            "CREATE TABLE t (x TEXT);
+             INSERT INTO t VALUES ('lisa'), ('lXsi'), ('lisX'), (NULL);",
+        )?;
+        let count: i64 = db.query_row(
+            "SELECT count(*) FROM t WHERE x REGEXP 'l.s[aeiouy]'",
+            [],
+            |r| r.get(0),
+        )?;
+        assert_eq!(2, count);
+        let is_match: Option<bool> = db.query_row("SELECT regexp('a', NULL)", [], |r| r.get(0))?;
+        assert_eq!(None, is_match);
+        let err = db
+            .query_row("SELECT 'a' REGEXP '('", [], |r| r.get::<_, bool>(0))
+            .unwrap_err();
+        assert!(err.to_string().contains("regex parse error"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_and_capture() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        register_functions(&db)?;
+        assert_eq!(
+            Some("2021/03/14, 2020/01/02".to_owned()),
+            query(
+                &db,
+                r"SELECT regexp_replace('14.03.2021, 02.01.2020',
+                    '(\d+)\.(\d+)\.(?P<y>\d+)', '${y}/$2/$1')"
+            )?
+        );
+        assert_eq!(None, query(&db, "SELECT regexp_replace(NULL, 'a', 'b')")?);
+        assert_eq!(
+            Some("b42".to_owned()),
+            query(&db, r"SELECT regexp_capture('a1 b42', '[b-z]\d+')")?
+        );
+        assert_eq!(
+            Some("1".to_owned()),
+            query(&db, r"SELECT regexp_capture('a1 b42', '[a-z](\d+)', 1)")?
+        );
+        assert_eq!(
+            Some("a".to_owned()),
+            query(
+                &db,
+                r"SELECT regexp_capture('a1 b42', '(?P<letter>[a-z])\d', 'letter')"
+            )?
+        );
+        assert_eq!(
+            None,
+            query(&db, r"SELECT regexp_capture('a1', '[a-z](\d+)', 2)")?
+        );
+        assert_eq!(None, query(&db, r"SELECT regexp_capture('abc', '\d')")?);
+        assert!(query(&db, r"SELECT regexp_capture('a1', '\d', -1)").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_change_case() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        register_functions(&db)?;
+        assert_eq!(
+            Some("STRASSE ÉTÉ".to_owned()),
+            query(&db, "SELECT upper('straße été')")?
+        );
+        assert_eq!(
+            Some("ὀδυσσεύς".to_owned()),
+            query(&db, "SELECT lower('ὈΔΥΣΣΕΎΣ')")?
+        );
+        assert_eq!(None, query(&db, "SELECT upper(NULL)")?);
+        let (s, t): (String, String) =
+            db.query_row("SELECT lower(42), typeof(lower(42))", [], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })?;
+        assert_eq!(("42".to_owned(), "text".to_owned()), (s, t));
+        assert_eq!(Some("1.0".to_owned()), query(&db, "SELECT upper(1.0)")?);
+        assert_eq!(Some("AB".to_owned()), query(&db, "SELECT upper(x'6162')")?);
+        assert_eq!(
+            Some("1.5".to_owned()),
+            query(&db, "SELECT regexp_capture(1.5, '.*')")?
+        );
+        let is_match: bool = db.query_row(r"SELECT 1042 REGEXP '^\d+$'", [], |r| r.get(0))?;
+        assert!(is_match);
+        Ok(())
+    }
+}
@@ -0,0 +1,725 @@
+//! `feature = "stats"` Statistical aggregate functions.
+//!
+//! [`register_functions`] adds the following aggregates, which are also
+//! window functions with the `window` feature:
+//! * `median(X)`,
+//! * `percentile(X, P)` with `P` between 0.0 and 100.0, the same for all rows,
+//!   interpolating linearly between the closest values,
+//! * `mode(X)`, the most frequent value (the smallest one if there are
+//!   several),
+//! * `var_samp(X)`, `var_pop(X)`, `stddev_samp(X)` and `stddev_pop(X)`,
+//! * `covar_samp(X, Y)` (also named `covar`), `covar_pop(X, Y)` and
+//!   `corr(X, Y)`, ignoring rows where `X` or `Y` is `NULL`,
+//! * `string_agg(X, SEP)` concatenating `X` values in input order, each but
+//!   the first preceded by its `SEP`, and `string_agg(X, SEP, KEY)`
+//!   concatenating them by increasing `KEY`.
+//!
+//! `NULL` values are ignored, and the result is `NULL` if there are not
+//! enough values.
+//!
+//! ```rust
+//! # use rusqlite::{Connection, Result};
+//! # use rusqlite::functions::stats;
+//! fn example(db: &Connection) -> Result<f64> {
+//!     stats::register_functions(db)?;
+//!     db.query_row(
+//!         "SELECT median(column1) FROM (VALUES (1), (3), (10))",
+//!         [],
+//!         |r| r.get(0),
+//!     )
+//! }
+//! ```
+#[cfg(feature = "window")]
+use std::cell::RefMut;
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::os::raw::c_int;
+use std::panic::RefUnwindSafe;
+
+#[cfg(feature = "window")]
+use super::WindowAggregate;
+use super::{Aggregate, Context, FunctionFlags};
+use crate::types::{ToSql, Value, ValueRef};
+use crate::{Connection, Error, Result};
+
+/// `feature = "stats"` Register the statistical aggregate functions.
+pub fn register_functions(conn: &Connection) -> Result<()> {
+    register(conn, "median", 1, Percentile(Some(50.0)))?;
+    register(conn, "percentile", 2, Percentile(None))?;
+    register(conn, "mode", 1, Mode)?;
+    register(conn, "var_samp", 1, Variance::SAMPLE)?;
+    register(conn, "var_pop", 1, Variance::POPULATION)?;
+    register(conn, "stddev_samp", 1, Variance::SAMPLE.sqrt())?;
+    register(conn, "stddev_pop", 1, Variance::POPULATION.sqrt())?;
+    register(conn, "covar", 2, Covariance::Sample)?;
+    register(conn, "covar_samp", 2, Covariance::Sample)?;
+    register(conn, "covar_pop", 2, Covariance::Population)?;
+    register(conn, "corr", 2, Covariance::Correlation)?;
+    register(conn, "string_agg", 2, StringAgg)?;
+    register(conn, "string_agg", 3, StringAgg)
+}
+
+fn flags() -> FunctionFlags {
+    FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC
+}
+
+#[cfg(feature = "window")]
+fn register<A, W, T>(conn: &Connection, name: &str, n_arg: c_int, aggr: W) -> Result<()>
+where
+    A: std::panic::RefUnwindSafe + std::panic::UnwindSafe,
+    W: WindowAggregate<A, T>,
+    T: ToSql,
+{
+    conn.create_window_function(name, n_arg, flags(), aggr)
+}
+
+#[cfg(not(feature = "window"))]
+fn register<A, D, T>(conn: &Connection, name: &str, n_arg: c_int, aggr: D) -> Result<()>
+where
+    A: std::panic::RefUnwindSafe + std::panic::UnwindSafe,
+    D: Aggregate<A, T>,
+    T: ToSql,
+{
+    conn.create_aggregate_function(name, n_arg, flags(), aggr)
+}
+
+fn user_error(msg: String) -> Error {
+    Error::UserFunctionError(msg.into())
+}
+
+/// Returns the `idx`th argument as a `f64`, `None` if it is `NULL`.
+fn get_f64(ctx: &Context<'_>, idx: usize) -> Result<Option<f64>> {
+    match ctx.get_raw(idx) {
+        ValueRef::Null => Ok(None),
+        _ => ctx.get(idx).map(Some),
+    }
+}
+
+// Same order as SQLite with the BINARY collation.
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    fn rank(v: &Value) -> u8 {
+        match v {
+            Value::Null => 0,
+            Value::Integer(_) | Value::Real(_) => 1,
+            Value::Text(_) => 2,
+            Value::Blob(_) => 3,
+        }
+    }
+    match (a, b) {
+        (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+        (Value::Integer(a), Value::Real(b)) => {
+            (*a as f64).partial_cmp(b).unwrap_or(Ordering::Equal)
+        }
+        (Value::Real(a), Value::Integer(b)) => {
+            a.partial_cmp(&(*b as f64)).unwrap_or(Ordering::Equal)
+        }
+        (Value::Real(a), Value::Real(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        (Value::Text(a), Value::Text(b)) => a.cmp(b),
+        (Value::Blob(a), Value::Blob(b)) => a.cmp(b),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Values and requested percentile.
+///
+/// Values are only kept sorted once the aggregate is used as a window
+/// function, where `inverse` has to find them: plain aggregates select the
+/// percentile in `finalize` instead.
+#[derive(Default)]
+struct Values {
+    p: Option<f64>,
+    values: RefCell<Vec<f64>>,
+    sorted: Cell<bool>,
+}
+
+// `values` and `sorted` are only updated together, and a panic while sorting
+// leaves a permutation of the same values behind.
+impl RefUnwindSafe for Values {}
+
+fn compare_f64(a: &f64, b: &f64) -> Ordering {
+    a.partial_cmp(b).unwrap_or(Ordering::Equal)
+}
+
+fn sorted_position(values: &[f64], x: f64) -> usize {
+    match values.binary_search_by(|v| compare_f64(v, &x)) {
+        Ok(i) | Err(i) => i,
+    }
+}
+
+impl Values {
+    /// Sorts the values, once, and returns them.
+    #[cfg(feature = "window")]
+    fn sorted(&self) -> RefMut<'_, Vec<f64>> {
+        let mut values = self.values.borrow_mut();
+        if !self.sorted.replace(true) {
+            values.sort_unstable_by(compare_f64);
+        }
+        values
+    }
+}
+
+/// Rank of the `p` percentile (the median by default) among `n > 0` values.
+fn rank(p: Option<f64>, n: usize) -> f64 {
+    (n - 1) as f64 * p.unwrap_or(50.0) / 100.0
+}
+
+/// Interpolates between the values at the closest ranks.
+fn interpolate(rank: f64, a: f64, b: f64) -> f64 {
+    a + (b - a) * (rank - rank.floor())
+}
+
+/// `median(X)` if the percentile is given, otherwise `percentile(X, P)`.
+struct Percentile(Option<f64>);
+
+impl Percentile {
+    fn percentile(&self, ctx: &Context<'_>, values: &mut Values) -> Result<()> {
+        if self.0.is_some() {
+            return Ok(());
+        }
+        let p = match get_f64(ctx, 1) {
+            Ok(Some(p)) if (0.0..=100.0).contains(&p) => p,
+            _ => {
+                return Err(user_error(
+                    "2nd argument to percentile() is not a number between 0.0 and 100.0".to_owned(),
+                ))
+            }
+        };
+        match values.p {
+            Some(previous) if (previous - p).abs() > f64::EPSILON => Err(user_error(
+                "2nd argument to percentile() is not the same for all input rows".to_owned(),
+            )),
+            _ => {
+                values.p = Some(p);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Aggregate<Values, Option<f64>> for Percentile {
+    fn init(&self) -> Values {
+        Values {
+            p: self.0,
+            ..Values::default()
+        }
+    }
+
+    fn step(&self, ctx: &mut Context<'_>, values: &mut Values) -> Result<()> {
+        self.percentile(ctx, values)?;
+        if let Some(x) = get_f64(ctx, 0)? {
+            let sorted = values.sorted.get();
+            let values = values.values.get_mut();
+            if sorted {
+                let i = sorted_position(values, x);
+                values.insert(i, x);
+            } else {
+                values.push(x);
+            }
+        }
+        Ok(())
+    }
+
+    fn finalize(&self, values: Option<Values>) -> Result<Option<f64>> {
+        Ok(values.and_then(|values| {
+            let p = values.p;
+            let mut values = values.values.into_inner();
+            if values.is_empty() {
+                return None;
+            }
+            let rank = rank(p, values.len());
+            let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+            let (_, &mut a, greater) = values.select_nth_unstable_by(lower, compare_f64);
+            let b = if upper == lower {
+                a
+            } else {
+                greater.iter().copied().min_by(compare_f64).unwrap_or(a)
+            };
+            Some(interpolate(rank, a, b))
+        }))
+    }
+}
+
+#[cfg(feature = "window")]
+impl WindowAggregate<Values, Option<f64>> for Percentile {
+    fn value(&self, values: Option<&Values>) -> Result<Option<f64>> {
+        Ok(values.and_then(|values| {
+            let sorted = values.sorted();
+            if sorted.is_empty() {
+                return None;
+            }
+            let rank = rank(values.p, sorted.len());
+            let (a, b) = (sorted[rank.floor() as usize], sorted[rank.ceil() as usize]);
+            Some(interpolate(rank, a, b))
+        }))
+    }
+
+    fn inverse(&self, ctx: &mut Context<'_>, values: &mut Values) -> Result<()> {
+        if let Some(x) = get_f64(ctx, 0)? {
+            let mut sorted = values.sorted();
+            let i = sorted_position(&sorted, x);
+            if i < sorted.len() {
+                sorted.remove(i);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `mode(X)`: distinct values, sorted, with their number of occurrences.
+struct Mode;
+
+fn position(counts: &[(Value, u64)], x: &Value) -> std::result::Result<usize, usize> {
+    counts.binary_search_by(|(v, _)| compare_values(v, x))
+}
+
+impl Aggregate<Vec<(Value, u64)>, Option<Value>> for Mode {
+    fn init(&self) -> Vec<(Value, u64)> {
+        Vec::new()
+    }
+
+    fn step(&self, ctx: &mut Context<'_>, counts: &mut Vec<(Value, u64)>) -> Result<()> {
+        let x = Value::from(ctx.get_raw(0));
+        if x == Value::Null {
+            return Ok(());
+        }
+        match position(counts, &x) {
+            Ok(i) => counts[i].1 += 1,
+            Err(i) => counts.insert(i, (x, 1)),
+        }
+        Ok(())
+    }
+
+    fn finalize(&self, counts: Option<Vec<(Value, u64)>>) -> Result<Option<Value>> {
+        self.result(counts.as_ref())
+    }
+}
+
+impl Mode {
+    fn result(&self, counts: Option<&Vec<(Value, u64)>>) -> Result<Option<Value>> {
+        Ok(counts.and_then(|counts| {
+            let mut mode: Option<&(Value, u64)> = None;
+            for entry in counts {
+                match mode {
+                    Some(mode) if mode.1 >= entry.1 => {}
+                    _ => mode = Some(entry),
+                }
+            }
+            mode.map(|(v, _)| v.clone())
+        }))
+    }
+}
+
+#[cfg(feature = "window")]
+impl WindowAggregate<Vec<(Value, u64)>, Option<Value>> for Mode {
+    fn value(&self, counts: Option<&Vec<(Value, u64)>>) -> Result<Option<Value>> {
+        self.result(counts)
+    }
+
+    fn inverse(&self, ctx: &mut Context<'_>, counts: &mut Vec<(Value, u64)>) -> Result<()> {
+        let x = Value::from(ctx.get_raw(0));
+        if let Ok(i) = position(counts, &x) {
+            counts[i].1 -= 1;
+            if counts[i].1 == 0 {
+                counts.remove(i);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Running count, means and co-moments of `(X, Y)` pairs (Welford's
+/// algorithm), supporting removal.
+#[derive(Clone, Copy, Default)]
+struct Moments {
+    n: u64,
+    mean_x: f64,
+    mean_y: f64,
+    m2_x: f64,
+    m2_y: f64,
+    c_xy: f64,
+}
+
+impl Moments {
+    fn add(&mut self, x: f64, y: f64) {
+        self.n += 1;
+        let n = self.n as f64;
+        let dx = x - self.mean_x;
+        let dy = y - self.mean_y;
+        self.mean_x += dx / n;
+        self.mean_y += dy / n;
+        self.m2_x += dx * (x - self.mean_x);
+        self.m2_y += dy * (y - self.mean_y);
+        self.c_xy += dx * (y - self.mean_y);
+    }
+
+    #[cfg(feature = "window")]
+    fn remove(&mut self, x: f64, y: f64) {
+        if self.n <= 1 {
+            *self = Moments::default();
+            return;
+        }
+        self.n -= 1;
+        let n = self.n as f64;
+        let mean_x = (self.mean_x * (n + 1.0) - x) / n;
+        let mean_y = (self.mean_y * (n + 1.0) - y) / n;
+        self.m2_x -= (x - mean_x) * (x - self.mean_x);
+        self.m2_y -= (y - mean_y) * (y - self.mean_y);
+        self.c_xy -= (x - mean_x) * (y - self.mean_y);
+        self.mean_x = mean_x;
+        self.mean_y = mean_y;
+    }
+
+    /// `m2 / (n - ddof)`, `None` without enough values.
+    fn normalize(&self, m2: f64, ddof: u64) -> Option<f64> {
+        if self.n > ddof {
+            Some(m2.max(0.0) / (self.n - ddof) as f64)
+        } else {
+            None
+        }
+    }
+}
+
+/// `var_samp(X)`, `var_pop(X)`, `stddev_samp(X)` and `stddev_pop(X)`.
+#[derive(Clone, Copy)]
+struct Variance {
+    ddof: u64,
+    sqrt: bool,
+}
+
+impl Variance {
+    const POPULATION: Variance = Variance {
+        ddof: 0,
+        sqrt: false,
+    };
+    const SAMPLE: Variance = Variance {
+        ddof: 1,
+        sqrt: false,
+    };
+
+    fn sqrt(self) -> Variance {
+        Variance { sqrt: true, ..self }
+    }
+
+    fn result(&self, moments: Option<&Moments>) -> Result<Option<f64>> {
+        Ok(moments
+            .and_then(|m| m.normalize(m.m2_x, self.ddof))
+            .map(|v| if self.sqrt { v.sqrt() } else { v }))
+    }
+}
+
+impl Aggregate<Moments, Option<f64>> for Variance {
+    fn init(&self) -> Moments {
+        Moments::default()
+    }
+
+    fn step(&self, ctx: &mut Context<'_>, moments: &mut Moments) -> Result<()> {
+        if let Some(x) = get_f64(ctx, 0)? {
+            moments.add(x, x);
+        }
+        Ok(())
+    }
+
+    fn finalize(&self, moments: Option<Moments>) -> Result<Option<f64>> {
+        self.result(moments.as_ref())
+    }
+}
+
+#[cfg(feature = "window")]
+impl WindowAggregate<Moments, Option<f64>> for Variance {
+    fn value(&self, moments: Option<&Moments>) -> Result<Option<f64>> {
+        self.result(moments)
+    }
+
+    fn inverse(&self, ctx: &mut Context<'_>, moments: &mut Moments) -> Result<()> {
+        if let Some(x) = get_f64(ctx, 0)? {
+            moments.remove(x, x);
+        }
+        Ok(())
+    }
+}
+
+/// `covar_samp(X, Y)`, `covar_pop(X, Y)` and `corr(X, Y)`.
+#[derive(Clone, Copy)]
+enum Covariance {
+    Population,
+    Sample,
+    Correlation,
+}
+
+impl Covariance {
+    fn pair(ctx: &Context<'_>) -> Result<Option<(f64, f64)>> {
+        Ok(match (get_f64(ctx, 0)?, get_f64(ctx, 1)?) {
+            (Some(x), Some(y)) => Some((x, y)),
+            _ => None,
+        })
+    }
+
+    fn result(&self, moments: Option<&Moments>) -> Result<Option<f64>> {
+        Ok(moments.and_then(|m| match self {
+            Covariance::Population => m.normalize(m.c_xy, 0),
+            Covariance::Sample => m.normalize(m.c_xy, 1),
+            Covariance::Correlation => {
+                let d = (m.m2_x.max(0.0) * m.m2_y.max(0.0)).sqrt();
+                if m.n > 1 && d > 0.0 {
+                    Some((m.c_xy / d).clamp(-1.0, 1.0))
+                } else {
+                    None
+                }
+            }
+        }))
+    }
+}
+
+impl Aggregate<Moments, Option<f64>> for Covariance {
+    fn init(&self) -> Moments {
+        Moments::default()
+    }
+
+    fn step(&self, ctx: &mut Context<'_>, moments: &mut Moments) -> Result<()> {
+        if let Some((x, y)) = Covariance::pair(ctx)? {
+            moments.add(x, y);
+        }
+        Ok(())
+    }
+
+    fn finalize(&self, moments: Option<Moments>) -> Result<Option<f64>> {
+        self.result(moments.as_ref())
+    }
+}
+
+#[cfg(feature = "window")]
+impl WindowAggregate<Moments, Option<f64>> for Covariance {
+    fn value(&self, moments: Option<&Moments>) -> Result<Option<f64>> {
+        self.result(moments)
+    }
+
+    fn inverse(&self, ctx: &mut Context<'_>, moments: &mut Moments) -> Result<()> {
+        if let Some((x, y)) = Covariance::pair(ctx)? {
+            moments.remove(x, y);
+        }
+        Ok(())
+    }
+}
+
+/// A `string_agg` value with its preceding separator and sort key.
+struct Part {
+    separator: String,
+    text: String,
+    key: Value,
+}
+
+/// `string_agg(X, SEP)` and `string_agg(X, SEP, KEY)`, rows are removed from
+/// the window in the order they were added.
+struct StringAgg;
+
+impl StringAgg {
+    fn result(&self, parts: Option<&VecDeque<Part>>) -> Result<Option<String>> {
+        let parts = match parts {
+            Some(parts) if !parts.is_empty() => parts,
+            _ => return Ok(None),
+        };
+        let mut sorted: Vec<&Part> = parts.iter().collect();
+        // stable, so that equal keys keep the input order
+        sorted.sort_by(|a, b| compare_values(&a.key, &b.key));
+        let mut s = String::new();
+        for (i, part) in sorted.into_iter().enumerate() {
+            if i > 0 {
+                s.push_str(&part.separator);
+            }
+            s.push_str(&part.text);
+        }
+        Ok(Some(s))
+    }
+}
+
+impl Aggregate<VecDeque<Part>, Option<String>> for StringAgg {
+    fn init(&self) -> VecDeque<Part> {
+        VecDeque::new()
+    }
+
+    fn step(&self, ctx: &mut Context<'_>, parts: &mut VecDeque<Part>) -> Result<()> {
+        let text = match ctx.get_text(0)? {
+            Some(text) => text.to_owned(),
+            None => return Ok(()),
+        };
+        let separator = match ctx.get_raw(1) {
+            ValueRef::Null => String::new(),
+            _ => ctx.get::<String>(1)?,
+        };
+        let key = if ctx.len() > 2 {
+            Value::from(ctx.get_raw(2))
+        } else {
+            Value::Null
+        };
+        parts.push_back(Part {
+            separator,
+            text,
+            key,
+        });
+        Ok(())
+    }
+
+    fn finalize(&self, parts: Option<VecDeque<Part>>) -> Result<Option<String>> {
+        self.result(parts.as_ref())
+    }
+}
+
+#[cfg(feature = "window")]
+impl WindowAggregate<VecDeque<Part>, Option<String>> for StringAgg {
+    fn value(&self, parts: Option<&VecDeque<Part>>) -> Result<Option<String>> {
+        self.result(parts)
+    }
+
+    fn inverse(&self, ctx: &mut Context<'_>, parts: &mut VecDeque<Part>) -> Result<()> {
+        if let ValueRef::Null = ctx.get_raw(0) {
+            return Ok(());
+        }
+        parts.pop_front();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::register_functions;
+    use crate::types::Value;
+    use crate::{Connection, Result};
+
+    fn db() -> Result<Connection> {
+        let db = Connection::open_in_memory()?;
+        register_functions(&db)?;
+        db.execute_batch(
+            "CREATE TABLE t (i INTEGER PRIMARY KEY, x, y, s TEXT);
+             INSERT INTO t VALUES
+                 (1, 2, 1, 'b'), (2, 4, 3, 'a'), (3, 4, 2, NULL), (4, 4, 5, 'd'),
+                 (5, 5, 4, 'c'), (6, 5, 7, 'f'), (7, 7, 6, 'e'), (8, 9, 9, 'g'),
+                 (9, NULL, 8, 'h');",
+        )?;
+        Ok(db)
+    }
+
+    fn assert_close(expected: f64, actual: Option<f64>) {
+        let actual = actual.expect("NULL result");
+        assert!(
+            (expected - actual).abs() < 1e-9,
+            "{} != {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_aggregates() -> Result<()> {
+        let db = db()?;
+        let f = |sql: &str| -> Result<Option<f64>> {
+            db.query_row(&format!("SELECT {} FROM t", sql), [], |r| r.get(0))
+        };
+        assert_close(4.5, f("median(x)")?);
+        assert_close(4.0, f("percentile(x, 25)")?);
+        assert_close(7.6, f("percentile(x, 90)")?);
+        assert_close(9.0, f("percentile(x, 100)")?);
+        assert_close(4.0, f("var_pop(x)")?);
+        assert_close(2.0, f("stddev_pop(x)")?);
+        assert_close(32.0 / 7.0, f("var_samp(x)")?);
+        assert_close((32.0f64 / 7.0).sqrt(), f("stddev_samp(x)")?);
+        assert_close(5.0, f("covar(x, y)")?);
+        assert_close(5.0, f("covar_samp(x, y)")?);
+        assert_close(35.0 / 8.0, f("covar_pop(x, y)")?);
+        assert_close(35.0 / (32.0f64 * 49.875).sqrt(), f("corr(x, y)")?);
+        assert_close(1.0, f("corr(x, 2 * x + 1)")?);
+        assert_eq!(None, f("median(x) FILTER (WHERE 0)")?);
+        assert_eq!(None, f("var_samp(x) FILTER (WHERE i = 1)")?);
+        assert!(f("percentile(x, 101)").is_err());
+        assert!(f("percentile(x, i)").is_err());
+
+        let mode: Value = db.query_row("SELECT mode(x) FROM t", [], |r| r.get(0))?;
+        assert_eq!(Value::Integer(4), mode);
+        let mode: Value = db.query_row(
+            "SELECT mode(column1) FROM (VALUES ('b'), ('a'), (1.5), ('a'), (1.5))",
+            [],
+            |r| r.get(0),
+        )?;
+        assert_eq!(Value::Real(1.5), mode);
+
+        let s: String = db.query_row("SELECT string_agg(s, ',') FROM t", [], |r| r.get(0))?;
+        assert_eq!("b,a,d,c,f,e,g,h", s);
+        let s: String = db.query_row("SELECT string_agg(s, '', s) FROM t", [], |r| r.get(0))?;
+        assert_eq!("abcdefgh", s);
+        let s: String = db.query_row("SELECT string_agg(x, '-', -i) FROM t", [], |r| r.get(0))?;
+        assert_eq!("9-7-5-5-4-4-4-2", s);
+        let s: String = db.query_row(
+            "SELECT string_agg(column1, ',') FROM (VALUES (1.0), (2.5), (3), (x'41'))",
+            [],
+            |r| r.get(0),
+        )?;
+        assert_eq!("1.0,2.5,3,A", s);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "window")]
+    fn test_window() -> Result<()> {
+        let db = db()?;
+        let window = |f: &str| -> Result<Vec<Option<f64>>> {
+            let sql = format!(
+                "SELECT {} OVER (ORDER BY i ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING) FROM t",
+                f
+            );
+            let mut stmt = db.prepare(&sql)?;
+            let rows = stmt.query_map([], |r| r.get(0))?;
+            rows.collect()
+        };
+        assert_eq!(
+            vec![3.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 8.0, 9.0],
+            window("median(x)")?
+                .into_iter()
+                .map(Option::unwrap)
+                .collect::<Vec<_>>()
+        );
+        let variances = window("var_pop(x)")?;
+        let expected = [
+            1.0,
+            8.0 / 9.0,
+            0.0,
+            2.0 / 9.0,
+            2.0 / 9.0,
+            8.0 / 9.0,
+            8.0 / 3.0,
+            1.0,
+        ];
+        for (expected, actual) in expected.iter().zip(variances) {
+            assert_close(*expected, actual);
+        }
+        assert_eq!(None, window("var_samp(x)")?[8]);
+        let covar = window("covar_pop(x, y)")?;
+        assert_close(0.0, covar[8]);
+        let corr = window("corr(x, i)")?;
+        assert_close(1.0, corr[0]);
+
+        let mut stmt = db.prepare(
+            "SELECT string_agg(s, ',') OVER (ORDER BY i ROWS BETWEEN 1 PRECEDING AND CURRENT ROW),
+                    string_agg(s, '', s) OVER (ORDER BY i ROWS 2 PRECEDING),
+                    mode(y % 2) OVER (ORDER BY i ROWS 2 PRECEDING)
+             FROM t",
+        )?;
+        let rows = stmt
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))?
+            .collect::<Result<Vec<(Option<String>, String, i64)>>>()?;
+        assert_eq!(
+            vec![
+                (Some("b".to_owned()), "b".to_owned(), 1),
+                (Some("b,a".to_owned()), "ab".to_owned(), 1),
+                (Some("a".to_owned()), "ab".to_owned(), 1),
+                (Some("d".to_owned()), "ad".to_owned(), 1),
+                (Some("d,c".to_owned()), "cd".to_owned(), 0),
+                (Some("c,f".to_owned()), "cdf".to_owned(), 1),
+                (Some("f,e".to_owned()), "cef".to_owned(), 0),
+                (Some("e,g".to_owned()), "efg".to_owned(), 1),
+                (Some("g,h".to_owned()), "egh".to_owned(), 0),
+            ],
+            rows
+        );
+        Ok(())
+    }
+}
@@ -96,12 +96,23 @@ impl Connection {
 }
 
 impl InnerConnection {
+    // Only the hooks registered through this connection are removed, so that
+    // dropping a connection created by `Connection::from_handle` keeps those of
+    // the owning connection.
     #[inline]
     pub fn remove_hooks(&mut self) {
-        self.update_hook(None::<fn(Action, &str, &str, i64)>);
-        self.commit_hook(None::<fn() -> bool>);
-        self.rollback_hook(None::<fn()>);
-        self.progress_handler(0, None::<fn() -> bool>);
+        if self.free_update_hook.is_some() {
+            self.update_hook(None::<fn(Action, &str, &str, i64)>);
+        }
+        if self.free_commit_hook.is_some() {
+            self.commit_hook(None::<fn() -> bool>);
+        }
+        if self.free_rollback_hook.is_some() {
+            self.rollback_hook(None::<fn()>);
+        }
+        if self.progress_handler.is_some() {
+            self.progress_handler(0, None::<fn() -> bool>);
+        }
     }
 
     fn commit_hook<'c, F>(&'c mut self, hook: Option<F>)
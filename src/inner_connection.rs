@@ -33,6 +33,8 @@ pub struct InnerConnection {
     pub free_update_hook: Option<unsafe fn(*mut ::std::os::raw::c_void)>,
     #[cfg(feature = "hooks")]
     pub progress_handler: Option<Box<dyn FnMut() -> bool + Send>>,
+    #[cfg(feature = "collation")]
+    pub collation_needed: Option<Box<crate::collation::CollationNeeded>>,
     owned: bool,
 }
 
@@ -51,6 +53,8 @@ impl InnerConnection {
             free_update_hook: None,
             #[cfg(feature = "hooks")]
             progress_handler: None,
+            #[cfg(feature = "collation")]
+            collation_needed: None,
             owned,
         }
     }
@@ -151,6 +155,8 @@ impl InnerConnection {
             return Ok(());
         }
         self.remove_hooks();
+        #[cfg(feature = "collation")]
+        self.remove_collation_needed();
         let mut shared_handle = self.interrupt_lock.lock().unwrap();
         assert!(
             !shared_handle.is_null(),
@@ -71,6 +71,8 @@ use crate::types::ValueRef;
 
 pub use crate::cache::CachedStatement;
 pub use crate::column::Column;
+#[cfg(feature = "column_metadata")]
+pub use crate::column::ColumnOrigin;
 pub use crate::error::Error;
 pub use crate::ffi::ErrorCode;
 #[cfg(feature = "hooks")]
@@ -87,6 +89,9 @@ pub use crate::version::*;
 #[macro_use]
 mod error;
 
+#[cfg(feature = "auto_extension")]
+pub mod auto_extension;
+
 #[cfg(feature = "backup")]
 pub mod backup;
 #[cfg(feature = "blob")]
@@ -94,7 +99,7 @@ pub mod blob;
 mod busy;
 mod cache;
 #[cfg(feature = "collation")]
-mod collation;
+pub mod collation;
 mod column;
 pub mod config;
 #[cfg(any(feature = "functions", feature = "vtab"))]
@@ -108,13 +113,21 @@ mod inner_connection;
 pub mod limits;
 #[cfg(feature = "load_extension")]
 mod load_extension_guard;
+#[cfg(feature = "blob")]
+pub mod lob;
 mod params;
 mod pragma;
+pub mod query_plan;
 mod raw_statement;
 mod row;
+#[cfg(feature = "column_metadata")]
+pub mod schema;
 #[cfg(feature = "session")]
 pub mod session;
 mod statement;
+pub mod status;
+#[cfg(feature = "session")]
+pub mod sync;
 #[cfg(feature = "trace")]
 pub mod trace;
 mod transaction;
@@ -306,6 +319,7 @@ pub const TEMP_DB: DatabaseName<'static> = DatabaseName::Temp;
     feature = "backup",
     feature = "blob",
     feature = "session",
+    feature = "column_metadata",
     feature = "modern_sqlite"
 ))]
 impl DatabaseName<'_> {
@@ -0,0 +1,617 @@
+//! `feature = "blob"` Large objects stored as a sequence of chunks
+//!
+//! A single BLOB is limited by `SQLITE_MAX_LENGTH` (1 GB by default, 2 GB at
+//! most) and [`Blob`](crate::blob::Blob) offsets are `i32`. A
+//! [`LobStore`] splits each object into fixed-size chunk rows instead, so
+//! objects are only limited by the size of the database, and exposes each
+//! one as a [`Lob`] implementing `std::io::{Read, Write, Seek}`.
+//!
+//! The store uses two tables: `<name>` with one row per object, and
+//! `<name>_chunks` with the chunks themselves.
+//!
+//! ```rust
+//! # use rusqlite::{Connection, Result};
+//! # use rusqlite::lob::LobStore;
+//! # use std::io::{Read, Seek, SeekFrom, Write};
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Connection::open_in_memory()?;
+//! let store = LobStore::new(&db, "assets")?;
+//!
+//! let mut lob = store.create()?;
+//! lob.write_all(b"Hello, world!")?;
+//! let id = lob.id();
+//!
+//! let mut lob = store.open(id)?;
+//! lob.seek(SeekFrom::Start(7))?;
+//! let mut s = String::new();
+//! lob.read_to_string(&mut s)?;
+//! assert_eq!("world!", s);
+//! # Ok(())
+//! # }
+//! ```
+use std::cmp::min;
+use std::convert::TryFrom;
+use std::io;
+
+use crate::error::error_from_sqlite_code;
+use crate::pragma::Sql;
+use crate::{ffi, params, Connection, DatabaseName, Error, OptionalExtension, Result};
+
+/// Default size of the chunks of new objects: 1 MiB
+pub const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+/// `feature = "blob"` Set of large objects stored in a pair of tables.
+#[derive(Debug)]
+pub struct LobStore<'conn> {
+    conn: &'conn Connection,
+    // quoted table names
+    objects: String,
+    chunks: String,
+    // unquoted, for `blob_open`
+    chunks_name: String,
+    chunk_size: usize,
+}
+
+impl<'conn> LobStore<'conn> {
+    /// Open the store named `name` in the main database, creating its tables
+    /// if they do not exist.
+    ///
+    /// `conn` may be a [`Transaction`](crate::Transaction), so that changes
+    /// made through the store are committed or rolled back with it.
+    pub fn new(conn: &'conn Connection, name: &str) -> Result<LobStore<'conn>> {
+        let chunks_name = format!("{}_chunks", name);
+        let store = LobStore {
+            conn,
+            objects: quote(name),
+            chunks: quote(&chunks_name),
+            chunks_name,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        };
+        conn.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {objects} (
+                 id INTEGER PRIMARY KEY,
+                 length INTEGER NOT NULL,
+                 chunk_size INTEGER NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS {chunks} (
+                 lob_id INTEGER NOT NULL,
+                 idx INTEGER NOT NULL,
+                 data BLOB NOT NULL,
+                 UNIQUE (lob_id, idx)
+             );",
+            objects = store.objects,
+            chunks = store.chunks,
+        ))?;
+        Ok(store)
+    }
+
+    /// Set the size of the chunks of the objects created afterwards.
+    ///
+    /// Existing objects keep the chunk size they were created with.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0 or larger than `i32::MAX`.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> LobStore<'conn> {
+        assert!(
+            chunk_size > 0 && chunk_size <= i32::MAX as usize,
+            "invalid chunk size"
+        );
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Create a new empty object.
+    pub fn create(&self) -> Result<Lob<'_>> {
+        self.conn.execute(
+            &format!(
+                "INSERT INTO {} (length, chunk_size) VALUES (0, ?)",
+                self.objects
+            ),
+            [self.chunk_size as i64],
+        )?;
+        Ok(Lob {
+            store: self,
+            id: self.conn.last_insert_rowid(),
+            len: 0,
+            chunk_size: self.chunk_size as u64,
+            pos: 0,
+        })
+    }
+
+    /// Open the object `id`.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err(Error::QueryReturnedNoRows)` if there is no such
+    /// object.
+    pub fn open(&self, id: i64) -> Result<Lob<'_>> {
+        let (len, chunk_size): (i64, i64) = self.conn.query_row(
+            &format!(
+                "SELECT length, chunk_size FROM {} WHERE id = ?",
+                self.objects
+            ),
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(Lob {
+            store: self,
+            id,
+            len: len as u64,
+            chunk_size: chunk_size as u64,
+            pos: 0,
+        })
+    }
+
+    /// Returns the identifiers of all the objects, in ascending order.
+    pub fn ids(&self) -> Result<Vec<i64>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT id FROM {} ORDER BY id", self.objects))?;
+        let ids = stmt.query_map([], |row| row.get(0))?;
+        ids.collect()
+    }
+
+    /// Delete the object `id`, returning `false` if there was no such object.
+    pub fn delete(&self, id: i64) -> Result<bool> {
+        self.in_savepoint(|| {
+            self.conn.execute(
+                &format!("DELETE FROM {} WHERE lob_id = ?", self.chunks),
+                [id],
+            )?;
+            let n = self
+                .conn
+                .execute(&format!("DELETE FROM {} WHERE id = ?", self.objects), [id])?;
+            Ok(n > 0)
+        })
+    }
+
+    /// Replace the content of the object `id` with the content of `reader`,
+    /// and return the new length.
+    ///
+    /// The replacement is atomic: if reading or writing fails, the object is
+    /// left unchanged. Inside a [`Transaction`](crate::Transaction), it is
+    /// committed or rolled back with the transaction.
+    pub fn replace<R: io::Read>(&self, id: i64, mut reader: R) -> Result<u64> {
+        self.in_savepoint(|| {
+            let mut lob = self.open(id)?;
+            lob.truncate(0)?;
+            io::copy(&mut reader, &mut lob).map_err(from_io_error)
+        })
+    }
+
+    /// Run `f` in a savepoint, rolled back if `f` fails.
+    fn in_savepoint<T, F: FnOnce() -> Result<T>>(&self, f: F) -> Result<T> {
+        self.conn.execute_batch("SAVEPOINT _rusqlite_lob")?;
+        match f() {
+            Ok(value) => {
+                self.conn.execute_batch("RELEASE _rusqlite_lob")?;
+                Ok(value)
+            }
+            Err(e) => {
+                let _ = self
+                    .conn
+                    .execute_batch("ROLLBACK TO _rusqlite_lob; RELEASE _rusqlite_lob");
+                Err(e)
+            }
+        }
+    }
+
+    /// (rowid, length) of chunk `idx` of object `id`
+    fn chunk(&self, id: i64, idx: u64) -> Result<Option<(i64, u64)>> {
+        self.conn
+            .query_row(
+                &format!(
+                    "SELECT rowid, length(data) FROM {} WHERE lob_id = ? AND idx = ?",
+                    self.chunks
+                ),
+                [id, idx as i64],
+                |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as u64)),
+            )
+            .optional()
+    }
+
+    /// Make chunk `idx` of object `id` at least `len` bytes long (but no
+    /// longer than `chunk_size`), padding it with zeroes, and return its
+    /// rowid.
+    ///
+    /// The last chunk of an object may be longer than the bytes it holds:
+    /// it grows geometrically, so that appending does not rewrite it on every
+    /// write. The extra bytes are always zeroes.
+    fn grow_chunk(&self, id: i64, idx: u64, len: u64, chunk_size: u64) -> Result<i64> {
+        match self.chunk(id, idx)? {
+            Some((rowid, chunk_len)) => {
+                if chunk_len < len {
+                    let new_len = min(chunk_size, len.max(chunk_len * 2));
+                    // A BLOB cannot be resized in place, so the chunk is
+                    // rewritten with its current bytes followed by zeroes.
+                    let mut data = vec![0u8; new_len as usize];
+                    let blob = self.conn.blob_open(
+                        DatabaseName::Main,
+                        &self.chunks_name,
+                        "data",
+                        rowid,
+                        true,
+                    )?;
+                    blob.read_at_exact(&mut data[..chunk_len as usize], 0)?;
+                    blob.close()?;
+                    self.conn.execute(
+                        &format!("UPDATE {} SET data = ? WHERE rowid = ?", self.chunks),
+                        params![data, rowid],
+                    )?;
+                }
+                Ok(rowid)
+            }
+            None => {
+                self.conn.execute(
+                    &format!(
+                        "INSERT INTO {} (lob_id, idx, data) VALUES (?, ?, zeroblob(?))",
+                        self.chunks
+                    ),
+                    [id, idx as i64, len as i64],
+                )?;
+                Ok(self.conn.last_insert_rowid())
+            }
+        }
+    }
+}
+
+/// `feature = "blob"` Handle to an object of a [`LobStore`].
+///
+/// Reads and writes go straight to the database; the length is cached, so
+/// an object should not be modified through two handles at once.
+#[derive(Debug)]
+pub struct Lob<'store> {
+    store: &'store LobStore<'store>,
+    id: i64,
+    len: u64,
+    chunk_size: u64,
+    pos: u64,
+}
+
+impl Lob<'_> {
+    /// Returns the identifier of the object.
+    #[inline]
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// Returns the length of the object in bytes.
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the object is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the size of the chunks of the object.
+    #[inline]
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size as usize
+    }
+
+    /// Set the length of the object to `len`, dropping the bytes after it or
+    /// padding it with zeroes.
+    ///
+    /// The current position is not changed, even if it is now past the end.
+    pub fn truncate(&mut self, len: u64) -> Result<()> {
+        let store = self.store;
+        if len < self.len {
+            let last = if len == 0 {
+                None
+            } else {
+                Some((len - 1) / self.chunk_size)
+            };
+            store.conn.execute(
+                &format!(
+                    "DELETE FROM {} WHERE lob_id = ?1 AND idx > ?2",
+                    store.chunks
+                ),
+                [self.id, last.map_or(-1, |idx| idx as i64)],
+            )?;
+            if let Some(idx) = last {
+                store.conn.execute(
+                    &format!(
+                        "UPDATE {} SET data = substr(data, 1, ?3) WHERE lob_id = ?1 AND idx = ?2",
+                        store.chunks
+                    ),
+                    [self.id, idx as i64, (len - idx * self.chunk_size) as i64],
+                )?;
+            }
+        } else if len > self.len {
+            let first = self.len / self.chunk_size;
+            let last = (len - 1) / self.chunk_size;
+            for idx in first..=last {
+                let chunk_len = min(self.chunk_size, len - idx * self.chunk_size);
+                store.grow_chunk(self.id, idx, chunk_len, self.chunk_size)?;
+            }
+        }
+        self.set_len(len)
+    }
+
+    fn set_len(&mut self, len: u64) -> Result<()> {
+        let len_i64 = i64::try_from(len).map_err(|_| Error::BlobSizeError)?;
+        self.store.conn.execute(
+            &format!("UPDATE {} SET length = ? WHERE id = ?", self.store.objects),
+            [len_i64, self.id],
+        )?;
+        self.len = len;
+        Ok(())
+    }
+
+    fn read_(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.pos >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+        let idx = self.pos / self.chunk_size;
+        let offset = self.pos % self.chunk_size;
+        let n = min(
+            buf.len() as u64,
+            min(self.chunk_size - offset, self.len - self.pos),
+        ) as usize;
+        let store = self.store;
+        let (rowid, _) = store
+            .chunk(self.id, idx)?
+            .ok_or_else(|| error_from_sqlite_code(ffi::SQLITE_CORRUPT, None))?;
+        let blob =
+            store
+                .conn
+                .blob_open(DatabaseName::Main, &store.chunks_name, "data", rowid, true)?;
+        blob.read_at_exact(&mut buf[..n], offset as usize)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn write_(&mut self, buf: &[u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.pos > self.len {
+            self.truncate(self.pos)?;
+        }
+        let idx = self.pos / self.chunk_size;
+        let offset = self.pos % self.chunk_size;
+        let n = min(buf.len() as u64, self.chunk_size - offset) as usize;
+        let store = self.store;
+        let rowid = store.grow_chunk(self.id, idx, offset + n as u64, self.chunk_size)?;
+        let mut blob =
+            store
+                .conn
+                .blob_open(DatabaseName::Main, &store.chunks_name, "data", rowid, false)?;
+        blob.write_at(&buf[..n], offset as usize)?;
+        blob.close()?;
+        let end = self.pos + n as u64;
+        if end > self.len {
+            self.set_len(end)?;
+        }
+        self.pos = end;
+        Ok(n)
+    }
+}
+
+impl io::Read for Lob<'_> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_(buf)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+    }
+}
+
+impl io::Write for Lob<'_> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_(buf)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl io::Seek for Lob<'_> {
+    /// Seek to an offset, in bytes, in the object. Seeking past the end is
+    /// allowed; writing there pads the object with zeroes.
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            io::SeekFrom::Start(offset) => Some(offset),
+            io::SeekFrom::Current(offset) => offset_by(self.pos, offset),
+            io::SeekFrom::End(offset) => offset_by(self.len, offset),
+        };
+        match pos {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            )),
+        }
+    }
+}
+
+fn offset_by(base: u64, offset: i64) -> Option<u64> {
+    if offset >= 0 {
+        base.checked_add(offset as u64)
+    } else {
+        base.checked_sub(offset.unsigned_abs())
+    }
+}
+
+fn quote(name: &str) -> String {
+    let mut sql = Sql::new();
+    sql.push_identifier(name);
+    sql.as_str().to_owned()
+}
+
+/// Recover the `rusqlite::Error` wrapped by `Lob`, or report a read error.
+fn from_io_error(e: io::Error) -> Error {
+    match e.get_ref() {
+        Some(inner) if inner.is::<Error>() => *e.into_inner().unwrap().downcast::<Error>().unwrap(),
+        _ => error_from_sqlite_code(ffi::SQLITE_IOERR_READ, Some(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LobStore;
+    use crate::{Connection, Result};
+    use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+    fn read_all(store: &LobStore<'_>, id: i64) -> Vec<u8> {
+        let mut buf = Vec::new();
+        store.open(id).unwrap().read_to_end(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_write_read() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        let store = LobStore::new(&db, "lob")?.with_chunk_size(7);
+        let data: Vec<u8> = (0..100u8).collect();
+
+        let mut lob = store.create()?;
+        lob.write_all(&data).unwrap();
+        assert_eq!(100, lob.len());
+        assert_eq!(7, lob.chunk_size());
+        let id = lob.id();
+        assert_eq!(data, read_all(&store, id));
+        let chunks: i64 = db.query_row("SELECT count(*) FROM lob_chunks", [], |r| r.get(0))?;
+        assert_eq!(15, chunks);
+
+        let mut lob = store.open(id)?;
+        lob.seek(SeekFrom::Start(12)).unwrap();
+        let mut buf = [0u8; 10];
+        lob.read_exact(&mut buf).unwrap();
+        assert_eq!(&data[12..22], &buf);
+
+        lob.seek(SeekFrom::End(-3)).unwrap();
+        lob.write_all(b"abcdef").unwrap();
+        assert_eq!(103, lob.len());
+        let all = read_all(&store, id);
+        assert_eq!(&data[..97], &all[..97]);
+        assert_eq!(b"abcdef", &all[97..]);
+
+        assert!(lob.seek(SeekFrom::Current(-1000)).is_err());
+        assert_eq!(vec![id], store.ids()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_small_appends() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        let store = LobStore::new(&db, "lob")?.with_chunk_size(1000);
+        let data: Vec<u8> = (0..2500u32).map(|i| (i % 251) as u8).collect();
+
+        let mut lob = store.create()?;
+        for part in data.chunks(3) {
+            lob.write_all(part).unwrap();
+        }
+        assert_eq!(data, read_all(&store, lob.id()));
+        let sizes: Vec<i64> = db
+            .prepare("SELECT length(data) FROM lob_chunks ORDER BY idx")?
+            .query_map([], |r| r.get(0))?
+            .collect::<Result<_>>()?;
+        assert_eq!(vec![1000, 1000, 512], sizes);
+        Ok(())
+    }
+
+    #[test]
+    fn test_utf16_database() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("PRAGMA encoding = 'UTF-16le'")?;
+        let store = LobStore::new(&db, "lob")?.with_chunk_size(9);
+        let data = b"\0A\0B\xFF\0\0\x01\0\0C\xFE\0".to_vec();
+
+        let mut lob = store.create()?;
+        // the first chunk grows from 6 to 9 bytes
+        for part in data.chunks(3) {
+            lob.write_all(part).unwrap();
+        }
+        assert_eq!(data, read_all(&store, lob.id()));
+        let sizes: Vec<i64> = db
+            .prepare("SELECT length(data) FROM lob_chunks ORDER BY idx")?
+            .query_map([], |r| r.get(0))?
+            .collect::<Result<_>>()?;
+        assert_eq!(vec![9, 6], sizes);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sparse_write_and_truncate() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        let store = LobStore::new(&db, "lob")?.with_chunk_size(4);
+
+        let mut lob = store.create()?;
+        lob.seek(SeekFrom::Start(6)).unwrap();
+        lob.write_all(b"xy").unwrap();
+        assert_eq!(8, lob.len());
+        let id = lob.id();
+        assert_eq!(b"\0\0\0\0\0\0xy".to_vec(), read_all(&store, id));
+
+        let mut lob = store.open(id)?;
+        lob.truncate(5)?;
+        assert_eq!(vec![0; 5], read_all(&store, id));
+        lob.truncate(10)?;
+        assert_eq!(vec![0; 10], read_all(&store, id));
+        lob.truncate(0)?;
+        assert!(lob.is_empty());
+        let chunks: i64 = db.query_row("SELECT count(*) FROM lob_chunks", [], |r| r.get(0))?;
+        assert_eq!(0, chunks);
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_and_delete() -> Result<()> {
+        let mut db = Connection::open_in_memory()?;
+        let id = {
+            let store = LobStore::new(&db, "lob")?.with_chunk_size(3);
+            let mut lob = store.create()?;
+            lob.write_all(b"old content").unwrap();
+            assert_eq!(3, store.replace(lob.id(), Cursor::new(b"new"))?);
+            assert_eq!(b"new".to_vec(), read_all(&store, lob.id()));
+            lob.id()
+        };
+
+        {
+            let tx = db.transaction()?;
+            let store = LobStore::new(&tx, "lob")?;
+            store.replace(id, Cursor::new(b"rolled back"))?;
+            assert_eq!(b"rolled back".to_vec(), read_all(&store, id));
+        }
+        let store = LobStore::new(&db, "lob")?;
+        assert_eq!(b"new".to_vec(), read_all(&store, id));
+
+        assert!(store.replace(id + 1, Cursor::new(b"x")).is_err());
+        assert!(store.delete(id)?);
+        assert!(!store.delete(id)?);
+        assert!(store.open(id).is_err());
+        assert!(db.is_autocommit());
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_with_failing_reader() -> Result<()> {
+        struct Failing;
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(std::io::ErrorKind::Other, "broken"))
+            }
+        }
+
+        let db = Connection::open_in_memory()?;
+        let store = LobStore::new(&db, "lob")?;
+        let mut lob = store.create()?;
+        lob.write_all(b"kept").unwrap();
+        assert!(store.replace(lob.id(), Failing).is_err());
+        assert_eq!(b"kept".to_vec(), read_all(&store, lob.id()));
+        assert!(db.is_autocommit());
+        Ok(())
+    }
+}
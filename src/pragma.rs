@@ -71,7 +71,14 @@ impl Sql {
                 ));
             }
             #[cfg(feature = "array")]
-            ToSqlOutput::Array(_) => {
+            ToSqlOutput::Array(_) | ToSqlOutput::ArrayData(_) => {
+                return Err(Error::SqliteFailure(
+                    ffi::Error::new(ffi::SQLITE_MISUSE),
+                    Some(format!("Unsupported value \"{:?}\"", value)),
+                ));
+            }
+            #[cfg(feature = "modern_sqlite")]
+            ToSqlOutput::Pointer(_) => {
                 return Err(Error::SqliteFailure(
                     ffi::Error::new(ffi::SQLITE_MISUSE),
                     Some(format!("Unsupported value \"{:?}\"", value)),
@@ -149,6 +156,63 @@ impl Sql {
     }
 }
 
+#[cfg(feature = "session")]
+impl Sql {
+    /// Append `value` as a SQL literal, preserving its storage class.
+    pub fn push_literal(&mut self, value: ValueRef<'_>) -> Result<()> {
+        match value {
+            ValueRef::Null => self.push_null(),
+            ValueRef::Integer(i) => self.push_int(i),
+            ValueRef::Real(r) => {
+                if r.is_nan() {
+                    self.push_null();
+                } else if r.is_infinite() {
+                    self.buf.push_str(if r > 0.0 { "9e999" } else { "-9e999" });
+                } else {
+                    // `Debug` gives the shortest representation that
+                    // round-trips and always looks like a real to SQLite.
+                    self.buf.push_str(&format!("{:?}", r));
+                }
+            }
+            ValueRef::Text(s) => {
+                let s = std::str::from_utf8(s)?;
+                self.push_string_literal(s);
+            }
+            ValueRef::Blob(b) => self.push_blob(b),
+        };
+        Ok(())
+    }
+
+    pub fn push_quoted_identifier(&mut self, s: &str) {
+        self.wrap_and_escape(s, '"');
+    }
+
+    pub fn push_null(&mut self) {
+        self.buf.push_str("NULL");
+    }
+
+    pub fn push_blob(&mut self, b: &[u8]) {
+        const HEX: &[u8; 16] = b"0123456789ABCDEF";
+        self.buf.push_str("X'");
+        for byte in b {
+            self.buf.push(HEX[(byte >> 4) as usize] as char);
+            self.buf.push(HEX[(byte & 0xF) as usize] as char);
+        }
+        self.buf.push('\'');
+    }
+
+    pub fn push_comma(&mut self) {
+        self.buf.push(',');
+    }
+}
+
+#[cfg(any(feature = "session", feature = "column_metadata", feature = "blob"))]
+impl Sql {
+    pub fn push_sql(&mut self, sql: &str) {
+        self.buf.push_str(sql);
+    }
+}
+
 impl Deref for Sql {
     type Target = str;
 
@@ -427,6 +491,24 @@ mod test {
         assert_eq!("'value''; --'", sql.as_str());
     }
 
+    #[test]
+    #[cfg(feature = "session")]
+    fn push_literal() -> Result<()> {
+        use crate::types::ValueRef;
+        let mut sql = Sql::new();
+        sql.push_literal(ValueRef::Null)?;
+        sql.push_comma();
+        sql.push_literal(ValueRef::Integer(-1))?;
+        sql.push_comma();
+        sql.push_literal(ValueRef::Real(1.0))?;
+        sql.push_comma();
+        sql.push_literal(ValueRef::Text(b"it's"))?;
+        sql.push_comma();
+        sql.push_literal(ValueRef::Blob(&[0x00, 0xAB]))?;
+        assert_eq!("NULL,-1,1.0,'it''s',X'00AB'", sql.as_str());
+        Ok(())
+    }
+
     #[test]
     fn locking_mode() -> Result<()> {
         let db = Connection::open_in_memory()?;
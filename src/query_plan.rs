@@ -0,0 +1,397 @@
+//! Query plan introspection
+//!
+//! [`Statement::query_plan`](crate::Statement::query_plan) runs
+//! [`EXPLAIN QUERY PLAN`](https://sqlite.org/eqp.html) on a statement, which
+//! can be used to check that a query does not scan a whole table:
+//!
+//! ```rust,no_run
+//! # use rusqlite::{Connection, Result};
+//! fn check_indexed(conn: &Connection) -> Result<()> {
+//!     let stmt = conn.prepare("SELECT * FROM person WHERE name = ?")?;
+//!     let plan = stmt.query_plan()?;
+//!     assert!(!plan.has_full_scan(), "full scan:\n{}", plan);
+//!     Ok(())
+//! }
+//! ```
+
+use std::fmt;
+
+use crate::{Connection, Result};
+
+/// Kind of step of a query plan, derived from its description.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum QueryPlanNodeKind {
+    /// Visit every row of a table (`SCAN t`)
+    FullScan,
+    /// Visit every row of a subquery or common table expression
+    /// (`SCAN (subquery-1)`, or `SCAN c` after `MATERIALIZE c`)
+    ///
+    /// SQLite describes a scan of a common table expression under an alias
+    /// like a scan of a table, so it is reported as a
+    /// [`FullScan`](QueryPlanNodeKind::FullScan).
+    SubqueryScan,
+    /// Visit every entry of an index (`SCAN t USING [COVERING] INDEX i`)
+    IndexScan,
+    /// Visit only the rows matching a constraint (`SEARCH t USING ...`)
+    Search,
+    /// Sort rows or remove duplicates in a temporary b-tree
+    /// (`USE TEMP B-TREE FOR ...`)
+    TempBTree,
+    /// Any other step (subqueries, compound queries, virtual tables, ...)
+    Other,
+}
+
+/// One step of a [`QueryPlan`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryPlanNode {
+    id: i32,
+    detail: String,
+    kind: QueryPlanNodeKind,
+    children: Vec<QueryPlanNode>,
+}
+
+impl QueryPlanNode {
+    /// Returns the identifier of this step.
+    #[inline]
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    /// Returns the description of this step, as reported by SQLite.
+    #[inline]
+    pub fn detail(&self) -> &str {
+        &self.detail
+    }
+
+    /// Returns the nested steps.
+    #[inline]
+    pub fn children(&self) -> &[QueryPlanNode] {
+        &self.children
+    }
+
+    /// Returns the kind of this step.
+    #[inline]
+    pub fn kind(&self) -> QueryPlanNodeKind {
+        self.kind
+    }
+
+    /// Kind of the step described by `detail`, given the names of the
+    /// `subqueries` that the plan materializes or runs as co-routines.
+    fn kind_of(detail: &str, subqueries: &[&str]) -> QueryPlanNodeKind {
+        if detail.starts_with("USE TEMP B-TREE") {
+            QueryPlanNodeKind::TempBTree
+        } else if detail.starts_with("SEARCH ") {
+            QueryPlanNodeKind::Search
+        } else if let Some(scanned) = detail.strip_prefix("SCAN ") {
+            // SQLite < 3.36 writes "SCAN SUBQUERY 1" and "SCAN TABLE t"
+            let is_subquery = ["(subquery-", "SUBQUERY ", "CTE "]
+                .iter()
+                .any(|prefix| scanned.starts_with(prefix))
+                || subqueries.contains(&scanned.strip_prefix("TABLE ").unwrap_or(scanned));
+            if detail.contains(" USING ") {
+                QueryPlanNodeKind::IndexScan
+            } else if detail.contains(" VIRTUAL TABLE ") || scanned == "CONSTANT ROW" {
+                QueryPlanNodeKind::Other
+            } else if is_subquery {
+                QueryPlanNodeKind::SubqueryScan
+            } else {
+                QueryPlanNodeKind::FullScan
+            }
+        } else {
+            QueryPlanNodeKind::Other
+        }
+    }
+
+    /// Returns the table scanned or searched by this step, as named in the
+    /// query (i.e. its alias if it has one).
+    pub fn table(&self) -> Option<&str> {
+        match self.kind() {
+            QueryPlanNodeKind::FullScan
+            | QueryPlanNodeKind::IndexScan
+            | QueryPlanNodeKind::Search => {
+                let mut words = self.detail.split(' ').skip(1);
+                // SQLite < 3.36 writes "SCAN TABLE t"
+                match words.next() {
+                    Some("TABLE") => words.next(),
+                    name => name,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if this step visits every row of a table.
+    #[inline]
+    pub fn is_full_scan(&self) -> bool {
+        self.kind() == QueryPlanNodeKind::FullScan
+    }
+
+    /// Returns `true` if this step uses a temporary b-tree.
+    #[inline]
+    pub fn uses_temp_btree(&self) -> bool {
+        self.kind() == QueryPlanNodeKind::TempBTree
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        writeln!(f, "{:width$}{}", "", self.detail, width = depth * 2)?;
+        for child in &self.children {
+            child.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of `EXPLAIN QUERY PLAN`, as a tree of steps.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryPlan {
+    roots: Vec<QueryPlanNode>,
+}
+
+impl QueryPlan {
+    pub(crate) fn explain(conn: &Connection, sql: &str) -> Result<QueryPlan> {
+        let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {}", sql))?;
+        // SQLite < 3.24 returns (selectid, order, from, detail) rows instead of
+        // (id, parent, notused, detail), so the plan is flat.
+        let is_tree = stmt.column_name(0)? == "id";
+        // Unbound parameters are fine here: they are treated as NULL.
+        let mut rows = stmt.raw_query();
+        let mut steps: Vec<(i32, i32, String)> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let (id, parent) = if is_tree {
+                (row.get(0)?, row.get(1)?)
+            } else {
+                (steps.len() as i32, 0)
+            };
+            steps.push((id, parent, row.get(3)?));
+        }
+        let subqueries: Vec<String> = steps
+            .iter()
+            .filter_map(|(_, _, detail)| {
+                ["MATERIALIZE ", "CO-ROUTINE "]
+                    .iter()
+                    .find_map(|prefix| detail.strip_prefix(prefix))
+                    .map(str::to_owned)
+            })
+            .collect();
+        let subqueries: Vec<&str> = subqueries.iter().map(String::as_str).collect();
+        Ok(QueryPlan {
+            roots: children_of(0, &mut steps, &subqueries),
+        })
+    }
+
+    /// Returns the top-level steps.
+    #[inline]
+    pub fn roots(&self) -> &[QueryPlanNode] {
+        &self.roots
+    }
+
+    /// Returns all the steps, parents before their children.
+    pub fn nodes(&self) -> Vec<&QueryPlanNode> {
+        fn visit<'a>(nodes: &'a [QueryPlanNode], all: &mut Vec<&'a QueryPlanNode>) {
+            for node in nodes {
+                all.push(node);
+                visit(&node.children, all);
+            }
+        }
+        let mut all = Vec::new();
+        visit(&self.roots, &mut all);
+        all
+    }
+
+    /// Returns `true` if any step visits every row of a table.
+    #[inline]
+    pub fn has_full_scan(&self) -> bool {
+        self.nodes().iter().any(|node| node.is_full_scan())
+    }
+
+    /// Returns `true` if any step uses a temporary b-tree.
+    #[inline]
+    pub fn uses_temp_btree(&self) -> bool {
+        self.nodes().iter().any(|node| node.uses_temp_btree())
+    }
+}
+
+/// Same layout as the `.eqp` output of the `sqlite3` shell, without the
+/// `QUERY PLAN` header.
+impl fmt::Display for QueryPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for root in &self.roots {
+            root.fmt_indented(f, 0)?;
+        }
+        Ok(())
+    }
+}
+
+/// Build the nodes whose parent is `parent`, in the order SQLite returned them.
+fn children_of(
+    parent: i32,
+    steps: &mut Vec<(i32, i32, String)>,
+    subqueries: &[&str],
+) -> Vec<QueryPlanNode> {
+    let mut children = Vec::new();
+    let mut i = 0;
+    while i < steps.len() {
+        if steps[i].1 == parent {
+            let (id, _, detail) = steps.remove(i);
+            children.push((id, detail));
+        } else {
+            i += 1;
+        }
+    }
+    children
+        .into_iter()
+        .map(|(id, detail)| QueryPlanNode {
+            id,
+            kind: QueryPlanNode::kind_of(&detail, subqueries),
+            detail,
+            children: children_of(id, steps, subqueries),
+        })
+        .collect()
+}
+
+/// `feature = "scanstatus"` Performance counters of one loop of a
+/// statement, see [`Statement::scan_status`](crate::Statement::scan_status).
+#[cfg(feature = "scanstatus")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScanStatus {
+    pub(crate) loops: i64,
+    pub(crate) rows_visited: i64,
+    pub(crate) estimated_rows: f64,
+    pub(crate) name: Option<String>,
+    pub(crate) explain: Option<String>,
+    pub(crate) select_id: i32,
+}
+
+#[cfg(feature = "scanstatus")]
+impl ScanStatus {
+    /// Returns the number of times the loop ran.
+    #[inline]
+    pub fn loops(&self) -> i64 {
+        self.loops
+    }
+
+    /// Returns the total number of rows visited by all iterations of the
+    /// loop.
+    #[inline]
+    pub fn rows_visited(&self) -> i64 {
+        self.rows_visited
+    }
+
+    /// Returns the number of rows the query planner expected each iteration
+    /// of the loop to visit.
+    #[inline]
+    pub fn estimated_rows(&self) -> f64 {
+        self.estimated_rows
+    }
+
+    /// Returns the name of the table or index used by the loop.
+    #[inline]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the `EXPLAIN QUERY PLAN` description of the loop.
+    #[inline]
+    pub fn explain(&self) -> Option<&str> {
+        self.explain.as_deref()
+    }
+
+    /// Returns the `EXPLAIN QUERY PLAN` identifier of the query the loop
+    /// belongs to.
+    #[inline]
+    pub fn select_id(&self) -> i32 {
+        self.select_id
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::QueryPlanNodeKind;
+    use crate::{Connection, Result};
+
+    fn db() -> Result<Connection> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "CREATE TABLE person(id INTEGER PRIMARY KEY, name TEXT, age INTEGER);
+             CREATE INDEX person_name ON person(name);",
+        )?;
+        Ok(db)
+    }
+
+    #[test]
+    fn test_search() -> Result<()> {
+        let db = db()?;
+        let stmt = db.prepare("SELECT * FROM person WHERE name = ?")?;
+        let plan = stmt.query_plan()?;
+        assert_eq!(1, plan.roots().len());
+        let node = &plan.roots()[0];
+        assert_eq!(QueryPlanNodeKind::Search, node.kind());
+        assert_eq!(Some("person"), node.table());
+        assert!(!plan.has_full_scan());
+        assert!(!plan.uses_temp_btree());
+        Ok(())
+    }
+
+    #[test]
+    fn test_full_scan() -> Result<()> {
+        let db = db()?;
+        let stmt = db.prepare("SELECT * FROM person WHERE age > 18 ORDER BY age")?;
+        let plan = stmt.query_plan()?;
+        assert!(plan.has_full_scan());
+        assert!(plan.uses_temp_btree());
+        let scan = plan.nodes().into_iter().find(|n| n.is_full_scan()).unwrap();
+        assert_eq!(Some("person"), scan.table());
+        Ok(())
+    }
+
+    #[test]
+    fn test_subquery_scan() -> Result<()> {
+        let db = db()?;
+        for sql in &[
+            "WITH adult AS (SELECT DISTINCT name FROM person WHERE id = ?) SELECT * FROM adult",
+            "SELECT * FROM (SELECT DISTINCT name FROM person WHERE id = ?)",
+        ] {
+            let plan = db.prepare(sql)?.query_plan()?;
+            assert!(!plan.has_full_scan(), "{}", plan);
+            let scan = plan
+                .nodes()
+                .into_iter()
+                .find(|n| n.kind() == QueryPlanNodeKind::SubqueryScan)
+                .unwrap();
+            assert_eq!(None, scan.table());
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "modern_sqlite")]
+    fn test_tree() -> Result<()> {
+        let db = db()?;
+        let stmt = db.prepare(
+            "SELECT name FROM person WHERE id IN (SELECT id FROM person WHERE age > 18)",
+        )?;
+        let plan = stmt.query_plan()?;
+        assert!(plan.roots().iter().any(|n| !n.children().is_empty()));
+        assert_eq!(plan.nodes().len(), plan.to_string().lines().count());
+        assert!(plan.to_string().lines().any(|l| l.starts_with("  ")));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "scanstatus")]
+    fn test_scan_status() -> Result<()> {
+        let db = db()?;
+        db.execute_batch("INSERT INTO person(name, age) VALUES ('a', 1), ('b', 2), ('c', 3);")?;
+        let mut stmt = db.prepare("SELECT * FROM person WHERE age > 1")?;
+        assert_eq!(2, stmt.query([])?.mapped(|_| Ok(())).count());
+        let scans = stmt.scan_status();
+        assert_eq!(1, scans.len());
+        assert_eq!(1, scans[0].loops());
+        assert_eq!(3, scans[0].rows_visited());
+        assert_eq!(Some("person"), scans[0].name());
+        stmt.reset_scan_status();
+        assert_eq!(0, stmt.scan_status()[0].loops());
+        Ok(())
+    }
+}
@@ -26,6 +26,10 @@ pub struct RawStatement {
     // One example of a case where the result of `sqlite_sql` and the value in
     // `statement_cache_key` might differ is if the statement has a `tail`.
     statement_cache_key: Option<Arc<str>>,
+    // Rowid columns of the tables read by this statement, used by
+    // `Row::blob_reader`.
+    #[cfg(all(feature = "blob", feature = "column_metadata"))]
+    pub(crate) rowid_names: crate::blob::RowidNamesCache,
 }
 
 impl RawStatement {
@@ -36,6 +40,8 @@ impl RawStatement {
             tail,
             cache: Default::default(),
             statement_cache_key: None,
+            #[cfg(all(feature = "blob", feature = "column_metadata"))]
+            rowid_names: Default::default(),
         }
     }
 
@@ -83,6 +89,24 @@ impl RawStatement {
         }
     }
 
+    #[inline]
+    #[cfg(feature = "column_metadata")]
+    pub fn column_database_name(&self, idx: usize) -> Option<&CStr> {
+        unsafe { nullable_cstr(ffi::sqlite3_column_database_name(self.ptr, idx as c_int)) }
+    }
+
+    #[inline]
+    #[cfg(feature = "column_metadata")]
+    pub fn column_table_name(&self, idx: usize) -> Option<&CStr> {
+        unsafe { nullable_cstr(ffi::sqlite3_column_table_name(self.ptr, idx as c_int)) }
+    }
+
+    #[inline]
+    #[cfg(feature = "column_metadata")]
+    pub fn column_origin_name(&self, idx: usize) -> Option<&CStr> {
+        unsafe { nullable_cstr(ffi::sqlite3_column_origin_name(self.ptr, idx as c_int)) }
+    }
+
     #[inline]
     pub fn column_name(&self, idx: usize) -> Option<&CStr> {
         let idx = idx as c_int;
@@ -205,3 +229,13 @@ impl Drop for RawStatement {
         self.finalize_();
     }
 }
+
+#[cfg(feature = "column_metadata")]
+#[inline]
+unsafe fn nullable_cstr<'a>(ptr: *const std::os::raw::c_char) -> Option<&'a CStr> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(ptr))
+    }
+}
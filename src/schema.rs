@@ -0,0 +1,523 @@
+//! `feature = "column_metadata"` Typed schema introspection
+//!
+//! [`Connection::schema`] describes the tables, views and triggers of a
+//! database, including their columns, indexes and foreign keys:
+//!
+//! ```rust,no_run
+//! # use rusqlite::{Connection, Result, MAIN_DB};
+//! fn print_schema(conn: &Connection) -> Result<()> {
+//!     for table in conn.schema(MAIN_DB)?.tables {
+//!         println!("{} ({:?})", table.name, table.kind);
+//!         for column in &table.columns {
+//!             println!("  {} {:?}", column.name, column.decl_type);
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Column details come from
+//! [`sqlite3_table_column_metadata`](https://sqlite.org/c3ref/table_column_metadata.html),
+//! which requires SQLite to be compiled with `SQLITE_ENABLE_COLUMN_METADATA`
+//! (the `bundled` build is).
+
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+use crate::ffi;
+use crate::pragma::Sql;
+use crate::{str_to_cstring, Connection, DatabaseName, Result, Row};
+
+/// (type, name, tbl_name, sql) row of `sqlite_master`
+type MasterEntry = (String, String, String, Option<String>);
+
+/// Kind of a [`Table`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TableKind {
+    /// Ordinary table
+    Table,
+    /// View
+    View,
+    /// Virtual table (`CREATE VIRTUAL TABLE`)
+    Virtual,
+}
+
+/// Description of a table or view
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Table {
+    /// Name
+    pub name: String,
+    /// Table, view or virtual table
+    pub kind: TableKind,
+    /// `true` for a `WITHOUT ROWID` table
+    pub without_rowid: bool,
+    /// `CREATE` statement, as stored in `sqlite_master`
+    pub sql: Option<String>,
+    /// Columns, in declaration order
+    pub columns: Vec<Column>,
+    /// Indexes, including the ones created implicitly for `UNIQUE` and
+    /// `PRIMARY KEY` constraints
+    pub indexes: Vec<Index>,
+    /// Foreign keys
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+impl Table {
+    /// Returns the column named `name`, ignoring ASCII case like SQLite does.
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the primary key columns, in key order.
+    ///
+    /// This is empty for a rowid table without an explicit `PRIMARY KEY`.
+    pub fn primary_key(&self) -> Vec<&Column> {
+        let mut pk: Vec<&Column> = self.columns.iter().filter(|c| c.primary_key > 0).collect();
+        pk.sort_by_key(|c| c.primary_key);
+        pk
+    }
+}
+
+/// Description of a table or view column
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Column {
+    /// Name
+    pub name: String,
+    /// Declared type
+    pub decl_type: Option<String>,
+    /// Name of the default collating sequence, `None` for a view column
+    pub collation: Option<String>,
+    /// `true` if the column has a `NOT NULL` constraint
+    pub not_null: bool,
+    /// Position of the column in the primary key, starting at 1, or 0 if the
+    /// column is not part of it
+    pub primary_key: i32,
+    /// `true` if the column is an `INTEGER PRIMARY KEY AUTOINCREMENT`
+    pub autoincrement: bool,
+    /// Default value, as an SQL expression
+    pub default: Option<String>,
+}
+
+/// Description of an index
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Index {
+    /// Name
+    pub name: String,
+    /// `true` for a `UNIQUE` index
+    pub unique: bool,
+    /// How the index was created
+    pub origin: IndexOrigin,
+    /// `true` for a partial index (with a `WHERE` clause)
+    pub partial: bool,
+    /// Indexed columns, in key order. `None` stands for an expression.
+    pub columns: Vec<Option<String>>,
+    /// `CREATE INDEX` statement, `None` for an implicit index
+    pub sql: Option<String>,
+}
+
+/// How an [`Index`] was created
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IndexOrigin {
+    /// `CREATE INDEX`
+    CreateIndex,
+    /// `UNIQUE` constraint
+    Unique,
+    /// `PRIMARY KEY` constraint
+    PrimaryKey,
+}
+
+/// Description of a foreign key
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ForeignKey {
+    /// Referenced table
+    pub table: String,
+    /// Referencing columns
+    pub from: Vec<String>,
+    /// Referenced columns. `None` stands for the primary key of the
+    /// referenced table.
+    pub to: Vec<Option<String>>,
+    /// `ON UPDATE` action, e.g. `NO ACTION` or `CASCADE`
+    pub on_update: String,
+    /// `ON DELETE` action
+    pub on_delete: String,
+}
+
+/// Description of a trigger
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Trigger {
+    /// Name
+    pub name: String,
+    /// Table or view the trigger is attached to
+    pub table: String,
+    /// `CREATE TRIGGER` statement
+    pub sql: Option<String>,
+}
+
+/// Description of a database
+#[derive(Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Schema {
+    /// Tables, views and virtual tables, sorted by name
+    pub tables: Vec<Table>,
+    /// Triggers, sorted by name
+    pub triggers: Vec<Trigger>,
+}
+
+impl Schema {
+    /// Returns the table or view named `name`, ignoring ASCII case like
+    /// SQLite does.
+    pub fn table(&self, name: &str) -> Option<&Table> {
+        self.tables
+            .iter()
+            .find(|t| t.name.eq_ignore_ascii_case(name))
+    }
+}
+
+impl Connection {
+    /// `feature = "column_metadata"` Describes the tables, views and triggers
+    /// of database `db_name`.
+    ///
+    /// SQLite internal tables (`sqlite_%`) are skipped.
+    pub fn schema(&self, db_name: DatabaseName<'_>) -> Result<Schema> {
+        let mut schema = Schema::default();
+        for (kind, name, tbl_name, sql) in self.master_entries(db_name, None)? {
+            match kind.as_str() {
+                "table" | "view" => {
+                    let table = self.describe_table(db_name, &kind, name, sql)?;
+                    schema.tables.push(table);
+                }
+                "trigger" => schema.triggers.push(Trigger {
+                    name,
+                    table: tbl_name,
+                    sql,
+                }),
+                _ => {}
+            }
+        }
+        Ok(schema)
+    }
+
+    /// `feature = "column_metadata"` Describes the table or view `name` of
+    /// database `db_name`, or returns `None` if there is no such table.
+    pub fn table_schema(&self, db_name: DatabaseName<'_>, name: &str) -> Result<Option<Table>> {
+        for (kind, name, _, sql) in self.master_entries(db_name, Some(name))? {
+            if kind == "table" || kind == "view" {
+                return self.describe_table(db_name, &kind, name, sql).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Schema objects, sorted by name
+    fn master_entries(
+        &self,
+        db_name: DatabaseName<'_>,
+        name: Option<&str>,
+    ) -> Result<Vec<MasterEntry>> {
+        let mut sql = Sql::new();
+        sql.push_sql("SELECT type, name, tbl_name, sql FROM ");
+        sql.push_schema_name(db_name);
+        sql.push_sql(".sqlite_master WHERE name NOT LIKE 'sqlite\\_%' ESCAPE '\\'");
+        if name.is_some() {
+            sql.push_sql(" AND name = ?1 COLLATE NOCASE");
+        }
+        sql.push_sql(" ORDER BY name");
+        let mut stmt = self.prepare(&sql)?;
+        let rows = match name {
+            Some(name) => stmt.query([name])?,
+            None => stmt.query([])?,
+        };
+        rows.mapped(|row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .collect()
+    }
+
+    fn describe_table(
+        &self,
+        db_name: DatabaseName<'_>,
+        kind: &str,
+        name: String,
+        sql: Option<String>,
+    ) -> Result<Table> {
+        let kind = match sql {
+            _ if kind == "view" => TableKind::View,
+            Some(ref sql) if is_virtual(sql) => TableKind::Virtual,
+            _ => TableKind::Table,
+        };
+
+        let mut columns = Vec::new();
+        self.pragma(Some(db_name), "table_info", &name, |row| {
+            columns.push(Column {
+                name: row.get(1)?,
+                decl_type: row.get::<_, Option<String>>(2)?.filter(|t| !t.is_empty()),
+                collation: None,
+                not_null: row.get(3)?,
+                primary_key: row.get(5)?,
+                autoincrement: false,
+                default: row.get(4)?,
+            });
+            Ok(())
+        })?;
+        if kind != TableKind::View {
+            for column in &mut columns {
+                self.column_metadata(db_name, &name, column)?;
+            }
+        }
+
+        let mut indexes = Vec::new();
+        if kind == TableKind::Table {
+            self.pragma(Some(db_name), "index_list", &name, |row| {
+                let origin: String = row.get(3)?;
+                let origin = match origin.as_str() {
+                    "u" => IndexOrigin::Unique,
+                    "pk" => IndexOrigin::PrimaryKey,
+                    _ => IndexOrigin::CreateIndex,
+                };
+                indexes.push(Index {
+                    name: row.get(1)?,
+                    unique: row.get(2)?,
+                    origin,
+                    partial: row.get(4)?,
+                    columns: Vec::new(),
+                    sql: None,
+                });
+                Ok(())
+            })?;
+            // index_list reports the most recently created index first
+            indexes.reverse();
+            for index in &mut indexes {
+                let mut columns = Vec::new();
+                self.pragma(Some(db_name), "index_info", &index.name, |row| {
+                    columns.push(row.get(2)?);
+                    Ok(())
+                })?;
+                index.columns = columns;
+                if let Some((_, _, _, sql)) = self
+                    .master_entries(db_name, Some(&index.name))?
+                    .into_iter()
+                    .next()
+                {
+                    index.sql = sql;
+                }
+            }
+        }
+
+        let mut foreign_keys: Vec<(i32, ForeignKey)> = Vec::new();
+        if kind == TableKind::Table {
+            self.pragma(Some(db_name), "foreign_key_list", &name, |row| {
+                let id: i32 = row.get(0)?;
+                let (from, to) = (row.get(3)?, row.get(4)?);
+                match foreign_keys.last_mut() {
+                    Some((last, fk)) if *last == id => {
+                        fk.from.push(from);
+                        fk.to.push(to);
+                    }
+                    _ => foreign_keys.push((id, foreign_key(row, from, to)?)),
+                }
+                Ok(())
+            })?;
+            // foreign_key_list reports the last declared foreign key first
+            foreign_keys.reverse();
+        }
+
+        // The PRIMARY KEY of a WITHOUT ROWID table is reported by index_info
+        // as if it were an index; a rowid table has no such entry.
+        let mut without_rowid = false;
+        if kind == TableKind::Table {
+            self.pragma(Some(db_name), "index_info", &name, |_| {
+                without_rowid = true;
+                Ok(())
+            })?;
+        }
+
+        Ok(Table {
+            name,
+            kind,
+            without_rowid,
+            sql,
+            columns,
+            indexes,
+            foreign_keys: foreign_keys.into_iter().map(|(_, fk)| fk).collect(),
+        })
+    }
+
+    /// Completes `column` with `sqlite3_table_column_metadata`.
+    fn column_metadata(
+        &self,
+        db_name: DatabaseName<'_>,
+        table: &str,
+        column: &mut Column,
+    ) -> Result<()> {
+        let db_name = db_name.to_cstring()?;
+        let table = str_to_cstring(table)?;
+        let column_name = str_to_cstring(&column.name)?;
+        let mut data_type: *const c_char = ptr::null();
+        let mut coll_seq: *const c_char = ptr::null();
+        let mut not_null: c_int = 0;
+        let mut primary_key: c_int = 0;
+        let mut autoinc: c_int = 0;
+        let mut c = self.db.borrow_mut();
+        let r = unsafe {
+            ffi::sqlite3_table_column_metadata(
+                c.db(),
+                db_name.as_ptr(),
+                table.as_ptr(),
+                column_name.as_ptr(),
+                &mut data_type,
+                &mut coll_seq,
+                &mut not_null,
+                &mut primary_key,
+                &mut autoinc,
+            )
+        };
+        c.decode_result(r)?;
+        // The strings belong to SQLite and stay valid until the schema
+        // changes.
+        if !coll_seq.is_null() {
+            let coll_seq = unsafe { CStr::from_ptr(coll_seq) };
+            column.collation = Some(coll_seq.to_string_lossy().into_owned());
+        }
+        column.not_null = not_null != 0;
+        column.autoincrement = autoinc != 0;
+        Ok(())
+    }
+}
+
+fn foreign_key(row: &Row<'_>, from: String, to: Option<String>) -> Result<ForeignKey> {
+    Ok(ForeignKey {
+        table: row.get(2)?,
+        from: vec![from],
+        to: vec![to],
+        on_update: row.get(5)?,
+        on_delete: row.get(6)?,
+    })
+}
+
+/// `CREATE VIRTUAL TABLE ...`
+fn is_virtual(sql: &str) -> bool {
+    match sql.split_whitespace().nth(1) {
+        Some(word) => word.eq_ignore_ascii_case("VIRTUAL"),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{IndexOrigin, TableKind};
+    use crate::{Connection, Result, MAIN_DB, TEMP_DB};
+
+    fn db() -> Result<Connection> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "CREATE TABLE author(id INTEGER PRIMARY KEY AUTOINCREMENT,
+                                 name TEXT NOT NULL COLLATE NOCASE UNIQUE);
+             CREATE TABLE book(isbn TEXT, edition INT DEFAULT 1, author_id INT,
+                               PRIMARY KEY (isbn, edition),
+                               FOREIGN KEY (author_id) REFERENCES author ON DELETE CASCADE
+                              ) WITHOUT ROWID;
+             CREATE INDEX book_author ON book(author_id, lower(isbn)) WHERE author_id > 0;
+             CREATE VIEW author_name AS SELECT name FROM author;
+             CREATE TRIGGER author_del AFTER DELETE ON author BEGIN SELECT 1; END;",
+        )?;
+        Ok(db)
+    }
+
+    #[test]
+    fn test_schema() -> Result<()> {
+        let db = db()?;
+        let schema = db.schema(MAIN_DB)?;
+        let names: Vec<&str> = schema.tables.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(vec!["author", "author_name", "book"], names);
+        assert_eq!(1, schema.triggers.len());
+        assert_eq!("author_del", schema.triggers[0].name);
+        assert_eq!("author", schema.triggers[0].table);
+        assert_eq!(TableKind::View, schema.table("AUTHOR_NAME").unwrap().kind);
+        assert!(db.schema(TEMP_DB)?.tables.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_columns() -> Result<()> {
+        let db = db()?;
+        let author = db.table_schema(MAIN_DB, "author")?.unwrap();
+        assert_eq!(TableKind::Table, author.kind);
+        assert!(!author.without_rowid);
+        let id = author.column("id").unwrap();
+        assert_eq!(Some("INTEGER"), id.decl_type.as_deref());
+        assert_eq!(1, id.primary_key);
+        assert!(id.autoincrement);
+        let name = author.column("name").unwrap();
+        assert_eq!(Some("NOCASE"), name.collation.as_deref());
+        assert!(name.not_null);
+        assert!(!name.autoincrement);
+
+        let book = db.table_schema(MAIN_DB, "book")?.unwrap();
+        assert!(book.without_rowid);
+        let pk: Vec<&str> = book.primary_key().iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(vec!["isbn", "edition"], pk);
+        assert_eq!(
+            Some("1"),
+            book.column("edition").unwrap().default.as_deref()
+        );
+        assert_eq!(Some("BINARY"), book.columns[0].collation.as_deref());
+
+        let view = db.table_schema(MAIN_DB, "author_name")?.unwrap();
+        assert_eq!(1, view.columns.len());
+        assert_eq!(None, view.columns[0].collation);
+
+        assert!(db.table_schema(MAIN_DB, "missing")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_indexes_and_foreign_keys() -> Result<()> {
+        let db = db()?;
+        let author = db.table_schema(MAIN_DB, "author")?.unwrap();
+        assert_eq!(1, author.indexes.len());
+        assert_eq!(IndexOrigin::Unique, author.indexes[0].origin);
+        assert!(author.indexes[0].unique);
+        assert_eq!(None, author.indexes[0].sql);
+
+        let book = db.table_schema(MAIN_DB, "book")?.unwrap();
+        let index = book
+            .indexes
+            .iter()
+            .find(|i| i.name == "book_author")
+            .unwrap();
+        assert_eq!(IndexOrigin::CreateIndex, index.origin);
+        assert!(index.partial);
+        assert!(!index.unique);
+        assert_eq!(vec![Some("author_id".to_owned()), None], index.columns);
+        assert!(index.sql.is_some());
+
+        assert_eq!(1, book.foreign_keys.len());
+        let fk = &book.foreign_keys[0];
+        assert_eq!("author", fk.table);
+        assert_eq!(vec!["author_id".to_owned()], fk.from);
+        assert_eq!(vec![None], fk.to);
+        assert_eq!("CASCADE", fk.on_delete);
+        assert_eq!("NO ACTION", fk.on_update);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "csvtab")]
+    fn test_virtual_table() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        crate::vtab::csvtab::load_module(&db)?;
+        db.execute_batch("CREATE VIRTUAL TABLE vtab USING csv(filename='test.csv', header=yes)")?;
+        let table = db.table_schema(MAIN_DB, "vtab")?.unwrap();
+        assert_eq!(TableKind::Virtual, table.kind);
+        assert!(table.indexes.is_empty());
+        Ok(())
+    }
+}
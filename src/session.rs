@@ -1,6 +1,7 @@
 //! `feature = "session"` [Session Extension](https://sqlite.org/sessionintro.html)
 #![allow(non_camel_case_types)]
 
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::CStr;
 use std::io::{Read, Write};
 use std::marker::PhantomData;
@@ -14,8 +15,9 @@ use fallible_streaming_iterator::FallibleStreamingIterator;
 use crate::error::error_from_sqlite_code;
 use crate::ffi;
 use crate::hooks::Action;
-use crate::types::ValueRef;
-use crate::{errmsg_to_string, str_to_cstring, Connection, DatabaseName, Result};
+use crate::pragma::Sql;
+use crate::types::{Value, ValueRef};
+use crate::{errmsg_to_string, str_to_cstring, Connection, DatabaseName, Error, Result};
 
 // https://sqlite.org/session.html
 
@@ -262,6 +264,316 @@ pub fn concat_strm(
     Ok(())
 }
 
+/// `feature = "session"` Transform the changeset read from `input` and write
+/// the result to `output`.
+///
+/// See [`Changeset::filter_map`].
+pub fn filter_map_strm<F>(input: &mut dyn Read, output: &mut dyn Write, f: F) -> Result<()>
+where
+    F: FnMut(Change) -> Result<Option<Change>>,
+{
+    // Peek at the first table header, then give it back to the iterator.
+    let mut header = [0u8; 1];
+    let n = input
+        .read(&mut header)
+        .map_err(|e| error_from_sqlite_code(ffi::SQLITE_IOERR_READ, Some(e.to_string())))?;
+    check_not_patchset(&header[..n])?;
+    let mut chained = (&header[..n]).chain(input);
+    let input: &mut dyn Read = &mut chained;
+    let input_ref = &input;
+    let iter = ChangesetIter::start_strm(input_ref)?;
+    filter_map_changes(iter, output, f)
+}
+
+/// Each table in a patchset starts with 'P' instead of 'T'.
+fn check_not_patchset(bytes: &[u8]) -> Result<()> {
+    if bytes.first() == Some(&b'P') {
+        return Err(error_from_sqlite_code(
+            ffi::SQLITE_MISUSE,
+            Some("patchsets are not supported".to_owned()),
+        ));
+    }
+    Ok(())
+}
+
+/// `feature = "session"` Count the changes made to each table by the
+/// changeset read from `input`.
+#[inline]
+pub fn summary_strm(input: &mut dyn Read) -> Result<BTreeMap<String, TableSummary>> {
+    let input_ref = &input;
+    summarize(ChangesetIter::start_strm(input_ref)?)
+}
+
+fn filter_map_changes<F>(
+    mut iter: ChangesetIter<'_>,
+    output: &mut dyn Write,
+    mut f: F,
+) -> Result<()>
+where
+    F: FnMut(Change) -> Result<Option<Change>>,
+{
+    let mut writer = ChangesetWriter {
+        output,
+        table: None,
+    };
+    while let Some(item) = iter.next()? {
+        if let Some(change) = f(Change::from_item(item)?)? {
+            writer.write(&change)?;
+        }
+    }
+    Ok(())
+}
+
+fn summarize(mut iter: ChangesetIter<'_>) -> Result<BTreeMap<String, TableSummary>> {
+    let mut summaries: BTreeMap<String, TableSummary> = BTreeMap::new();
+    while let Some(item) = iter.next()? {
+        let op = item.op()?;
+        let summary = summaries.entry(op.table_name().to_owned()).or_default();
+        match op.code() {
+            Action::SQLITE_INSERT => summary.inserts += 1,
+            Action::SQLITE_UPDATE => summary.updates += 1,
+            Action::SQLITE_DELETE => summary.deletes += 1,
+            Action::UNKNOWN => {}
+        };
+        if op.indirect() {
+            summary.indirect += 1;
+        }
+    }
+    Ok(summaries)
+}
+
+/// `feature = "session"` Number of changes made to one table.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct TableSummary {
+    /// Number of inserted rows
+    pub inserts: usize,
+    /// Number of updated rows
+    pub updates: usize,
+    /// Number of deleted rows
+    pub deletes: usize,
+    /// Number of changes flagged as indirect
+    pub indirect: usize,
+}
+
+/// `feature = "session"` An owned copy of a change, which can be modified
+/// before being written to a new changeset by [`Changeset::filter_map`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    table_name: String,
+    pk: Vec<u8>,
+    code: Action,
+    indirect: bool,
+    old: Vec<Option<Value>>,
+    new: Vec<Option<Value>>,
+}
+
+impl Change {
+    fn from_item(item: &ChangesetItem) -> Result<Change> {
+        let op = item.op()?;
+        let n = op.number_of_columns() as usize;
+        let mut old = vec![None; n];
+        let mut new = vec![None; n];
+        for col in 0..n {
+            if op.code() != Action::SQLITE_INSERT {
+                old[col] = item.old_value_if_set(col)?.map(Value::from);
+            }
+            if op.code() != Action::SQLITE_DELETE {
+                new[col] = item.new_value_if_set(col)?.map(Value::from);
+            }
+        }
+        Ok(Change {
+            table_name: op.table_name().to_owned(),
+            pk: item.pk()?.to_vec(),
+            code: op.code(),
+            indirect: op.indirect(),
+            old,
+            new,
+        })
+    }
+
+    /// Returns the table name.
+    #[inline]
+    pub fn table_name(&self) -> &str {
+        &self.table_name
+    }
+
+    /// Move the change to another table.
+    #[inline]
+    pub fn set_table_name<S: Into<String>>(&mut self, table_name: S) {
+        self.table_name = table_name.into();
+    }
+
+    /// Returns the number of columns in table
+    #[inline]
+    pub fn number_of_columns(&self) -> usize {
+        self.pk.len()
+    }
+
+    /// Returns the primary key definition of the table.
+    #[inline]
+    pub fn pk(&self) -> &[u8] {
+        &self.pk
+    }
+
+    /// Returns the action code.
+    #[inline]
+    pub fn code(&self) -> Action {
+        self.code
+    }
+
+    /// Returns `true` for an 'indirect' change.
+    #[inline]
+    pub fn indirect(&self) -> bool {
+        self.indirect
+    }
+
+    /// Set or clear the indirect flag.
+    #[inline]
+    pub fn set_indirect(&mut self, indirect: bool) {
+        self.indirect = indirect;
+    }
+
+    /// Returns the old.* value of column `col`, if any.
+    ///
+    /// Only `SQLITE_DELETE` and `SQLITE_UPDATE` have old values, and an
+    /// update only has them for the primary key and the modified columns.
+    #[inline]
+    pub fn old_value(&self, col: usize) -> Option<&Value> {
+        self.old[col].as_ref()
+    }
+
+    /// Returns the new.* value of column `col`, if any.
+    ///
+    /// Only `SQLITE_INSERT` and `SQLITE_UPDATE` have new values, and an
+    /// update only has them for the modified columns.
+    #[inline]
+    pub fn new_value(&self, col: usize) -> Option<&Value> {
+        self.new[col].as_ref()
+    }
+
+    /// Replace the old.* value of column `col`.
+    #[inline]
+    pub fn set_old_value(&mut self, col: usize, value: Option<Value>) {
+        self.old[col] = value;
+    }
+
+    /// Replace the new.* value of column `col`.
+    #[inline]
+    pub fn set_new_value(&mut self, col: usize, value: Option<Value>) {
+        self.new[col] = value;
+    }
+
+    /// Remove column `col`, e.g. to strip a column that the recipient of the
+    /// changeset must not see or does not have.
+    ///
+    /// # Failure
+    ///
+    /// Will return Err if `col` is part of the primary key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `col` is out of bounds.
+    pub fn remove_column(&mut self, col: usize) -> Result<()> {
+        if self.pk[col] != 0 {
+            return Err(error_from_sqlite_code(
+                ffi::SQLITE_MISUSE,
+                Some(format!("column {} is part of the primary key", col)),
+            ));
+        }
+        self.pk.remove(col);
+        self.old.remove(col);
+        self.new.remove(col);
+        Ok(())
+    }
+}
+
+/// Writes changes using the changeset format described in `sqlite3session.c`.
+struct ChangesetWriter<'a> {
+    output: &'a mut dyn Write,
+    /// Header of the last table written
+    table: Option<(String, Vec<u8>)>,
+}
+
+impl ChangesetWriter<'_> {
+    fn write(&mut self, change: &Change) -> Result<()> {
+        let mut buf = Vec::new();
+        let (old, new) = match change.code {
+            Action::SQLITE_INSERT => (None, Some(&change.new)),
+            Action::SQLITE_UPDATE => {
+                if change.new.iter().all(Option::is_none) {
+                    return Ok(());
+                }
+                (Some(&change.old), Some(&change.new))
+            }
+            Action::SQLITE_DELETE => (Some(&change.old), None),
+            Action::UNKNOWN => return Ok(()),
+        };
+        let same_table = match self.table {
+            Some((ref table_name, ref pk)) => *table_name == change.table_name && *pk == change.pk,
+            None => false,
+        };
+        if !same_table {
+            buf.push(b'T');
+            put_varint(&mut buf, change.pk.len());
+            buf.extend_from_slice(&change.pk);
+            buf.extend_from_slice(change.table_name.as_bytes());
+            buf.push(0);
+            self.table = Some((change.table_name.clone(), change.pk.clone()));
+        }
+        buf.push(change.code as u8);
+        buf.push(change.indirect as u8);
+        for record in old.iter().chain(new.iter()) {
+            for value in record.iter() {
+                put_value(&mut buf, value.as_ref());
+            }
+        }
+        self.output
+            .write_all(&buf)
+            .map_err(|e| error_from_sqlite_code(ffi::SQLITE_IOERR_WRITE, Some(e.to_string())))
+    }
+}
+
+fn put_value(buf: &mut Vec<u8>, value: Option<&Value>) {
+    match value {
+        None => buf.push(0),
+        Some(Value::Integer(i)) => {
+            buf.push(ffi::SQLITE_INTEGER as u8);
+            buf.extend_from_slice(&i.to_be_bytes());
+        }
+        Some(Value::Real(r)) => {
+            buf.push(ffi::SQLITE_FLOAT as u8);
+            buf.extend_from_slice(&r.to_bits().to_be_bytes());
+        }
+        Some(Value::Text(s)) => {
+            buf.push(ffi::SQLITE_TEXT as u8);
+            put_varint(buf, s.len());
+            buf.extend_from_slice(s.as_bytes());
+        }
+        Some(Value::Blob(b)) => {
+            buf.push(ffi::SQLITE_BLOB as u8);
+            put_varint(buf, b.len());
+            buf.extend_from_slice(b);
+        }
+        Some(Value::Null) => buf.push(ffi::SQLITE_NULL as u8),
+    }
+}
+
+/// SQLite variable-length integer: big-endian groups of 7 bits, with the
+/// high bit set on all bytes but the last.
+fn put_varint(buf: &mut Vec<u8>, mut v: usize) {
+    let mut tmp = [0u8; 10];
+    let mut i = tmp.len() - 1;
+    tmp[i] = (v & 0x7f) as u8;
+    v >>= 7;
+    while v != 0 {
+        i -= 1;
+        tmp[i] = (v & 0x7f) as u8 | 0x80;
+        v >>= 7;
+    }
+    buf.extend_from_slice(&tmp[i..]);
+}
+
 /// `feature = "session"` Changeset or Patchset
 pub struct Changeset {
     cs: *mut c_void,
@@ -302,6 +614,53 @@ impl Changeset {
         });
         Ok(Changeset { cs, n })
     }
+
+    /// Build a new changeset by passing each change through `f`.
+    ///
+    /// `f` returns the change, possibly modified, to keep it or `None` to
+    /// drop it. Updates left without any new value (e.g. because the only
+    /// modified column has been removed) are dropped too.
+    ///
+    /// # Failure
+    ///
+    /// Will return Err if `self` is a patchset, as patchsets do not carry
+    /// the values needed to rewrite their changes.
+    pub fn filter_map<F>(&self, f: F) -> Result<Changeset>
+    where
+        F: FnMut(Change) -> Result<Option<Change>>,
+    {
+        if self.n > 0 {
+            check_not_patchset(unsafe { from_raw_parts(self.cs as *const u8, 1) })?;
+        }
+        let mut output = Vec::new();
+        filter_map_changes(self.iter()?, &mut output, f)?;
+        Changeset::from_bytes(&output)
+    }
+
+    /// Count the changes made to each table
+    #[inline]
+    pub fn summary(&self) -> Result<BTreeMap<String, TableSummary>> {
+        summarize(self.iter()?)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Changeset> {
+        if bytes.is_empty() {
+            return Ok(Changeset {
+                cs: ptr::null_mut(),
+                n: 0,
+            });
+        }
+        if bytes.len() > c_int::MAX as usize {
+            return Err(error_from_sqlite_code(ffi::SQLITE_TOOBIG, None));
+        }
+        let n = bytes.len() as c_int;
+        let cs = unsafe { ffi::sqlite3_malloc(n) };
+        if cs.is_null() {
+            return Err(error_from_sqlite_code(ffi::SQLITE_NOMEM, None));
+        }
+        unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), cs as *mut u8, bytes.len()) };
+        Ok(Changeset { cs, n })
+    }
 }
 
 impl Drop for Changeset {
@@ -474,6 +833,36 @@ impl ChangesetItem {
         }
     }
 
+    /// Obtain old.* Values, or `None` for a column left unchanged by an
+    /// `SQLITE_UPDATE`.
+    #[inline]
+    pub(crate) fn old_value_if_set(&self, col: usize) -> Result<Option<ValueRef<'_>>> {
+        unsafe {
+            let mut p_value: *mut ffi::sqlite3_value = ptr::null_mut();
+            check!(ffi::sqlite3changeset_old(self.it, col as i32, &mut p_value,));
+            if p_value.is_null() {
+                Ok(None)
+            } else {
+                Ok(Some(ValueRef::from_value(p_value)))
+            }
+        }
+    }
+
+    /// Obtain new.* Values, or `None` for a column left unchanged by an
+    /// `SQLITE_UPDATE`.
+    #[inline]
+    pub(crate) fn new_value_if_set(&self, col: usize) -> Result<Option<ValueRef<'_>>> {
+        unsafe {
+            let mut p_value: *mut ffi::sqlite3_value = ptr::null_mut();
+            check!(ffi::sqlite3changeset_new(self.it, col as i32, &mut p_value,));
+            if p_value.is_null() {
+                Ok(None)
+            } else {
+                Ok(Some(ValueRef::from_value(p_value)))
+            }
+        }
+    }
+
     /// Obtain the current operation
     #[inline]
     pub fn op(&self) -> Result<Operation<'_>> {
@@ -661,6 +1050,292 @@ impl Connection {
     }
 }
 
+impl Connection {
+    /// `feature = "session"` Render a changeset as the equivalent sequence of
+    /// `INSERT`, `UPDATE` and `DELETE` statements.
+    ///
+    /// Column names are looked up in the `main` schema of this connection,
+    /// which must contain every table referenced by the changeset. Rows are
+    /// identified by their primary key (see [`ChangesetItem::pk`]).
+    #[inline]
+    pub fn changeset_to_sql(&self, cs: &Changeset) -> Result<Vec<String>> {
+        self.changes_to_sql(cs.iter()?)
+    }
+
+    /// `feature = "session"` Render the changeset read from `input` as the
+    /// equivalent sequence of SQL statements.
+    ///
+    /// See [`Connection::changeset_to_sql`].
+    #[inline]
+    pub fn changeset_to_sql_strm(&self, input: &mut dyn Read) -> Result<Vec<String>> {
+        let input_ref = &input;
+        self.changes_to_sql(ChangesetIter::start_strm(input_ref)?)
+    }
+
+    fn changes_to_sql(&self, mut iter: ChangesetIter<'_>) -> Result<Vec<String>> {
+        let mut columns: HashMap<String, Vec<String>> = HashMap::new();
+        let mut statements = Vec::new();
+        while let Some(item) = iter.next()? {
+            let op = item.op()?;
+            let table_name = op.table_name();
+            if !columns.contains_key(table_name) {
+                let names = self.table_column_names(table_name)?;
+                columns.insert(table_name.to_owned(), names);
+            }
+            let names = &columns[table_name];
+            if names.len() != op.number_of_columns() as usize {
+                return Err(Error::SqliteFailure(
+                    ffi::Error::new(ffi::SQLITE_SCHEMA),
+                    Some(format!(
+                        "table {} has {} columns but the changeset expects {}",
+                        table_name,
+                        names.len(),
+                        op.number_of_columns()
+                    )),
+                ));
+            }
+            statements.push(item_to_sql(item, &op, names)?);
+        }
+        Ok(statements)
+    }
+
+    pub(crate) fn table_column_names(&self, table_name: &str) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        self.pragma(Some(DatabaseName::Main), "table_info", &table_name, |row| {
+            names.push(row.get(1)?);
+            Ok(())
+        })?;
+        if names.is_empty() {
+            return Err(Error::SqliteFailure(
+                ffi::Error::new(ffi::SQLITE_SCHEMA),
+                Some(format!("no such table: {}", table_name)),
+            ));
+        }
+        Ok(names)
+    }
+}
+
+impl Connection {
+    /// `feature = "session"` Compute the changes that would make every table
+    /// of `from` identical to the same table in `to`, like the `sqldiff`
+    /// utility.
+    ///
+    /// Only tables with a primary key and the same columns and primary key
+    /// definition in both databases are compared. Other tables, including
+    /// virtual tables, are skipped: see [`Connection::diff_warnings`]. The
+    /// shadow tables backing a virtual table (e.g. for FTS5) are compared like
+    /// any other table. Like in SQL, table and column names are matched case
+    /// insensitively.
+    pub fn diff_databases(
+        &self,
+        from: DatabaseName<'_>,
+        to: DatabaseName<'_>,
+    ) -> Result<Changeset> {
+        let (tables, _) = self.diff_tables(from, to)?;
+        let mut session = Session::new_with_name(self, to)?;
+        for table in &tables {
+            // Despite its documentation, `sqlite3session_diff` does nothing
+            // for a table which is not attached yet.
+            session.attach(Some(table))?;
+            session.diff(from, table)?;
+        }
+        session.changeset()
+    }
+
+    /// `feature = "session"` Tables skipped by
+    /// [`Connection::diff_databases`], ordered by table name within each kind
+    /// of warning.
+    pub fn diff_warnings(
+        &self,
+        from: DatabaseName<'_>,
+        to: DatabaseName<'_>,
+    ) -> Result<Vec<DiffWarning>> {
+        self.diff_tables(from, to).map(|(_, warnings)| warnings)
+    }
+
+    /// Names in `to` of the tables to compare, and warnings about the others
+    fn diff_tables(
+        &self,
+        from: DatabaseName<'_>,
+        to: DatabaseName<'_>,
+    ) -> Result<(Vec<String>, Vec<DiffWarning>)> {
+        let from_tables = self.table_names(from)?;
+        let to_tables = self.table_names(to)?;
+        let mut tables = Vec::new();
+        let mut warnings = Vec::new();
+        for (key, (table, is_virtual)) in &to_tables {
+            let from_table = from_tables.get(key);
+            if *is_virtual || matches!(from_table, Some((_, true))) {
+                warnings.push(DiffWarning::VirtualTable(table.clone()));
+                continue;
+            }
+            let from_table = match from_table {
+                Some((from_table, _)) => from_table,
+                None => {
+                    warnings.push(DiffWarning::OnlyInTo(table.clone()));
+                    continue;
+                }
+            };
+            let from_columns = self.table_columns(from, from_table)?;
+            let to_columns = self.table_columns(to, table)?;
+            if from_columns != to_columns {
+                warnings.push(DiffWarning::SchemaMismatch(table.clone()));
+            } else if to_columns.iter().all(|&(_, pk)| pk == 0) {
+                warnings.push(DiffWarning::NoPrimaryKey(table.clone()));
+            } else {
+                tables.push(table.clone());
+            }
+        }
+        for (key, (table, is_virtual)) in &from_tables {
+            if to_tables.contains_key(key) {
+                continue;
+            }
+            warnings.push(if *is_virtual {
+                DiffWarning::VirtualTable(table.clone())
+            } else {
+                DiffWarning::OnlyInFrom(table.clone())
+            });
+        }
+        Ok((tables, warnings))
+    }
+
+    /// Table names, and whether each one is a virtual table, by lower case
+    /// name
+    fn table_names(&self, db_name: DatabaseName<'_>) -> Result<BTreeMap<String, (String, bool)>> {
+        let mut sql = Sql::new();
+        sql.push_sql("SELECT name, sql LIKE 'CREATE VIRTUAL %' FROM ");
+        sql.push_schema_name(db_name);
+        sql.push_sql(
+            ".sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\'",
+        );
+        let mut stmt = self.prepare(&sql)?;
+        let names = stmt.query_map([], |row| {
+            let name: String = row.get(0)?;
+            Ok((name.to_ascii_lowercase(), (name, row.get(1)?)))
+        })?;
+        names.collect()
+    }
+
+    /// Lower case column names and primary key positions
+    fn table_columns(&self, db_name: DatabaseName<'_>, table: &str) -> Result<Vec<(String, i32)>> {
+        let mut columns = Vec::new();
+        self.pragma(Some(db_name), "table_info", &table, |row| {
+            let name: String = row.get(1)?;
+            columns.push((name.to_ascii_lowercase(), row.get(5)?));
+            Ok(())
+        })?;
+        Ok(columns)
+    }
+}
+
+/// `feature = "session"` A table skipped by [`Connection::diff_databases`]
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum DiffWarning {
+    /// The table only exists in the `from` database.
+    OnlyInFrom(String),
+    /// The table only exists in the `to` database.
+    OnlyInTo(String),
+    /// The table has different columns or primary key definitions in the
+    /// two databases.
+    SchemaMismatch(String),
+    /// The table has no primary key.
+    NoPrimaryKey(String),
+    /// The table is a virtual table in either database.
+    VirtualTable(String),
+}
+
+fn item_to_sql(item: &ChangesetItem, op: &Operation<'_>, columns: &[String]) -> Result<String> {
+    let pk = item.pk()?;
+    let mut sql = Sql::new();
+    match op.code() {
+        Action::SQLITE_INSERT => {
+            sql.push_sql("INSERT INTO ");
+            sql.push_quoted_identifier(op.table_name());
+            sql.open_brace();
+            for (i, column) in columns.iter().enumerate() {
+                if i > 0 {
+                    sql.push_comma();
+                }
+                sql.push_quoted_identifier(column);
+            }
+            sql.close_brace();
+            sql.push_sql(" VALUES");
+            sql.open_brace();
+            for i in 0..columns.len() {
+                if i > 0 {
+                    sql.push_comma();
+                }
+                sql.push_literal(item.new_value(i)?)?;
+            }
+            sql.close_brace();
+        }
+        Action::SQLITE_UPDATE => {
+            sql.push_sql("UPDATE ");
+            sql.push_quoted_identifier(op.table_name());
+            sql.push_sql(" SET ");
+            let mut first = true;
+            for (i, column) in columns.iter().enumerate() {
+                // unchanged columns have no new value
+                if let Some(value) = item.new_value_if_set(i)? {
+                    if !first {
+                        sql.push_comma();
+                    }
+                    first = false;
+                    sql.push_quoted_identifier(column);
+                    sql.push_equal_sign();
+                    sql.push_literal(value)?;
+                }
+            }
+            push_pk_condition(&mut sql, pk, columns, |i| item.old_value(i))?;
+        }
+        Action::SQLITE_DELETE => {
+            sql.push_sql("DELETE FROM ");
+            sql.push_quoted_identifier(op.table_name());
+            push_pk_condition(&mut sql, pk, columns, |i| item.old_value(i))?;
+        }
+        Action::UNKNOWN => {
+            return Err(Error::SqliteFailure(
+                ffi::Error::new(ffi::SQLITE_MISUSE),
+                Some(format!("unknown operation on table {}", op.table_name())),
+            ));
+        }
+    };
+    Ok(sql.as_str().to_owned())
+}
+
+fn push_pk_condition<'a, F>(
+    sql: &mut Sql,
+    pk: &[u8],
+    columns: &[String],
+    old_value: F,
+) -> Result<()>
+where
+    F: Fn(usize) -> Result<ValueRef<'a>>,
+{
+    sql.push_sql(" WHERE ");
+    let mut first = true;
+    for (i, column) in columns.iter().enumerate() {
+        if pk[i] == 0 {
+            continue;
+        }
+        if !first {
+            sql.push_sql(" AND ");
+        }
+        first = false;
+        sql.push_quoted_identifier(column);
+        sql.push_equal_sign();
+        sql.push_literal(old_value(i)?)?;
+    }
+    if first {
+        return Err(Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_MISUSE),
+            Some("changeset table has no primary key".to_owned()),
+        ));
+    }
+    Ok(())
+}
+
 /// `feature = "session"` Constants passed to the conflict handler
 /// See [here](https://sqlite.org/session.html#SQLITE_CHANGESET_CONFLICT) for details.
 #[allow(missing_docs)]
@@ -779,9 +1454,11 @@ mod test {
     use std::io::Read;
     use std::sync::atomic::{AtomicBool, Ordering};
 
-    use super::{Changeset, ChangesetIter, ConflictAction, ConflictType, Session};
+    use super::{
+        Changegroup, Changeset, ChangesetIter, ConflictAction, ConflictType, DiffWarning, Session,
+    };
     use crate::hooks::Action;
-    use crate::{Connection, Result};
+    use crate::{Connection, DatabaseName, Result};
 
     fn one_changeset() -> Result<Changeset> {
         let db = Connection::open_in_memory()?;
@@ -906,6 +1583,273 @@ mod test {
         Ok(())
     }
 
+    fn changes_fixture() -> Result<(Connection, Changeset)> {
+        let schema = "CREATE TABLE foo(id INTEGER PRIMARY KEY, \"order\" TEXT, x REAL, b BLOB);
+                      INSERT INTO foo VALUES(1, 'one', 1.0, NULL);
+                      INSERT INTO foo VALUES(2, 'two', 2.5, x'00');";
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(schema)?;
+
+        let mut session = Session::new(&db)?;
+        session.attach(None)?;
+        db.execute_batch(
+            "INSERT INTO foo VALUES(3, 'it''s', 3.0, x'CAFE');
+             UPDATE foo SET x = 10.0, b = NULL WHERE id = 2;
+             DELETE FROM foo WHERE id = 1;",
+        )?;
+        let changeset = session.changeset()?;
+        drop(session);
+
+        let target = Connection::open_in_memory()?;
+        target.execute_batch(schema)?;
+        Ok((target, changeset))
+    }
+
+    type FooRow = (i64, String, f64, Option<Vec<u8>>);
+
+    fn dump(db: &Connection) -> Result<Vec<FooRow>> {
+        let mut stmt = db.prepare("SELECT * FROM foo ORDER BY id")?;
+        let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))?;
+        rows.collect()
+    }
+
+    #[test]
+    fn test_changeset_to_sql() -> Result<()> {
+        let (db, changeset) = changes_fixture()?;
+        let statements = db.changeset_to_sql(&changeset)?;
+        // changes are not ordered by time
+        let mut sorted = statements.clone();
+        sorted.sort();
+        assert_eq!(
+            vec![
+                r#"DELETE FROM "foo" WHERE "id"=1"#,
+                r#"INSERT INTO "foo"("id","order","x","b") VALUES(3,'it''s',3.0,X'CAFE')"#,
+                r#"UPDATE "foo" SET "x"=10.0,"b"=NULL WHERE "id"=2"#,
+            ],
+            sorted
+        );
+
+        for sql in &statements {
+            db.execute_batch(sql)?;
+        }
+        let (applied, _) = changes_fixture()?;
+        applied.apply(
+            &changeset,
+            None::<fn(&str) -> bool>,
+            |_conflict_type, _item| ConflictAction::SQLITE_CHANGESET_ABORT,
+        )?;
+        assert_eq!(dump(&applied)?, dump(&db)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_changeset_to_sql_strm() -> Result<()> {
+        let output = one_changeset_strm()?;
+
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("CREATE TABLE foo(t TEXT PRIMARY KEY NOT NULL);")?;
+        let statements = db.changeset_to_sql_strm(&mut output.as_slice())?;
+        assert_eq!(vec![r#"INSERT INTO "foo"("t") VALUES('bar')"#], statements);
+        Ok(())
+    }
+
+    #[test]
+    fn test_changeset_to_sql_schema_mismatch() -> Result<()> {
+        let changeset = one_changeset()?;
+
+        let db = Connection::open_in_memory()?;
+        assert!(db.changeset_to_sql(&changeset).is_err());
+        db.execute_batch("CREATE TABLE foo(t TEXT PRIMARY KEY NOT NULL, extra);")?;
+        assert!(db.changeset_to_sql(&changeset).is_err());
+        Ok(())
+    }
+
+    fn people_changeset(db: &Connection) -> Result<Vec<u8>> {
+        db.execute_batch(
+            "CREATE TABLE people(id INTEGER PRIMARY KEY, name TEXT, ssn TEXT);
+             CREATE TABLE audit(id INTEGER PRIMARY KEY, msg TEXT);
+             INSERT INTO people VALUES(1, 'alice', '111');
+             INSERT INTO people VALUES(2, 'bob', '222');",
+        )?;
+        let mut session = Session::new(db)?;
+        session.attach(None)?;
+        db.execute_batch(
+            "INSERT INTO people VALUES(3, 'carol', '333');
+             UPDATE people SET ssn = '999' WHERE id = 1;
+             INSERT INTO audit VALUES(1, 'carol added');",
+        )?;
+        session.set_indirect(true);
+        db.execute_batch("UPDATE people SET name = 'robert' WHERE id = 2;")?;
+
+        let mut output = Vec::new();
+        session.changeset_strm(&mut output)?;
+        Ok(output)
+    }
+
+    #[test]
+    fn test_filter_map_identity() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        let input = people_changeset(&db)?;
+
+        let mut output = Vec::new();
+        super::filter_map_strm(&mut input.as_slice(), &mut output, |change| {
+            Ok(Some(change))
+        })?;
+        assert_eq!(input, output);
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_map() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        let input = people_changeset(&db)?;
+        let changeset = Changegroup::new().and_then(|mut group| {
+            group.add_stream(&mut input.as_slice())?;
+            group.output()
+        })?;
+
+        let filtered = changeset.filter_map(|mut change| {
+            if change.table_name() != "people" {
+                return Ok(None);
+            }
+            assert!(change.remove_column(0).is_err());
+            change.remove_column(2)?;
+            change.set_table_name("users");
+            Ok(Some(change))
+        })?;
+
+        let summary = filtered.summary()?;
+        assert_eq!(1, summary.len());
+        let users = summary["users"];
+        assert_eq!(
+            (1, 1, 0, 1),
+            (users.inserts, users.updates, users.deletes, users.indirect)
+        );
+
+        let mut iter = filtered.iter()?;
+        while let Some(item) = iter.next()? {
+            let op = item.op()?;
+            assert_eq!(2, op.number_of_columns());
+            assert_eq!(op.code() == Action::SQLITE_UPDATE, op.indirect());
+        }
+
+        let peer = Connection::open_in_memory()?;
+        peer.execute_batch(
+            "CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT);
+             INSERT INTO users VALUES(1, 'alice');
+             INSERT INTO users VALUES(2, 'bob');",
+        )?;
+        peer.apply(
+            &filtered,
+            None::<fn(&str) -> bool>,
+            |_conflict_type, _item| ConflictAction::SQLITE_CHANGESET_ABORT,
+        )?;
+        let mut stmt = peer.prepare("SELECT name FROM users ORDER BY id")?;
+        let names: Vec<String> = stmt.query_map([], |r| r.get(0))?.collect::<Result<_>>()?;
+        assert_eq!(vec!["alice", "robert", "carol"], names);
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_map_patchset() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        people_changeset(&db)?;
+        let mut session = Session::new(&db)?;
+        session.attach(None)?;
+        db.execute_batch("DELETE FROM people WHERE id = 1;")?;
+        let patchset = session.patchset()?;
+
+        assert!(patchset.filter_map(|change| Ok(Some(change))).is_err());
+        let mut input = Vec::new();
+        session.patchset_strm(&mut input)?;
+        let mut output = Vec::new();
+        let r = super::filter_map_strm(&mut input.as_slice(), &mut output, |change| {
+            Ok(Some(change))
+        });
+        assert!(r.is_err());
+        assert!(output.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_summary_strm() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        let input = people_changeset(&db)?;
+
+        let summary = super::summary_strm(&mut input.as_slice())?;
+        assert_eq!(2, summary.len());
+        let people = summary["people"];
+        assert_eq!(
+            (1, 2, 0, 1),
+            (
+                people.inserts,
+                people.updates,
+                people.deletes,
+                people.indirect
+            )
+        );
+        assert_eq!(1, summary["audit"].inserts);
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_databases() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "ATTACH DATABASE ':memory:' AS staging;
+             CREATE TABLE main.t1(id INTEGER PRIMARY KEY, v TEXT);
+             CREATE TABLE main.t2(v TEXT);
+             CREATE TABLE main.t3(id INTEGER PRIMARY KEY);
+             CREATE TABLE main.t4(id INTEGER PRIMARY KEY, v TEXT);
+             CREATE TABLE staging.t1(id INTEGER PRIMARY KEY, v TEXT);
+             CREATE TABLE staging.t2(v TEXT);
+             CREATE TABLE staging.t4(id INTEGER PRIMARY KEY, w TEXT);
+             CREATE TABLE staging.t5(id INTEGER PRIMARY KEY);
+             CREATE VIRTUAL TABLE main.t6 USING fts5(v);
+             CREATE VIRTUAL TABLE staging.t6 USING fts5(v);
+             CREATE TABLE main.T7(ID INTEGER PRIMARY KEY, v TEXT);
+             CREATE TABLE staging.t7(id INTEGER PRIMARY KEY, v TEXT);
+             INSERT INTO main.t1 VALUES(1, 'a'), (2, 'b'), (3, 'c');
+             INSERT INTO staging.t1 VALUES(1, 'a'), (2, 'B'), (4, 'd');
+             INSERT INTO staging.t7 VALUES(1, 'a');",
+        )?;
+
+        let (from, to) = (DatabaseName::Main, DatabaseName::Attached("staging"));
+        assert_eq!(
+            vec![
+                DiffWarning::NoPrimaryKey("t2".to_owned()),
+                DiffWarning::SchemaMismatch("t4".to_owned()),
+                DiffWarning::OnlyInTo("t5".to_owned()),
+                DiffWarning::VirtualTable("t6".to_owned()),
+                DiffWarning::OnlyInFrom("t3".to_owned()),
+            ],
+            db.diff_warnings(from, to)?
+        );
+        let changeset = db.diff_databases(from, to)?;
+        let summary = changeset.summary()?;
+        assert_eq!(2, summary.len());
+        let t1 = summary["t1"];
+        assert_eq!((1, 1, 1), (t1.inserts, t1.updates, t1.deletes));
+        assert_eq!(1, summary["t7"].inserts);
+
+        // `apply` always targets the main database
+        db.apply(
+            &changeset,
+            None::<fn(&str) -> bool>,
+            |_conflict_type, _item| ConflictAction::SQLITE_CHANGESET_ABORT,
+        )?;
+        let mismatches: i64 = db.query_row(
+            "SELECT count(*) FROM (
+                 SELECT * FROM (SELECT * FROM main.t1 EXCEPT SELECT * FROM staging.t1)
+                 UNION ALL
+                 SELECT * FROM (SELECT * FROM staging.t1 EXCEPT SELECT * FROM main.t1))",
+            [],
+            |r| r.get(0),
+        )?;
+        assert_eq!(0, mismatches);
+        Ok(())
+    }
+
     #[test]
     fn test_session_empty() -> Result<()> {
         let db = Connection::open_in_memory()?;
@@ -10,13 +10,18 @@ use super::{len_as_c_int, str_for_sqlite};
 use super::{
     AndThenRows, Connection, Error, MappedRows, Params, RawStatement, Result, Row, Rows, ValueRef,
 };
+use crate::query_plan::QueryPlan;
+#[cfg(feature = "scanstatus")]
+use crate::query_plan::ScanStatus;
+#[cfg(feature = "modern_sqlite")]
+use crate::types::pointer::{free_pointer, POINTER_TYPE};
 use crate::types::{ToSql, ToSqlOutput};
 #[cfg(feature = "array")]
 use crate::vtab::array::{free_array, ARRAY_TYPE};
 
 /// A prepared statement.
 pub struct Statement<'conn> {
-    conn: &'conn Connection,
+    pub(crate) conn: &'conn Connection,
     pub(crate) stmt: RawStatement,
 }
 
@@ -673,6 +678,25 @@ impl Statement<'_> {
                     )
                 });
             }
+            #[cfg(feature = "array")]
+            ToSqlOutput::ArrayData(a) => {
+                let (a, kind, destructor) = a.into_raw();
+                return self.conn.decode_result(unsafe {
+                    ffi::sqlite3_bind_pointer(ptr, col as c_int, a, kind, Some(destructor))
+                });
+            }
+            #[cfg(feature = "modern_sqlite")]
+            ToSqlOutput::Pointer(p) => {
+                return self.conn.decode_result(unsafe {
+                    ffi::sqlite3_bind_pointer(
+                        ptr,
+                        col as c_int,
+                        p.into_raw(),
+                        POINTER_TYPE,
+                        Some(free_pointer),
+                    )
+                });
+            }
         };
         self.conn.decode_result(match value {
             ValueRef::Null => unsafe { ffi::sqlite3_bind_null(ptr, col as c_int) },
@@ -781,6 +805,109 @@ impl Statement<'_> {
         self.stmt.get_status(status, true)
     }
 
+    /// Returns how SQLite executes this statement, as reported by
+    /// `EXPLAIN QUERY PLAN`.
+    ///
+    /// The statement is not run, and its parameters do not need to be bound.
+    pub fn query_plan(&self) -> Result<QueryPlan> {
+        let sql = match self.stmt.sql() {
+            Some(sql) => sql.to_str()?,
+            None => "",
+        };
+        QueryPlan::explain(self.conn, sql)
+    }
+
+    /// `feature = "scanstatus"` Returns the performance counters of each loop
+    /// of this statement, accumulated since it was prepared or since the last
+    /// call to [`reset_scan_status`](Statement::reset_scan_status).
+    ///
+    /// Requires SQLite to be compiled with `SQLITE_ENABLE_STMT_SCANSTATUS`,
+    /// which the `bundled` build does when this feature is enabled.
+    #[cfg(feature = "scanstatus")]
+    pub fn scan_status(&self) -> Vec<ScanStatus> {
+        let stmt = unsafe { self.stmt.ptr() };
+        let mut scans = Vec::new();
+        for idx in 0.. {
+            let mut loops: i64 = 0;
+            let rc = unsafe {
+                ffi::sqlite3_stmt_scanstatus(
+                    stmt,
+                    idx,
+                    ffi::SQLITE_SCANSTAT_NLOOP,
+                    &mut loops as *mut i64 as *mut c_void,
+                )
+            };
+            if rc != 0 {
+                break;
+            }
+            let mut rows_visited: i64 = 0;
+            let mut estimated_rows: f64 = 0.0;
+            let mut name: *const std::os::raw::c_char = ptr::null();
+            let mut explain: *const std::os::raw::c_char = ptr::null();
+            let mut select_id: c_int = 0;
+            unsafe {
+                ffi::sqlite3_stmt_scanstatus(
+                    stmt,
+                    idx,
+                    ffi::SQLITE_SCANSTAT_NVISIT,
+                    &mut rows_visited as *mut i64 as *mut c_void,
+                );
+                ffi::sqlite3_stmt_scanstatus(
+                    stmt,
+                    idx,
+                    ffi::SQLITE_SCANSTAT_EST,
+                    &mut estimated_rows as *mut f64 as *mut c_void,
+                );
+                ffi::sqlite3_stmt_scanstatus(
+                    stmt,
+                    idx,
+                    ffi::SQLITE_SCANSTAT_NAME,
+                    &mut name as *mut _ as *mut c_void,
+                );
+                ffi::sqlite3_stmt_scanstatus(
+                    stmt,
+                    idx,
+                    ffi::SQLITE_SCANSTAT_EXPLAIN,
+                    &mut explain as *mut _ as *mut c_void,
+                );
+                ffi::sqlite3_stmt_scanstatus(
+                    stmt,
+                    idx,
+                    ffi::SQLITE_SCANSTAT_SELECTID,
+                    &mut select_id as *mut c_int as *mut c_void,
+                );
+            }
+            let to_string = |s: *const std::os::raw::c_char| {
+                if s.is_null() {
+                    None
+                } else {
+                    Some(
+                        unsafe { std::ffi::CStr::from_ptr(s) }
+                            .to_string_lossy()
+                            .into_owned(),
+                    )
+                }
+            };
+            scans.push(ScanStatus {
+                loops,
+                rows_visited,
+                estimated_rows,
+                name: to_string(name),
+                explain: to_string(explain),
+                select_id,
+            });
+        }
+        scans
+    }
+
+    /// `feature = "scanstatus"` Resets the counters reported by
+    /// [`scan_status`](Statement::scan_status).
+    #[cfg(feature = "scanstatus")]
+    #[inline]
+    pub fn reset_scan_status(&self) {
+        unsafe { ffi::sqlite3_stmt_scanstatus_reset(self.stmt.ptr()) }
+    }
+
     #[cfg(feature = "extra_check")]
     #[inline]
     pub(crate) fn check_no_tail(&self) -> Result<()> {
@@ -837,6 +964,30 @@ impl Statement<'_> {
         Statement { conn, stmt }
     }
 
+    /// Returns the value of column `col` converted to text like SQLite does,
+    /// `None` if it is `NULL`.
+    ///
+    /// Text or blobs previously borrowed from the column would be invalidated
+    /// by the conversion of a number, so they must not be alive.
+    #[cfg(feature = "csvtab")]
+    pub(crate) fn value_text(&self, col: usize) -> Result<Option<&[u8]>> {
+        let raw = unsafe { self.stmt.ptr() };
+        if self.stmt.column_type(col) == ffi::SQLITE_NULL {
+            return Ok(None);
+        }
+        unsafe {
+            let text = ffi::sqlite3_column_text(raw, col as c_int);
+            if text.is_null() {
+                return Err(crate::error::error_from_sqlite_code(
+                    ffi::SQLITE_NOMEM,
+                    None,
+                ));
+            }
+            let len = ffi::sqlite3_column_bytes(raw, col as c_int);
+            Ok(Some(from_raw_parts(text as *const u8, len as usize)))
+        }
+    }
+
     pub(super) fn value_ref(&self, col: usize) -> ValueRef<'_> {
         let raw = unsafe { self.stmt.ptr() };
 
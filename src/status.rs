@@ -0,0 +1,202 @@
+//! Run-time status counters
+//!
+//! [`Connection::db_status`] reports the memory used and the cache activity
+//! of one connection, while [`status`] reports memory allocation statistics
+//! for the whole process.
+
+use std::os::raw::c_int;
+
+use crate::ffi;
+use crate::{Connection, Result};
+
+/// Status parameters for database connections
+/// See [Status Parameters for database connections](https://sqlite.org/c3ref/c_dbstatus_options.html) for details.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DbStatus {
+    /// Equivalent to SQLITE_DBSTATUS_LOOKASIDE_USED: number of lookaside
+    /// memory slots currently checked out
+    LookasideUsed = 0,
+    /// Equivalent to SQLITE_DBSTATUS_CACHE_USED: bytes of heap memory used by
+    /// all pager caches
+    CacheUsed = 1,
+    /// Equivalent to SQLITE_DBSTATUS_SCHEMA_USED: bytes of heap memory used to
+    /// store the schemas
+    SchemaUsed = 2,
+    /// Equivalent to SQLITE_DBSTATUS_STMT_USED: bytes of heap and lookaside
+    /// memory used by all prepared statements
+    StmtUsed = 3,
+    /// Equivalent to SQLITE_DBSTATUS_LOOKASIDE_HIT: number of malloc calls
+    /// satisfied using lookaside memory (highwater only)
+    LookasideHit = 4,
+    /// Equivalent to SQLITE_DBSTATUS_LOOKASIDE_MISS_SIZE: number of malloc
+    /// calls which could not use lookaside memory because of their size
+    /// (highwater only)
+    LookasideMissSize = 5,
+    /// Equivalent to SQLITE_DBSTATUS_LOOKASIDE_MISS_FULL: number of malloc
+    /// calls which could not use lookaside memory because all of it was in
+    /// use (highwater only)
+    LookasideMissFull = 6,
+    /// Equivalent to SQLITE_DBSTATUS_CACHE_HIT: number of pager cache hits
+    CacheHit = 7, // 3.7.9
+    /// Equivalent to SQLITE_DBSTATUS_CACHE_MISS: number of pager cache misses
+    CacheMiss = 8, // 3.7.9
+    /// Equivalent to SQLITE_DBSTATUS_CACHE_WRITE: number of dirty cache
+    /// entries written to disk
+    CacheWrite = 9, // 3.7.12
+    /// Equivalent to SQLITE_DBSTATUS_DEFERRED_FKS: 1 if all foreign key
+    /// constraints have been resolved, 0 otherwise
+    DeferredFks = 10, // 3.8.0
+    /// Equivalent to SQLITE_DBSTATUS_CACHE_USED_SHARED: like `CacheUsed`, but
+    /// with memory of shared caches divided between the connections using it
+    CacheUsedShared = 11, // 3.10.0
+    /// Equivalent to SQLITE_DBSTATUS_CACHE_SPILL: number of dirty cache
+    /// entries written to disk in the middle of a transaction
+    CacheSpill = 12, // 3.23.0
+}
+
+/// Process-wide status parameters
+/// See [Status Parameters](https://sqlite.org/c3ref/c_status_malloc_count.html) for details.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Status {
+    /// Equivalent to SQLITE_STATUS_MEMORY_USED: bytes of memory currently
+    /// checked out by SQLite, excluding the page cache
+    MemoryUsed = 0,
+    /// Equivalent to SQLITE_STATUS_PAGECACHE_USED: number of pages used out of
+    /// the page cache memory configured with `SQLITE_CONFIG_PAGECACHE`
+    PagecacheUsed = 1,
+    /// Equivalent to SQLITE_STATUS_PAGECACHE_OVERFLOW: bytes of page cache
+    /// allocations which did not fit in `SQLITE_CONFIG_PAGECACHE` memory
+    PagecacheOverflow = 2,
+    /// Equivalent to SQLITE_STATUS_MALLOC_SIZE: size of the largest memory
+    /// allocation request (highwater only)
+    MallocSize = 5,
+    /// Equivalent to SQLITE_STATUS_PARSER_STACK: deepest parser stack
+    /// (highwater only)
+    ParserStack = 6,
+    /// Equivalent to SQLITE_STATUS_PAGECACHE_SIZE: size of the largest page
+    /// cache allocation request (highwater only)
+    PagecacheSize = 7,
+    /// Equivalent to SQLITE_STATUS_MALLOC_COUNT: number of separate memory
+    /// allocations currently checked out
+    MallocCount = 9,
+}
+
+/// Current and highest values of a status counter
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StatusValue {
+    /// Current value
+    pub current: i64,
+    /// Highest value since the last reset
+    pub highwater: i64,
+}
+
+impl Connection {
+    /// Returns the value of a status counter for this connection.
+    ///
+    /// If `reset` is `true`, the highest value (or, for `CacheHit`,
+    /// `CacheMiss`, `CacheWrite` and `CacheSpill`, the current value) is reset
+    /// after being read.
+    #[inline]
+    pub fn db_status(&self, status: DbStatus, reset: bool) -> Result<StatusValue> {
+        let c = self.db.borrow();
+        let mut current = 0;
+        let mut highwater = 0;
+        check!(unsafe {
+            ffi::sqlite3_db_status(
+                c.db(),
+                status as c_int,
+                &mut current,
+                &mut highwater,
+                reset as c_int,
+            )
+        });
+        Ok(StatusValue {
+            current: current.into(),
+            highwater: highwater.into(),
+        })
+    }
+}
+
+/// Returns the value of a process-wide status counter.
+///
+/// If `reset` is `true`, the highest value is reset to the current value
+/// after being read.
+///
+/// Memory counters are only maintained if `SQLITE_CONFIG_MEMSTATUS` is
+/// enabled, which is the default.
+#[cfg(feature = "modern_sqlite")] // 3.10.0
+#[inline]
+pub fn status(status: Status, reset: bool) -> Result<StatusValue> {
+    let mut current = 0;
+    let mut highwater = 0;
+    check!(unsafe {
+        ffi::sqlite3_status64(
+            status as c_int,
+            &mut current,
+            &mut highwater,
+            reset as c_int,
+        )
+    });
+    Ok(StatusValue { current, highwater })
+}
+
+/// Returns the value of a process-wide status counter.
+///
+/// If `reset` is `true`, the highest value is reset to the current value
+/// after being read.
+///
+/// Memory counters are only maintained if `SQLITE_CONFIG_MEMSTATUS` is
+/// enabled, which is the default.
+#[cfg(not(feature = "modern_sqlite"))]
+#[inline]
+pub fn status(status: Status, reset: bool) -> Result<StatusValue> {
+    let mut current = 0;
+    let mut highwater = 0;
+    check!(unsafe {
+        ffi::sqlite3_status(
+            status as c_int,
+            &mut current,
+            &mut highwater,
+            reset as c_int,
+        )
+    });
+    Ok(StatusValue {
+        current: current.into(),
+        highwater: highwater.into(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{status, DbStatus, Status};
+    use crate::{Connection, Result};
+
+    #[test]
+    fn test_db_status() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("CREATE TABLE foo(x); INSERT INTO foo VALUES(1);")?;
+        let _: i32 = db.query_row("SELECT x FROM foo", [], |r| r.get(0))?;
+
+        assert!(db.db_status(DbStatus::CacheUsed, false)?.current > 0);
+        assert!(db.db_status(DbStatus::SchemaUsed, false)?.current > 0);
+        assert!(db.db_status(DbStatus::CacheHit, false)?.current > 0);
+        db.db_status(DbStatus::CacheHit, true)?;
+        assert_eq!(0, db.db_status(DbStatus::CacheHit, false)?.current);
+        Ok(())
+    }
+
+    #[test]
+    fn test_status() -> Result<()> {
+        let _db = Connection::open_in_memory()?;
+        let memory_used = status(Status::MemoryUsed, false)?;
+        assert!(memory_used.current > 0);
+        assert!(memory_used.highwater >= memory_used.current);
+        assert!(status(Status::MallocCount, false)?.current > 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,494 @@
+//! `feature = "session"` Replication of changes between databases.
+//!
+//! A [`Replicator`] records the changes made to a database with a
+//! [`Session`], sends them to peers over any [`Write`] transport and applies
+//! the changes received from peers over any [`Read`] transport, resolving
+//! conflicts with a [`ConflictPolicy`].
+//!
+//! Local changes are kept in the `rusqlite_sync_log` table, and the
+//! `rusqlite_sync_peer` table keeps, for each peer, the last local change sent
+//! to it and the last change received from it (see [`Watermark`]). Messages
+//! which have already been received are ignored, so a message can safely be
+//! sent again if the transport failed.
+//!
+//! Changes received from one peer are forwarded to the other peers, which
+//! makes a star topology (e.g. one server and many clients) work. Topologies
+//! with cycles would make changes go round forever.
+//!
+//! All peers must have the same schema, and replicated tables must have a
+//! primary key.
+//!
+//! ```rust,no_run
+//! # use rusqlite::{Connection, Result};
+//! # use rusqlite::sync::{ConflictPolicy, Replicator};
+//! fn sync(client: &Connection, server: &Connection) -> Result<()> {
+//!     let mut client = Replicator::new(client, ConflictPolicy::LastWriterWins)?;
+//!     let mut server = Replicator::new(server, ConflictPolicy::LastWriterWins)?;
+//!
+//!     let mut message = Vec::new();
+//!     client.send("server", &mut message)?;
+//!     server.receive("client", &mut message.as_slice())?;
+//!
+//!     let mut message = Vec::new();
+//!     server.send("client", &mut message)?;
+//!     client.receive("server", &mut message.as_slice())?;
+//!     Ok(())
+//! }
+//! ```
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+
+use fallible_streaming_iterator::FallibleStreamingIterator;
+
+use crate::error::error_from_sqlite_code;
+use crate::ffi;
+use crate::session::{
+    Changegroup, ChangesetItem, ChangesetIter, ConflictAction, ConflictType, Session,
+};
+use crate::types::ValueRef;
+use crate::{params, Connection, OptionalExtension, Result};
+
+const INTERNAL_TABLE_PREFIX: &str = "rusqlite_sync_";
+
+/// `feature = "session"` How conflicting changes received from a peer are
+/// resolved.
+///
+/// Except with [`ConflictPolicy::Custom`], changes to rows which no longer
+/// exist locally are dropped, and changes violating a constraint abort the
+/// whole message.
+#[derive(Clone)]
+#[non_exhaustive]
+pub enum ConflictPolicy {
+    /// The change received from the peer replaces the local row.
+    LastWriterWins,
+    /// The row with the greatest value in the named column (e.g. a modification
+    /// timestamp) is kept. Ties are resolved in favor of the peer. Tables
+    /// without this column use [`ConflictPolicy::LastWriterWins`].
+    Timestamp(String),
+    /// The closure decides, as with [`Connection::apply`].
+    Custom(
+        Arc<dyn Fn(ConflictType, ChangesetItem) -> ConflictAction + Send + Sync + RefUnwindSafe>,
+    ),
+}
+
+/// `feature = "session"` Replication progress with one peer
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Watermark {
+    /// Sequence number of the last local change sent to the peer
+    pub sent: i64,
+    /// Sequence number, in the peer's log, of the last change received from
+    /// the peer
+    pub received: i64,
+}
+
+/// `feature = "session"` Exchanges changes between a database and its peers.
+pub struct Replicator<'conn> {
+    conn: &'conn Connection,
+    session: Session<'conn>,
+    policy: ConflictPolicy,
+}
+
+impl<'conn> Replicator<'conn> {
+    /// Create the replication tables if needed and start recording the
+    /// changes made to `conn`.
+    ///
+    /// Only the changes made while a `Replicator` exists are replicated.
+    pub fn new(conn: &'conn Connection, policy: ConflictPolicy) -> Result<Replicator<'conn>> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS rusqlite_sync_log(
+                 seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                 origin TEXT,
+                 changeset BLOB NOT NULL);
+             CREATE TABLE IF NOT EXISTS rusqlite_sync_peer(
+                 peer TEXT PRIMARY KEY NOT NULL,
+                 sent INTEGER NOT NULL DEFAULT 0,
+                 received INTEGER NOT NULL DEFAULT 0);",
+        )?;
+        Ok(Replicator {
+            conn,
+            session: start_session(conn)?,
+            policy,
+        })
+    }
+
+    /// Write the changes which have not been sent to `peer` yet to `output`.
+    ///
+    /// Must not be called inside a transaction.
+    pub fn send(&mut self, peer: &str, output: &mut dyn Write) -> Result<()> {
+        self.flush()?;
+        let tx = self.conn.unchecked_transaction()?;
+        let sent = self.watermark(peer)?.sent;
+        let mut group = Changegroup::new()?;
+        {
+            let mut stmt = tx.prepare(
+                "SELECT changeset FROM rusqlite_sync_log
+                 WHERE seq > ? AND (origin IS NULL OR origin <> ?) ORDER BY seq",
+            )?;
+            let mut rows = stmt.query(params![sent, peer])?;
+            while let Some(row) = rows.next()? {
+                let changeset: Vec<u8> = row.get(0)?;
+                group.add_stream(&mut changeset.as_slice())?;
+            }
+        }
+        let mut changeset = Vec::new();
+        group.output_strm(&mut changeset)?;
+        let last: Option<i64> =
+            tx.query_row("SELECT max(seq) FROM rusqlite_sync_log", [], |r| r.get(0))?;
+        let last = last.unwrap_or(0).max(sent);
+
+        let mut message = Vec::with_capacity(16 + changeset.len());
+        message.extend_from_slice(&last.to_be_bytes());
+        message.extend_from_slice(&(changeset.len() as u64).to_be_bytes());
+        message.extend_from_slice(&changeset);
+        output
+            .write_all(&message)
+            .map_err(|e| io_error(ffi::SQLITE_IOERR_WRITE, &e))?;
+
+        self.ensure_peer(peer)?;
+        tx.execute(
+            "UPDATE rusqlite_sync_peer SET sent = ? WHERE peer = ?",
+            params![last, peer],
+        )?;
+        tx.commit()
+    }
+
+    /// Apply the changes sent by `peer` and read from `input`.
+    ///
+    /// Returns `false` if the message had already been received.
+    ///
+    /// Must not be called inside a transaction.
+    pub fn receive(&mut self, peer: &str, input: &mut dyn Read) -> Result<bool> {
+        let mut header = [0u8; 16];
+        input
+            .read_exact(&mut header)
+            .map_err(|e| io_error(ffi::SQLITE_IOERR_READ, &e))?;
+        let mut seq = [0u8; 8];
+        let mut len = [0u8; 8];
+        seq.copy_from_slice(&header[..8]);
+        len.copy_from_slice(&header[8..]);
+        let seq = i64::from_be_bytes(seq);
+        let len = u64::from_be_bytes(len);
+        let mut changeset = Vec::new();
+        input
+            .take(len)
+            .read_to_end(&mut changeset)
+            .map_err(|e| io_error(ffi::SQLITE_IOERR_READ, &e))?;
+        if changeset.len() as u64 != len {
+            return Err(error_from_sqlite_code(
+                ffi::SQLITE_IOERR_SHORT_READ,
+                Some("truncated changeset".to_owned()),
+            ));
+        }
+
+        if seq <= self.watermark(peer)?.received {
+            return Ok(false);
+        }
+        // Local changes must be logged before the peer's changes overwrite
+        // the rows tracked by the session.
+        self.flush()?;
+        let tx = self.conn.unchecked_transaction()?;
+        if !changeset.is_empty() {
+            let policy = self.policy.clone();
+            let columns = self.timestamp_columns(&changeset)?;
+            self.session.set_enabled(false);
+            let r = self.conn.apply_strm(
+                &mut changeset.as_slice(),
+                None::<fn(&str) -> bool>,
+                move |conflict_type, item| resolve(&policy, &columns, conflict_type, item),
+            );
+            self.session.set_enabled(true);
+            r?;
+            // forwarded to the other peers
+            tx.execute(
+                "INSERT INTO rusqlite_sync_log(origin, changeset) VALUES(?, ?)",
+                params![peer, changeset],
+            )?;
+        }
+        self.ensure_peer(peer)?;
+        tx.execute(
+            "UPDATE rusqlite_sync_peer SET received = ? WHERE peer = ?",
+            params![seq, peer],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Replication progress with `peer`
+    pub fn watermark(&self, peer: &str) -> Result<Watermark> {
+        let watermark = self
+            .conn
+            .query_row(
+                "SELECT sent, received FROM rusqlite_sync_peer WHERE peer = ?",
+                params![peer],
+                |r| {
+                    Ok(Watermark {
+                        sent: r.get(0)?,
+                        received: r.get(1)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(watermark.unwrap_or_default())
+    }
+
+    /// Move the changes recorded by the session to the log.
+    fn flush(&mut self) -> Result<()> {
+        if self.session.is_empty() {
+            return Ok(());
+        }
+        let mut changeset = Vec::new();
+        self.session.changeset_strm(&mut changeset)?;
+        self.session = start_session(self.conn)?;
+        if !changeset.is_empty() {
+            self.conn.execute(
+                "INSERT INTO rusqlite_sync_log(origin, changeset) VALUES(NULL, ?)",
+                params![changeset],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn ensure_peer(&self, peer: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO rusqlite_sync_peer(peer) VALUES(?)",
+            params![peer],
+        )?;
+        Ok(())
+    }
+
+    /// Index of the timestamp column of each table modified by `changeset`
+    fn timestamp_columns(&self, changeset: &[u8]) -> Result<HashMap<String, usize>> {
+        let mut columns = HashMap::new();
+        let column = match self.policy {
+            ConflictPolicy::Timestamp(ref column) => column,
+            _ => return Ok(columns),
+        };
+        let input: &mut dyn Read = &mut &changeset[..];
+        let mut iter = ChangesetIter::start_strm(&input)?;
+        while let Some(item) = iter.next()? {
+            let op = item.op()?;
+            if columns.contains_key(op.table_name()) {
+                continue;
+            }
+            let names = self.conn.table_column_names(op.table_name())?;
+            if let Some(i) = names
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(column))
+            {
+                columns.insert(op.table_name().to_owned(), i);
+            }
+        }
+        Ok(columns)
+    }
+}
+
+fn start_session(conn: &Connection) -> Result<Session<'_>> {
+    let mut session = Session::new(conn)?;
+    session.table_filter(Some(|table: &str| {
+        !table.starts_with(INTERNAL_TABLE_PREFIX)
+    }));
+    session.attach(None)?;
+    Ok(session)
+}
+
+fn io_error(code: std::os::raw::c_int, e: &io::Error) -> crate::Error {
+    error_from_sqlite_code(code, Some(e.to_string()))
+}
+
+fn resolve(
+    policy: &ConflictPolicy,
+    timestamp_columns: &HashMap<String, usize>,
+    conflict_type: ConflictType,
+    item: ChangesetItem,
+) -> ConflictAction {
+    if let ConflictPolicy::Custom(ref f) = *policy {
+        return f(conflict_type, item);
+    }
+    match conflict_type {
+        ConflictType::SQLITE_CHANGESET_DATA | ConflictType::SQLITE_CHANGESET_CONFLICT => {}
+        ConflictType::SQLITE_CHANGESET_NOTFOUND => return ConflictAction::SQLITE_CHANGESET_OMIT,
+        _ => return ConflictAction::SQLITE_CHANGESET_ABORT,
+    };
+    match peer_is_newer(timestamp_columns, &item) {
+        Some(false) => ConflictAction::SQLITE_CHANGESET_OMIT,
+        _ => ConflictAction::SQLITE_CHANGESET_REPLACE,
+    }
+}
+
+/// Compare the timestamp of the peer's version of the row with the local
+/// one, or `None` if there is no timestamp column (e.g. with
+/// `ConflictPolicy::LastWriterWins`).
+fn peer_is_newer(timestamp_columns: &HashMap<String, usize>, item: &ChangesetItem) -> Option<bool> {
+    let op = item.op().ok()?;
+    let col = *timestamp_columns.get(op.table_name())?;
+    let local = item.conflict(col).ok()?;
+    // new.* for an insert or an update of the timestamp, old.* otherwise
+    let peer = match item.new_value_if_set(col) {
+        Ok(Some(value)) => value,
+        _ => item.old_value_if_set(col).ok()??,
+    };
+    Some(compare(peer, local) != Ordering::Less)
+}
+
+/// Same ordering as SQLite: NULL < INTEGER, REAL < TEXT < BLOB
+fn compare(a: ValueRef<'_>, b: ValueRef<'_>) -> Ordering {
+    fn rank(v: ValueRef<'_>) -> u8 {
+        match v {
+            ValueRef::Null => 0,
+            ValueRef::Integer(_) | ValueRef::Real(_) => 1,
+            ValueRef::Text(_) => 2,
+            ValueRef::Blob(_) => 3,
+        }
+    }
+    match (a, b) {
+        (ValueRef::Integer(a), ValueRef::Integer(b)) => a.cmp(&b),
+        (ValueRef::Integer(a), ValueRef::Real(b)) => {
+            (a as f64).partial_cmp(&b).unwrap_or(Ordering::Equal)
+        }
+        (ValueRef::Real(a), ValueRef::Integer(b)) => {
+            a.partial_cmp(&(b as f64)).unwrap_or(Ordering::Equal)
+        }
+        (ValueRef::Real(a), ValueRef::Real(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        (ValueRef::Text(a), ValueRef::Text(b)) | (ValueRef::Blob(a), ValueRef::Blob(b)) => a.cmp(b),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::{ConflictPolicy, Replicator, Watermark};
+    use crate::session::{ChangesetItem, ConflictAction, ConflictType};
+    use crate::{Connection, Result};
+
+    fn db() -> Result<Connection> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "CREATE TABLE item(id INTEGER PRIMARY KEY, name TEXT, modified INTEGER);
+             INSERT INTO item VALUES(1, 'one', 0);",
+        )?;
+        Ok(db)
+    }
+
+    fn exchange(
+        from: &mut Replicator<'_>,
+        from_name: &str,
+        to: &mut Replicator<'_>,
+        to_name: &str,
+    ) -> Result<bool> {
+        let mut message = Vec::new();
+        from.send(to_name, &mut message)?;
+        to.receive(from_name, &mut message.as_slice())
+    }
+
+    fn names(db: &Connection) -> Result<Vec<String>> {
+        let mut stmt = db.prepare("SELECT name FROM item ORDER BY id")?;
+        let rows = stmt.query_map([], |r| r.get(0))?;
+        rows.collect()
+    }
+
+    #[test]
+    fn test_replication() -> Result<()> {
+        let (server_db, client_db) = (db()?, db()?);
+        let mut server = Replicator::new(&server_db, ConflictPolicy::LastWriterWins)?;
+        let mut client = Replicator::new(&client_db, ConflictPolicy::LastWriterWins)?;
+
+        client_db.execute_batch("INSERT INTO item VALUES(2, 'two', 1)")?;
+        server_db.execute_batch("INSERT INTO item VALUES(3, 'three', 1)")?;
+        assert!(exchange(&mut client, "client", &mut server, "server")?);
+        assert!(exchange(&mut server, "server", &mut client, "client")?);
+        assert_eq!(vec!["one", "two", "three"], names(&server_db)?);
+        assert_eq!(names(&server_db)?, names(&client_db)?);
+
+        // the client's own changes are not sent back to it
+        let mut message = Vec::new();
+        server.send("client", &mut message)?;
+        assert_eq!(16, message.len());
+        assert!(!client.receive("server", &mut message.as_slice())?);
+
+        // a message received twice is ignored
+        client_db.execute_batch("UPDATE item SET name = 'deux' WHERE id = 2")?;
+        let mut message = Vec::new();
+        client.send("server", &mut message)?;
+        assert!(server.receive("client", &mut message.as_slice())?);
+        assert!(!server.receive("client", &mut message.as_slice())?);
+        assert_eq!(vec!["one", "deux", "three"], names(&server_db)?);
+        assert_eq!(
+            Watermark {
+                sent: 2,
+                received: 3
+            },
+            server.watermark("client")?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_relay() -> Result<()> {
+        let (server_db, a_db, b_db) = (db()?, db()?, db()?);
+        let mut server = Replicator::new(&server_db, ConflictPolicy::LastWriterWins)?;
+        let mut a = Replicator::new(&a_db, ConflictPolicy::LastWriterWins)?;
+        let mut b = Replicator::new(&b_db, ConflictPolicy::LastWriterWins)?;
+
+        a_db.execute_batch("INSERT INTO item VALUES(2, 'from a', 1)")?;
+        exchange(&mut a, "a", &mut server, "server")?;
+        exchange(&mut server, "server", &mut b, "b")?;
+        assert_eq!(vec!["one", "from a"], names(&b_db)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_writer_wins() -> Result<()> {
+        let (server_db, client_db) = (db()?, db()?);
+        let mut server = Replicator::new(&server_db, ConflictPolicy::LastWriterWins)?;
+        let mut client = Replicator::new(&client_db, ConflictPolicy::LastWriterWins)?;
+
+        server_db.execute_batch("UPDATE item SET name = 'server' WHERE id = 1")?;
+        client_db.execute_batch("UPDATE item SET name = 'client' WHERE id = 1")?;
+        exchange(&mut client, "client", &mut server, "server")?;
+        assert_eq!(vec!["client"], names(&server_db)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_timestamp() -> Result<()> {
+        let (server_db, client_db) = (db()?, db()?);
+        let policy = ConflictPolicy::Timestamp("modified".to_owned());
+        let mut server = Replicator::new(&server_db, policy.clone())?;
+        let mut client = Replicator::new(&client_db, policy)?;
+
+        server_db.execute_batch("UPDATE item SET name = 'server', modified = 20 WHERE id = 1")?;
+        client_db.execute_batch("UPDATE item SET name = 'client', modified = 10 WHERE id = 1")?;
+        exchange(&mut client, "client", &mut server, "server")?;
+        assert_eq!(vec!["server"], names(&server_db)?);
+        exchange(&mut server, "server", &mut client, "client")?;
+        assert_eq!(vec!["server"], names(&client_db)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom() -> Result<()> {
+        let (server_db, client_db) = (db()?, db()?);
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        let policy = ConflictPolicy::Custom(Arc::new(|_: ConflictType, _: ChangesetItem| {
+            CALLS.fetch_add(1, Ordering::Relaxed);
+            ConflictAction::SQLITE_CHANGESET_OMIT
+        }));
+        let mut server = Replicator::new(&server_db, policy)?;
+        let mut client = Replicator::new(&client_db, ConflictPolicy::LastWriterWins)?;
+
+        server_db.execute_batch("UPDATE item SET name = 'server' WHERE id = 1")?;
+        client_db.execute_batch("UPDATE item SET name = 'client' WHERE id = 1")?;
+        exchange(&mut client, "client", &mut server, "server")?;
+        assert_eq!(1, CALLS.load(Ordering::Relaxed));
+        assert_eq!(vec!["server"], names(&server_db)?);
+        Ok(())
+    }
+}
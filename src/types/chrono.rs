@@ -2,10 +2,10 @@
 
 use std::borrow::Cow;
 
-use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
 
-use crate::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
-use crate::Result;
+use crate::types::{FromSql, FromSqlError, FromSqlResult, Timestamp, ToSql, ToSqlOutput, ValueRef};
+use crate::{Error, Result};
 
 /// ISO 8601 calendar date without timezone => "YYYY-MM-DD"
 impl ToSql for NaiveDate {
@@ -132,6 +132,83 @@ impl FromSql for DateTime<Local> {
     }
 }
 
+/// Leap seconds are stored as the last nanosecond of the previous second.
+impl Timestamp for DateTime<Utc> {
+    #[inline]
+    fn to_unix(&self) -> (i64, u32) {
+        (
+            self.timestamp(),
+            self.timestamp_subsec_nanos().min(999_999_999),
+        )
+    }
+
+    #[inline]
+    fn from_unix(secs: i64, nanos: u32) -> Option<Self> {
+        Utc.timestamp_opt(secs, nanos).single()
+    }
+}
+
+impl Timestamp for DateTime<Local> {
+    #[inline]
+    fn to_unix(&self) -> (i64, u32) {
+        self.with_timezone(&Utc).to_unix()
+    }
+
+    #[inline]
+    fn from_unix(secs: i64, nanos: u32) -> Option<Self> {
+        DateTime::<Utc>::from_unix(secs, nanos).map(|dt| dt.with_timezone(&Local))
+    }
+}
+
+/// Date and time without timezone, taken as UTC.
+impl Timestamp for NaiveDateTime {
+    #[inline]
+    fn to_unix(&self) -> (i64, u32) {
+        Utc.from_utc_datetime(self).to_unix()
+    }
+
+    #[inline]
+    fn from_unix(secs: i64, nanos: u32) -> Option<Self> {
+        DateTime::<Utc>::from_unix(secs, nanos).map(|dt| dt.naive_utc())
+    }
+}
+
+/// Calendar date, taken as midnight UTC. The time of day is dropped when
+/// reading.
+impl Timestamp for NaiveDate {
+    #[inline]
+    fn to_unix(&self) -> (i64, u32) {
+        self.and_time(NaiveTime::from_hms_opt(0, 0, 0).unwrap())
+            .to_unix()
+    }
+
+    #[inline]
+    fn from_unix(secs: i64, nanos: u32) -> Option<Self> {
+        NaiveDateTime::from_unix(secs, nanos).map(|dt| dt.date())
+    }
+}
+
+/// Duration => `INTEGER` number of nanoseconds
+impl ToSql for Duration {
+    #[inline]
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        match self.num_nanoseconds() {
+            Some(nanos) => Ok(ToSqlOutput::from(nanos)),
+            None => Err(Error::ToSqlConversionFailure(
+                "duration out of range of i64 nanoseconds".into(),
+            )),
+        }
+    }
+}
+
+/// `INTEGER` number of nanoseconds => Duration
+impl FromSql for Duration {
+    #[inline]
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value.as_i64().map(Duration::nanoseconds)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::{Connection, Result};
@@ -252,4 +329,52 @@ mod test {
         assert!(result.is_ok());
         Ok(())
     }
+
+    #[test]
+    fn test_timestamp_wrappers() -> Result<()> {
+        use crate::params;
+        use crate::types::{JulianDay, UnixMillis, UnixSeconds};
+        let db = checked_memory_handle()?;
+        let dt = Utc.from_utc_datetime(&NaiveDateTime::new(
+            NaiveDate::from_ymd_opt(2016, 2, 23).unwrap(),
+            NaiveTime::from_hms_milli_opt(23, 56, 4, 789).unwrap(),
+        ));
+        db.execute(
+            "INSERT INTO foo (i, f) VALUES (?, ?)",
+            params![UnixMillis(dt), JulianDay(dt.naive_utc())],
+        )?;
+        let millis: i64 = db.query_row("SELECT i FROM foo", [], |r| r.get(0))?;
+        assert_eq!(1_456_271_764_789, millis);
+        let UnixMillis(v): UnixMillis<DateTime<Local>> =
+            db.query_row("SELECT i FROM foo", [], |r| r.get(0))?;
+        assert_eq!(dt, v);
+        let JulianDay(v): JulianDay<NaiveDateTime> =
+            db.query_row("SELECT f FROM foo", [], |r| r.get(0))?;
+        assert_eq!(dt.naive_utc(), v);
+        let UnixSeconds(v): UnixSeconds<NaiveDate> =
+            db.query_row("SELECT i / 1000 FROM foo", [], |r| r.get(0))?;
+        assert_eq!(NaiveDate::from_ymd_opt(2016, 2, 23).unwrap(), v);
+        let date: String = db.query_row(
+            "SELECT date(?, 'unixepoch')",
+            params![UnixSeconds(NaiveDate::from_ymd_opt(2016, 2, 23).unwrap())],
+            |r| r.get(0),
+        )?;
+        assert_eq!("2016-02-23", date);
+        Ok(())
+    }
+
+    #[test]
+    fn test_duration() -> Result<()> {
+        let db = checked_memory_handle()?;
+        let d = Duration::seconds(90) + Duration::nanoseconds(5);
+        let v: i64 = db.query_row("SELECT ?", [d], |r| r.get(0))?;
+        assert_eq!(90_000_000_005, v);
+        let v: Duration = db.query_row("SELECT ?", [d], |r| r.get(0))?;
+        assert_eq!(d, v);
+        assert!(db
+            .query_row("SELECT ?", [Duration::days(1_000_000)], |r| r
+                .get::<_, i64>(0))
+            .is_err());
+        Ok(())
+    }
 }
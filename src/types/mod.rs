@@ -31,7 +31,8 @@
 //! `"%Y-%m-%dT%H:%M:%S.%fZ"`, to store time values as strings.  These values
 //! can be parsed by SQLite's builtin
 //! [datetime](https://www.sqlite.org/lang_datefunc.html) functions.  If you
-//! want different storage for datetimes, you can use a newtype.
+//! want different storage for datetimes, you can use the
+//! [`UnixSeconds`], [`UnixMillis`] and [`JulianDay`] wrappers, or a newtype.
 #![cfg_attr(
     feature = "time",
     doc = r##"
@@ -66,6 +67,11 @@ impl ToSql for DateTimeSql {
 //! value was NULL (which gets translated to `None`).
 
 pub use self::from_sql::{FromSql, FromSqlError, FromSqlResult};
+#[cfg(feature = "modern_sqlite")]
+pub use self::pointer::PointerValue;
+#[cfg(feature = "series")]
+pub(crate) use self::timestamp::{civil_from_days, days_from_civil, parse_timestamp};
+pub use self::timestamp::{JulianDay, Timestamp, UnixMillis, UnixSeconds};
 pub use self::to_sql::{ToSql, ToSqlOutput};
 pub use self::value::Value;
 pub use self::value_ref::ValueRef;
@@ -75,10 +81,13 @@ use std::fmt;
 #[cfg(feature = "chrono")]
 mod chrono;
 mod from_sql;
+#[cfg(feature = "modern_sqlite")]
+pub(crate) mod pointer;
 #[cfg(feature = "serde_json")]
 mod serde_json;
 #[cfg(feature = "time")]
 mod time;
+mod timestamp;
 mod to_sql;
 #[cfg(feature = "url")]
 mod url;
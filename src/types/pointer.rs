@@ -0,0 +1,115 @@
+use super::{ToSql, ToSqlOutput};
+use crate::Result;
+use std::any::Any;
+use std::fmt;
+use std::os::raw::{c_char, c_void};
+use std::rc::Rc;
+
+// Pointer type given to SQLite, the `tag` of a `PointerValue` is checked
+// separately.
+pub(crate) const POINTER_TYPE: *const c_char = b"rusqlite_pointer\0" as *const u8 as *const c_char;
+
+/// `feature = "modern_sqlite"` A Rust object passed through SQLite with the
+/// [pointer passing interfaces](https://sqlite.org/bindptr.html), as a bound
+/// parameter or as the result of a user-defined function.
+///
+/// The object is only visible to code asking for the same `tag` and type (see
+/// `Context::get_pointer`); SQL sees it as `NULL`.
+#[derive(Clone)]
+pub struct PointerValue {
+    tag: &'static str,
+    value: Rc<dyn Any>,
+}
+
+impl PointerValue {
+    /// Wraps `value` under `tag`.
+    #[inline]
+    pub fn new<T: 'static>(tag: &'static str, value: T) -> PointerValue {
+        PointerValue::from_rc(tag, Rc::new(value))
+    }
+
+    /// Wraps a shared `value` under `tag`.
+    #[inline]
+    pub fn from_rc<T: 'static>(tag: &'static str, value: Rc<T>) -> PointerValue {
+        PointerValue { tag, value }
+    }
+
+    /// Returns the tag.
+    #[inline]
+    pub fn tag(&self) -> &'static str {
+        self.tag
+    }
+
+    /// Returns the wrapped object if it is a `T`.
+    #[inline]
+    pub fn downcast<T: 'static>(&self) -> Option<Rc<T>> {
+        self.value.clone().downcast().ok()
+    }
+
+    pub(crate) fn into_raw(self) -> *mut c_void {
+        Box::into_raw(Box::new(self)) as *mut c_void
+    }
+
+    /// # Safety
+    ///
+    /// `p` must be null or come from `sqlite3_value_pointer` with
+    /// `POINTER_TYPE`.
+    pub(crate) unsafe fn from_raw<T: 'static>(p: *mut c_void, tag: &str) -> Option<Rc<T>> {
+        let value = (p as *const PointerValue).as_ref()?;
+        if value.tag == tag {
+            value.downcast()
+        } else {
+            None
+        }
+    }
+}
+
+pub(crate) unsafe extern "C" fn free_pointer(p: *mut c_void) {
+    drop(Box::from_raw(p as *mut PointerValue));
+}
+
+impl PartialEq for PointerValue {
+    #[inline]
+    fn eq(&self, other: &PointerValue) -> bool {
+        self.tag == other.tag && Rc::ptr_eq(&self.value, &other.value)
+    }
+}
+
+impl fmt::Debug for PointerValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PointerValue")
+            .field("tag", &self.tag)
+            .finish()
+    }
+}
+
+impl ToSql for PointerValue {
+    #[inline]
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Pointer(self.clone()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PointerValue;
+    use crate::{Connection, Result};
+
+    #[test]
+    fn test_bind_pointer() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        let p = PointerValue::new("test", vec![1, 2, 3]);
+        let (is_null, typ): (bool, String) =
+            db.query_row("SELECT ? IS NULL, typeof(?1)", [&p], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })?;
+        assert!(is_null);
+        assert_eq!("null", typ);
+        assert_eq!(
+            Some(vec![1, 2, 3]),
+            p.downcast::<Vec<i32>>().map(|v| (*v).clone())
+        );
+        assert!(p.downcast::<String>().is_none());
+        Ok(())
+    }
+}
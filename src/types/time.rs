@@ -1,11 +1,17 @@
 //! [`ToSql`] and [`FromSql`] implementation for [`time::OffsetDateTime`].
-use crate::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use std::time::Duration;
+
+use crate::types::{FromSql, FromSqlError, FromSqlResult, Timestamp, ToSql, ToSqlOutput, ValueRef};
 use crate::Result;
-use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
+use time::{Date, OffsetDateTime, PrimitiveDateTime, UtcOffset};
 
 const CURRENT_TIMESTAMP_FMT: &str = "%Y-%m-%d %H:%M:%S";
 const SQLITE_DATETIME_FMT: &str = "%Y-%m-%dT%H:%M:%S.%NZ";
 const SQLITE_DATETIME_FMT_LEGACY: &str = "%Y-%m-%d %H:%M:%S:%N %z";
+/// -9999-01-01 00:00:00 UTC
+const MIN_UNIX_TIMESTAMP: i64 = -377_705_116_800;
+/// 9999-12-31 23:59:59 UTC
+const MAX_UNIX_TIMESTAMP: i64 = 253_402_300_799;
 
 impl ToSql for OffsetDateTime {
     #[inline]
@@ -31,6 +37,48 @@ impl FromSql for OffsetDateTime {
     }
 }
 
+impl Timestamp for OffsetDateTime {
+    #[inline]
+    fn to_unix(&self) -> (i64, u32) {
+        (self.unix_timestamp(), self.nanosecond())
+    }
+
+    fn from_unix(secs: i64, nanos: u32) -> Option<Self> {
+        if !(MIN_UNIX_TIMESTAMP..=MAX_UNIX_TIMESTAMP).contains(&secs) {
+            return None;
+        }
+        Some(OffsetDateTime::from_unix_timestamp(secs) + Duration::from_nanos(nanos.into()))
+    }
+}
+
+/// Date and time without offset, taken as UTC.
+impl Timestamp for PrimitiveDateTime {
+    #[inline]
+    fn to_unix(&self) -> (i64, u32) {
+        self.assume_utc().to_unix()
+    }
+
+    #[inline]
+    fn from_unix(secs: i64, nanos: u32) -> Option<Self> {
+        OffsetDateTime::from_unix(secs, nanos)
+            .map(|dt| PrimitiveDateTime::new(dt.date(), dt.time()))
+    }
+}
+
+/// Calendar date, taken as midnight UTC. The time of day is dropped when
+/// reading.
+impl Timestamp for Date {
+    #[inline]
+    fn to_unix(&self) -> (i64, u32) {
+        self.midnight().to_unix()
+    }
+
+    #[inline]
+    fn from_unix(secs: i64, nanos: u32) -> Option<Self> {
+        OffsetDateTime::from_unix(secs, nanos).map(|dt| dt.date())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::{Connection, Result};
@@ -79,4 +127,33 @@ mod test {
         assert!(result.is_ok());
         Ok(())
     }
+
+    #[test]
+    fn test_timestamp_wrappers() -> Result<()> {
+        use crate::params;
+        use crate::types::{JulianDay, UnixMillis, UnixSeconds};
+        use time::{Date, PrimitiveDateTime};
+        let db = checked_memory_handle()?;
+        let dt = OffsetDateTime::from_unix_timestamp(1_500_391_124) + Duration::from_millis(250);
+        db.execute(
+            "INSERT INTO foo (i, f) VALUES (?, ?)",
+            params![UnixMillis(dt), JulianDay(dt)],
+        )?;
+        let millis: i64 = db.query_row("SELECT i FROM foo", [], |r| r.get(0))?;
+        assert_eq!(1_500_391_124_250, millis);
+        let UnixMillis(v): UnixMillis<OffsetDateTime> =
+            db.query_row("SELECT i FROM foo", [], |r| r.get(0))?;
+        assert_eq!(dt, v);
+        let JulianDay(v): JulianDay<PrimitiveDateTime> =
+            db.query_row("SELECT f FROM foo", [], |r| r.get(0))?;
+        assert_eq!(PrimitiveDateTime::new(dt.date(), dt.time()), v);
+        let UnixSeconds(v): UnixSeconds<Date> =
+            db.query_row("SELECT i / 1000 FROM foo", [], |r| r.get(0))?;
+        assert_eq!(dt.date(), v);
+        assert!(db
+            .query_row("SELECT 1e15", [], |r| r
+                .get::<_, UnixSeconds<OffsetDateTime>>(0))
+            .is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,434 @@
+//! Alternative storage formats for points in time.
+//!
+//! By default, date/time types are stored as ISO 8601 text. The wrappers of
+//! this module store them as numbers instead:
+//!
+//! * [`UnixSeconds`]: `INTEGER` number of seconds since 1970-01-01 00:00:00
+//!   UTC, as returned by `strftime('%s')`,
+//! * [`UnixMillis`]: `INTEGER` number of milliseconds since the same epoch,
+//! * [`JulianDay`]: `REAL` fractional number of days since noon in Greenwich
+//!   on November 24, 4714 B.C., as returned by `julianday()`.
+//!
+//! They can wrap any [`Timestamp`]: `std::time::SystemTime`, and the chrono
+//! and time date/time types when the corresponding features are enabled.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use crate::{Error, Result};
+
+/// A point in time which can be stored with [`UnixSeconds`], [`UnixMillis`]
+/// or [`JulianDay`].
+pub trait Timestamp: Sized {
+    /// Returns the number of seconds since 1970-01-01 00:00:00 UTC (rounded
+    /// down) and the number of nanoseconds after them (less than
+    /// 1_000_000_000).
+    fn to_unix(&self) -> (i64, u32);
+
+    /// Builds a point in time from the number of seconds since 1970-01-01
+    /// 00:00:00 UTC and a number of nanoseconds (less than 1_000_000_000),
+    /// or returns `None` if it cannot be represented.
+    fn from_unix(secs: i64, nanos: u32) -> Option<Self>;
+}
+
+/// Stores a point in time as an `INTEGER` number of seconds since the Unix
+/// epoch, dropping the fractional part.
+///
+/// Both `INTEGER` and `REAL` values can be read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnixSeconds<T>(pub T);
+
+/// Stores a point in time as an `INTEGER` number of milliseconds since the
+/// Unix epoch, dropping the fractional part.
+///
+/// Both `INTEGER` and `REAL` values can be read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnixMillis<T>(pub T);
+
+/// Stores a point in time as a `REAL` Julian day number, like SQLite's
+/// `julianday()` function.
+///
+/// Values are read with millisecond precision, which is about what a `REAL`
+/// holds for current dates. Both `INTEGER` and `REAL` values can be read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct JulianDay<T>(pub T);
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+const SECS_PER_DAY: i64 = 86_400;
+/// Julian day number of 1970-01-01 00:00:00 UTC
+const UNIX_EPOCH_JULIAN_DAY: f64 = 2_440_587.5;
+
+fn out_of_range() -> Error {
+    Error::ToSqlConversionFailure("timestamp out of range".into())
+}
+
+fn from_unix<T: Timestamp>(secs: i64, nanos: u32) -> FromSqlResult<T> {
+    T::from_unix(secs, nanos).ok_or(FromSqlError::OutOfRange(secs))
+}
+
+fn from_unix_f64<T: Timestamp>(secs: f64) -> FromSqlResult<T> {
+    let whole = secs.floor();
+    if !whole.is_finite() || whole < i64::MIN as f64 || whole >= i64::MAX as f64 {
+        return Err(FromSqlError::InvalidType);
+    }
+    let mut nanos = ((secs - whole) * f64::from(NANOS_PER_SEC)).round() as u32;
+    let mut whole = whole as i64;
+    if nanos >= NANOS_PER_SEC {
+        whole += 1;
+        nanos -= NANOS_PER_SEC;
+    }
+    from_unix(whole, nanos)
+}
+
+fn from_unix_millis<T: Timestamp>(millis: i64) -> FromSqlResult<T> {
+    from_unix(
+        millis.div_euclid(1000),
+        millis.rem_euclid(1000) as u32 * 1_000_000,
+    )
+}
+
+impl<T: Timestamp> ToSql for UnixSeconds<T> {
+    #[inline]
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.0.to_unix().0))
+    }
+}
+
+impl<T: Timestamp> FromSql for UnixSeconds<T> {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Integer(secs) => from_unix(secs, 0),
+            ValueRef::Real(secs) => from_unix_f64(secs),
+            _ => Err(FromSqlError::InvalidType),
+        }
+        .map(UnixSeconds)
+    }
+}
+
+impl<T: Timestamp> ToSql for UnixMillis<T> {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        let (secs, nanos) = self.0.to_unix();
+        let millis = secs
+            .checked_mul(1000)
+            .and_then(|ms| ms.checked_add(i64::from(nanos / 1_000_000)))
+            .ok_or_else(out_of_range)?;
+        Ok(ToSqlOutput::from(millis))
+    }
+}
+
+impl<T: Timestamp> FromSql for UnixMillis<T> {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Integer(millis) => from_unix_millis(millis),
+            ValueRef::Real(millis) => from_unix_f64(millis / 1000.0),
+            _ => Err(FromSqlError::InvalidType),
+        }
+        .map(UnixMillis)
+    }
+}
+
+impl<T: Timestamp> ToSql for JulianDay<T> {
+    #[inline]
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        let (secs, nanos) = self.0.to_unix();
+        let days =
+            (secs as f64 + f64::from(nanos) / f64::from(NANOS_PER_SEC)) / SECS_PER_DAY as f64;
+        Ok(ToSqlOutput::from(days + UNIX_EPOCH_JULIAN_DAY))
+    }
+}
+
+impl<T: Timestamp> FromSql for JulianDay<T> {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let days = match value {
+            ValueRef::Integer(i) => i as f64,
+            ValueRef::Real(f) => f,
+            _ => return Err(FromSqlError::InvalidType),
+        };
+        let millis = ((days - UNIX_EPOCH_JULIAN_DAY) * (SECS_PER_DAY * 1000) as f64).round();
+        if !millis.is_finite() || millis < i64::MIN as f64 || millis >= i64::MAX as f64 {
+            return Err(FromSqlError::InvalidType);
+        }
+        from_unix_millis(millis as i64).map(JulianDay)
+    }
+}
+
+impl Timestamp for SystemTime {
+    fn to_unix(&self) -> (i64, u32) {
+        match self.duration_since(UNIX_EPOCH) {
+            Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+            Err(e) => {
+                let d = e.duration();
+                let secs = -(d.as_secs() as i64);
+                match d.subsec_nanos() {
+                    0 => (secs, 0),
+                    nanos => (secs - 1, NANOS_PER_SEC - nanos),
+                }
+            }
+        }
+    }
+
+    fn from_unix(secs: i64, nanos: u32) -> Option<SystemTime> {
+        if secs >= 0 {
+            UNIX_EPOCH.checked_add(Duration::new(secs as u64, nanos))
+        } else {
+            UNIX_EPOCH
+                .checked_sub(Duration::from_secs(secs.unsigned_abs()))?
+                .checked_add(Duration::from_nanos(u64::from(nanos)))
+        }
+    }
+}
+
+/// UTC ISO 8601 timestamp => "YYYY-MM-DDTHH:MM:SS.SSSSSSSSSZ"
+impl ToSql for SystemTime {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        let (secs, nanos) = self.to_unix();
+        let (year, month, day) = civil_from_days(secs.div_euclid(SECS_PER_DAY));
+        if !(0..=9999).contains(&year) {
+            return Err(out_of_range());
+        }
+        let time = secs.rem_euclid(SECS_PER_DAY);
+        Ok(ToSqlOutput::from(format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}Z",
+            year,
+            month,
+            day,
+            time / 3600,
+            time / 60 % 60,
+            time % 60,
+            nanos
+        )))
+    }
+}
+
+/// "YYYY-MM-DD", "YYYY-MM-DD HH:MM", "YYYY-MM-DD HH:MM:SS" or
+/// "YYYY-MM-DD HH:MM:SS.SSS" (with a space or a `T`), optionally followed by
+/// `Z` or a "[+-]HH:MM" offset => UTC timestamp.
+impl FromSql for SystemTime {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let s = value.as_str()?;
+        let (secs, nanos) = parse_timestamp(s)
+            .ok_or_else(|| FromSqlError::Other(format!("invalid timestamp: {:?}", s).into()))?;
+        from_unix(secs, nanos)
+    }
+}
+
+/// Parse the formats supported by SQLite date and time functions, except
+/// for "now" and Julian day numbers. Unlike SQLite, impossible dates such as
+/// 2016-02-30 are rejected instead of being normalized.
+pub(crate) fn parse_timestamp(s: &str) -> Option<(i64, u32)> {
+    let b = s.as_bytes();
+    let num = |from: usize, len: usize| -> Option<i64> {
+        let digits = b.get(from..from + len)?;
+        digits.iter().try_fold(0, |n, c| {
+            if c.is_ascii_digit() {
+                Some(n * 10 + i64::from(c - b'0'))
+            } else {
+                None
+            }
+        })
+    };
+    let sep = |i: usize, c: u8| b.get(i) == Some(&c);
+
+    if !(sep(4, b'-') && sep(7, b'-')) {
+        return None;
+    }
+    let (year, month, day) = (num(0, 4)?, num(5, 2)?, num(8, 2)?);
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    let mut secs = days_from_civil(year, month, day) * SECS_PER_DAY;
+    let mut nanos = 0;
+    let mut i = 10;
+    if sep(i, b' ') || sep(i, b'T') {
+        if !sep(13, b':') {
+            return None;
+        }
+        let (hh, mm) = (num(11, 2)?, num(14, 2)?);
+        let mut ss = 0;
+        i = 16;
+        if sep(i, b':') {
+            ss = num(17, 2)?;
+            i = 19;
+            if sep(i, b'.') {
+                i += 1;
+                let digits = b[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+                if digits == 0 {
+                    return None;
+                }
+                // SQLite ignores the digits after the milliseconds, keep
+                // up to nanoseconds
+                for (k, c) in b[i..i + digits].iter().take(9).enumerate() {
+                    nanos += u32::from(c - b'0') * 10u32.pow(8 - k as u32);
+                }
+                i += digits;
+            }
+        }
+        if hh > 23 || mm > 59 || ss > 59 {
+            return None;
+        }
+        secs += hh * 3600 + mm * 60 + ss;
+    }
+    match &b[i..] {
+        [] | [b'Z'] | [b'z'] => {}
+        [sign, _, _, b':', _, _] if *sign == b'+' || *sign == b'-' => {
+            let offset = num(i + 1, 2)? * 3600 + num(i + 4, 2)? * 60;
+            secs += if *sign == b'+' { -offset } else { offset };
+        }
+        _ => return None,
+    }
+    Some((secs, nanos))
+}
+
+fn days_in_month(y: i64, m: i64) -> i64 {
+    match m {
+        2 if y % 4 == 0 && (y % 100 != 0 || y % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// http://howardhinnant.github.io/date_algorithms.html
+pub(crate) fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((m + 9) % 12) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+pub(crate) fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    (y, m, d)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{JulianDay, UnixMillis, UnixSeconds};
+    use crate::{params, Connection, Result};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    fn checked_memory_handle() -> Result<Connection> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("CREATE TABLE foo (t TEXT, i INTEGER, f FLOAT)")?;
+        Ok(db)
+    }
+
+    #[test]
+    fn test_system_time() -> Result<()> {
+        let db = checked_memory_handle()?;
+        let times = [
+            UNIX_EPOCH,
+            UNIX_EPOCH + Duration::new(1_500_391_124, 123_456_789),
+            UNIX_EPOCH - Duration::new(86_400 * 365, 1),
+        ];
+        for &t in &times {
+            db.execute("INSERT INTO foo (t) VALUES (?)", [t])?;
+            let (from, julian): (SystemTime, f64) =
+                db.query_row("SELECT t, julianday(t) FROM foo", [], |r| {
+                    Ok((r.get(0)?, r.get(1)?))
+                })?;
+            db.execute("DELETE FROM foo", [])?;
+            assert_eq!(t, from);
+            assert!((julian - 2_440_587.5 - t.to_unix_days()).abs() < 1e-8);
+        }
+
+        let s: String =
+            db.query_row("SELECT ?", [UNIX_EPOCH + Duration::new(1, 5)], |r| r.get(0))?;
+        assert_eq!("1970-01-01T00:00:01.000000005Z", s);
+
+        for (s, secs) in &[
+            ("2016-02-23", 1_456_185_600),
+            ("2016-02-23 23:56:04", 1_456_271_764),
+            ("2016-02-23T23:56", 1_456_271_760),
+            ("2016-02-23 23:56:04+01:00", 1_456_268_164),
+        ] {
+            let t: SystemTime = db.query_row("SELECT ?", [s], |r| r.get(0))?;
+            assert_eq!(UNIX_EPOCH + Duration::from_secs(*secs), t);
+        }
+        let t: SystemTime = db.query_row("SELECT CURRENT_TIMESTAMP", [], |r| r.get(0))?;
+        assert!(t <= SystemTime::now());
+        for s in &["2016-02-30x", "2016-02-30", "2015-02-29", "2016-04-31"] {
+            assert!(
+                db.query_row("SELECT ?", [s], |r| r.get::<_, SystemTime>(0))
+                    .is_err(),
+                "{}",
+                s
+            );
+        }
+        let t: SystemTime = db.query_row("SELECT '2016-02-29'", [], |r| r.get(0))?;
+        assert_eq!(UNIX_EPOCH + Duration::from_secs(1_456_704_000), t);
+        Ok(())
+    }
+
+    trait UnixDays {
+        fn to_unix_days(&self) -> f64;
+    }
+
+    impl UnixDays for SystemTime {
+        fn to_unix_days(&self) -> f64 {
+            match self.duration_since(UNIX_EPOCH) {
+                Ok(d) => d.as_secs_f64() / 86_400.0,
+                Err(e) => -e.duration().as_secs_f64() / 86_400.0,
+            }
+        }
+    }
+
+    #[test]
+    fn test_wrappers() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch("CREATE TABLE foo (s INTEGER, m INTEGER, j REAL)")?;
+        let t = UNIX_EPOCH + Duration::new(1_500_391_124, 987_654_321);
+        db.execute(
+            "INSERT INTO foo (s, m, j) VALUES (?, ?, ?)",
+            params![UnixSeconds(t), UnixMillis(t), JulianDay(t)],
+        )?;
+        let (secs, millis, julian): (i64, i64, f64) =
+            db.query_row("SELECT s, m, j FROM foo", [], |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })?;
+        assert_eq!(1_500_391_124, secs);
+        assert_eq!(1_500_391_124_987, millis);
+        let expected: f64 = db.query_row(
+            "SELECT julianday(1500391124.987654321, 'unixepoch')",
+            [],
+            |r| r.get(0),
+        )?;
+        assert!((julian - expected).abs() < 1e-8);
+
+        let (UnixSeconds(s), UnixMillis(m), JulianDay(j)): (
+            UnixSeconds<SystemTime>,
+            UnixMillis<SystemTime>,
+            JulianDay<SystemTime>,
+        ) = db.query_row("SELECT s, m, j FROM foo", [], |r| {
+            Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+        })?;
+        assert_eq!(UNIX_EPOCH + Duration::from_secs(1_500_391_124), s);
+        assert_eq!(UNIX_EPOCH + Duration::from_millis(1_500_391_124_987), m);
+        assert_eq!(UNIX_EPOCH + Duration::from_millis(1_500_391_124_988), j);
+
+        // negative values and values computed by SQLite
+        let UnixMillis(t): UnixMillis<SystemTime> =
+            db.query_row("SELECT -1500", [], |r| r.get(0))?;
+        assert_eq!(UNIX_EPOCH - Duration::from_millis(1500), t);
+        let UnixSeconds(t): UnixSeconds<SystemTime> =
+            db.query_row("SELECT strftime('%s', '2016-02-23') + 0", [], |r| r.get(0))?;
+        assert_eq!(UNIX_EPOCH + Duration::from_secs(1_456_185_600), t);
+        let JulianDay(t): JulianDay<SystemTime> =
+            db.query_row("SELECT julianday('2016-02-23')", [], |r| r.get(0))?;
+        assert_eq!(UNIX_EPOCH + Duration::from_secs(1_456_185_600), t);
+        assert!(db
+            .query_row("SELECT 'x'", [], |r| r.get::<_, UnixSeconds<SystemTime>>(0))
+            .is_err());
+        Ok(())
+    }
+}
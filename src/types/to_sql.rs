@@ -1,6 +1,8 @@
+#[cfg(feature = "modern_sqlite")]
+use super::PointerValue;
 use super::{Null, Value, ValueRef};
 #[cfg(feature = "array")]
-use crate::vtab::array::Array;
+use crate::vtab::array::{Array, ArrayData};
 use crate::{Error, Result};
 use std::borrow::Cow;
 use std::convert::TryFrom;
@@ -24,6 +26,14 @@ pub enum ToSqlOutput<'a> {
     /// `feature = "array"`
     #[cfg(feature = "array")]
     Array(Array),
+
+    /// `feature = "array"` Typed or multi-column array.
+    #[cfg(feature = "array")]
+    ArrayData(ArrayData),
+
+    /// `feature = "modern_sqlite"` A Rust object passed by pointer.
+    #[cfg(feature = "modern_sqlite")]
+    Pointer(PointerValue),
 }
 
 // Generically allow any type that can be converted into a ValueRef
@@ -86,6 +96,10 @@ impl ToSql for ToSqlOutput<'_> {
             ToSqlOutput::ZeroBlob(i) => ToSqlOutput::ZeroBlob(i),
             #[cfg(feature = "array")]
             ToSqlOutput::Array(ref a) => ToSqlOutput::Array(a.clone()),
+            #[cfg(feature = "array")]
+            ToSqlOutput::ArrayData(ref a) => ToSqlOutput::ArrayData(a.clone()),
+            #[cfg(feature = "modern_sqlite")]
+            ToSqlOutput::Pointer(ref p) => ToSqlOutput::Pointer(p.clone()),
         })
     }
 }
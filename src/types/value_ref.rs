@@ -178,4 +178,33 @@ impl<'a> ValueRef<'a> {
             _ => unreachable!("sqlite3_value_type returned invalid value"),
         }
     }
+
+    /// Returns `value` converted to text like SQLite does (e.g. `1.0` for the
+    /// real 1), `None` if it is `NULL`.
+    ///
+    /// Text previously borrowed from `value` stays valid: only numbers are
+    /// converted, which adds a text representation without changing their
+    /// type.
+    #[cfg(any(feature = "csvtab", feature = "regexp", feature = "stats"))]
+    pub(crate) unsafe fn text_from_value(
+        value: *mut crate::ffi::sqlite3_value,
+    ) -> crate::Result<Option<&'a [u8]>> {
+        use crate::ffi;
+
+        Ok(match ValueRef::from_value(value) {
+            ValueRef::Null => None,
+            ValueRef::Text(s) | ValueRef::Blob(s) => Some(s),
+            ValueRef::Integer(_) | ValueRef::Real(_) => {
+                let text = ffi::sqlite3_value_text(value);
+                if text.is_null() {
+                    return Err(crate::error::error_from_sqlite_code(
+                        ffi::SQLITE_NOMEM,
+                        None,
+                    ));
+                }
+                let len = ffi::sqlite3_value_bytes(value);
+                Some(std::slice::from_raw_parts(text, len as usize))
+            }
+        })
+    }
 }
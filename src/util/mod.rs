@@ -5,7 +5,15 @@ pub(crate) use param_cache::ParamIndexCache;
 pub(crate) use small_cstr::SmallCString;
 
 // Doesn't use any modern features or vtab stuff, but is only used by them.
-#[cfg(any(feature = "modern_sqlite", feature = "vtab"))]
+#[cfg(any(
+    feature = "modern_sqlite",
+    feature = "vtab",
+    feature = "auto_extension"
+))]
 mod sqlite_string;
-#[cfg(any(feature = "modern_sqlite", feature = "vtab"))]
+#[cfg(any(
+    feature = "modern_sqlite",
+    feature = "vtab",
+    feature = "auto_extension"
+))]
 pub(crate) use sqlite_string::SqliteMallocString;
@@ -6,6 +6,10 @@
 //! Port of [carray](http://www.sqlite.org/cgi/src/finfo?name=ext/misc/carray.c)
 //! C extension: `https://www.sqlite.org/carray.html`
 //!
+//! The `rarray` function accepts an [`Array`] of [`Value`]s, or a typed
+//! [`I64Array`], [`F64Array`] or [`StringArray`] which are passed to SQLite
+//! without copying nor boxing their elements.
+//!
 //! # Example
 //!
 //! ```rust,no_run
@@ -22,6 +26,35 @@
 //!     for value in rows {
 //!         println!("{}", value?);
 //!     }
+//!     // Typed arrays are also accepted.
+//!     let ids: Rc<[i64]> = v.iter().copied().collect();
+//!     let mut stmt = db.prepare("SELECT * FROM person WHERE id IN rarray(?);")?;
+//!     stmt.execute(params![ids])?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! # Multi-column arrays
+//!
+//! The `rarray_rows` module turns a [`RowArray`] into a table whose columns
+//! are declared when the virtual table is created. The array is passed as
+//! the argument of the table-valued function, or with a `pointer = ?`
+//! constraint.
+//!
+//! ```rust,no_run
+//! # use rusqlite::{types::Value, Connection, Result, params};
+//! # use std::rc::Rc;
+//! fn example(db: &Connection) -> Result<()> {
+//!     rusqlite::vtab::array::load_module(&db)?;
+//!     db.execute_batch("CREATE VIRTUAL TABLE temp.batch USING rarray_rows(id, name)")?;
+//!     let rows = Rc::new(vec![
+//!         vec![Value::from(1), Value::from("one".to_owned())],
+//!         vec![Value::from(2), Value::from("two".to_owned())],
+//!     ]);
+//!     db.execute(
+//!         "UPDATE person SET name = b.name FROM batch(?) b WHERE person.id = b.id",
+//!         params![rows],
+//!     )?;
 //!     Ok(())
 //! }
 //! ```
@@ -30,25 +63,43 @@ use std::default::Default;
 use std::marker::PhantomData;
 use std::os::raw::{c_char, c_int, c_void};
 use std::rc::Rc;
+use std::str;
 
 use crate::ffi;
 use crate::types::{ToSql, ToSqlOutput, Value};
 use crate::vtab::{
-    eponymous_only_module, Context, IndexConstraintOp, IndexInfo, VTab, VTabConnection, VTabCursor,
-    Values,
+    dequote, eponymous_only_module, escape_double_quote, read_only_module, Context, CreateVTab,
+    IndexConstraintOp, IndexInfo, VTab, VTabConnection, VTabCursor, Values,
 };
-use crate::{Connection, Result};
+use crate::{Connection, Error, Result};
 
 // http://sqlite.org/bindptr.html
 
 pub(crate) const ARRAY_TYPE: *const c_char = b"rarray\0" as *const u8 as *const c_char;
+pub(crate) const I64_ARRAY_TYPE: *const c_char = b"rarray_i64\0" as *const u8 as *const c_char;
+pub(crate) const F64_ARRAY_TYPE: *const c_char = b"rarray_f64\0" as *const u8 as *const c_char;
+pub(crate) const STRING_ARRAY_TYPE: *const c_char = b"rarray_text\0" as *const u8 as *const c_char;
+pub(crate) const ROW_ARRAY_TYPE: *const c_char = b"rarray_rows\0" as *const u8 as *const c_char;
 
 pub(crate) unsafe extern "C" fn free_array(p: *mut c_void) {
     let _: Array = Rc::from_raw(p as *const Vec<Value>);
 }
 
+/// Typed arrays are fat pointers, so they are boxed to be passed to SQLite.
+unsafe extern "C" fn free_boxed<T>(p: *mut c_void) {
+    drop(Box::from_raw(p as *mut T));
+}
+
 /// Array parameter / pointer
 pub type Array = Rc<Vec<Value>>;
+/// Array of integers parameter / pointer
+pub type I64Array = Rc<[i64]>;
+/// Array of reals parameter / pointer
+pub type F64Array = Rc<[f64]>;
+/// Array of strings parameter / pointer
+pub type StringArray = Rc<[String]>;
+/// Array of rows parameter / pointer, for the `rarray_rows` module
+pub type RowArray = Rc<Vec<Vec<Value>>>;
 
 impl ToSql for Array {
     #[inline]
@@ -57,21 +108,134 @@ impl ToSql for Array {
     }
 }
 
-/// `feature = "array"` Register the "rarray" module.
+impl ToSql for I64Array {
+    #[inline]
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::ArrayData(ArrayData::I64(self.clone())))
+    }
+}
+
+impl ToSql for F64Array {
+    #[inline]
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::ArrayData(ArrayData::F64(self.clone())))
+    }
+}
+
+impl ToSql for StringArray {
+    #[inline]
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::ArrayData(ArrayData::String(self.clone())))
+    }
+}
+
+impl ToSql for RowArray {
+    #[inline]
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::ArrayData(ArrayData::Rows(self.clone())))
+    }
+}
+
+/// `feature = "array"` Any of the arrays accepted by the `rarray` and
+/// `rarray_rows` modules.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ArrayData {
+    /// Array of values
+    Values(Array),
+    /// Array of integers
+    I64(I64Array),
+    /// Array of reals
+    F64(F64Array),
+    /// Array of strings
+    String(StringArray),
+    /// Array of rows
+    Rows(RowArray),
+}
+
+/// Destructor passed to `sqlite3_bind_pointer` / `sqlite3_result_pointer`
+pub(crate) type PointerDestructor = unsafe extern "C" fn(*mut c_void);
+
+impl ArrayData {
+    /// Returns the pointer, its type and its destructor, to be passed to
+    /// `sqlite3_bind_pointer` or `sqlite3_result_pointer`.
+    pub(crate) fn into_raw(self) -> (*mut c_void, *const c_char, PointerDestructor) {
+        fn boxed<T>(t: T) -> *mut c_void {
+            Box::into_raw(Box::new(t)) as *mut c_void
+        }
+        match self {
+            ArrayData::Values(a) => (Rc::into_raw(a) as *mut c_void, ARRAY_TYPE, free_array),
+            ArrayData::I64(a) => (boxed(a), I64_ARRAY_TYPE, free_boxed::<I64Array>),
+            ArrayData::F64(a) => (boxed(a), F64_ARRAY_TYPE, free_boxed::<F64Array>),
+            ArrayData::String(a) => (boxed(a), STRING_ARRAY_TYPE, free_boxed::<StringArray>),
+            ArrayData::Rows(a) => (boxed(a), ROW_ARRAY_TYPE, free_boxed::<RowArray>),
+        }
+    }
+
+    /// Clones the array pointed to by `value`, if any.
+    pub(crate) unsafe fn from_value(value: *mut ffi::sqlite3_value) -> Option<ArrayData> {
+        unsafe fn boxed<T: Clone>(
+            value: *mut ffi::sqlite3_value,
+            kind: *const c_char,
+        ) -> Option<T> {
+            let ptr = ffi::sqlite3_value_pointer(value, kind) as *const T;
+            ptr.as_ref().cloned()
+        }
+        let ptr = ffi::sqlite3_value_pointer(value, ARRAY_TYPE);
+        if !ptr.is_null() {
+            let rc = Array::from_raw(ptr as *const Vec<Value>);
+            let array = rc.clone();
+            let _ = Array::into_raw(rc); // don't consume it
+            return Some(ArrayData::Values(array));
+        }
+        boxed(value, I64_ARRAY_TYPE)
+            .map(ArrayData::I64)
+            .or_else(|| boxed(value, F64_ARRAY_TYPE).map(ArrayData::F64))
+            .or_else(|| boxed(value, STRING_ARRAY_TYPE).map(ArrayData::String))
+            .or_else(|| boxed(value, ROW_ARRAY_TYPE).map(ArrayData::Rows))
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            ArrayData::Values(a) => a.len(),
+            ArrayData::I64(a) => a.len(),
+            ArrayData::F64(a) => a.len(),
+            ArrayData::String(a) => a.len(),
+            ArrayData::Rows(a) => a.len(),
+        }
+    }
+
+    /// Sets the result to the `col`-th column of the `row`-th element, or
+    /// `NULL` if there is no such column.
+    fn set_result(&self, ctx: &mut Context, row: usize, col: usize) -> Result<()> {
+        match (self, col) {
+            (ArrayData::Values(a), 0) => ctx.set_result(&a[row]),
+            (ArrayData::I64(a), 0) => ctx.set_result(&a[row]),
+            (ArrayData::F64(a), 0) => ctx.set_result(&a[row]),
+            (ArrayData::String(a), 0) => ctx.set_result(&a[row]),
+            (ArrayData::Rows(a), _) => match a[row].get(col) {
+                Some(value) => ctx.set_result(value),
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+/// `feature = "array"` Register the "rarray" and "rarray_rows" modules.
 pub fn load_module(conn: &Connection) -> Result<()> {
     let aux: Option<()> = None;
-    conn.create_module("rarray", eponymous_only_module::<ArrayTab>(), aux)
+    conn.create_module("rarray", eponymous_only_module::<ArrayTab>(), aux)?;
+    conn.create_module("rarray_rows", read_only_module::<ArrayTab>(), aux)
 }
 
-// Column numbers
-// const CARRAY_COLUMN_VALUE : c_int = 0;
-const CARRAY_COLUMN_POINTER: c_int = 1;
-
 /// An instance of the Array virtual table
 #[repr(C)]
 struct ArrayTab {
     /// Base class. Must be first
     base: ffi::sqlite3_vtab,
+    /// Column number of the hidden pointer column, after the value columns
+    pointer_column: c_int,
 }
 
 unsafe impl<'vtab> VTab<'vtab> for ArrayTab {
@@ -81,12 +245,34 @@ unsafe impl<'vtab> VTab<'vtab> for ArrayTab {
     fn connect(
         _: &mut VTabConnection,
         _aux: Option<&()>,
-        _args: &[&[u8]],
+        args: &[&[u8]],
     ) -> Result<(String, ArrayTab)> {
+        // `rarray` has a single `value` column, `rarray_rows` the declared ones.
+        let mut schema = String::from("CREATE TABLE x(");
+        let mut columns = 0;
+        if args.len() > 3 {
+            for arg in &args[3..] {
+                let name = dequote(str::from_utf8(arg)?.trim());
+                if name.is_empty() {
+                    return Err(Error::ModuleError("empty column name".to_owned()));
+                }
+                schema.push('"');
+                schema.push_str(&escape_double_quote(name));
+                schema.push_str("\",");
+                columns += 1;
+            }
+        } else if args[0] == b"rarray_rows" {
+            return Err(Error::ModuleError("no column specified".to_owned()));
+        } else {
+            schema.push_str("value,");
+            columns = 1;
+        }
+        schema.push_str("pointer hidden)");
         let vtab = ArrayTab {
             base: ffi::sqlite3_vtab::default(),
+            pointer_column: columns,
         };
-        Ok(("CREATE TABLE x(value,pointer hidden)".to_owned(), vtab))
+        Ok((schema, vtab))
     }
 
     fn best_index(&self, info: &mut IndexInfo) -> Result<()> {
@@ -99,7 +285,7 @@ unsafe impl<'vtab> VTab<'vtab> for ArrayTab {
             if constraint.operator() != IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_EQ {
                 continue;
             }
-            if let CARRAY_COLUMN_POINTER = constraint.column() {
+            if constraint.column() == self.pointer_column {
                 ptr_idx = Some(i);
             }
         }
@@ -121,10 +307,12 @@ unsafe impl<'vtab> VTab<'vtab> for ArrayTab {
     }
 
     fn open(&self) -> Result<ArrayTabCursor<'_>> {
-        Ok(ArrayTabCursor::new())
+        Ok(ArrayTabCursor::new(self.pointer_column))
     }
 }
 
+impl CreateVTab<'_> for ArrayTab {}
+
 /// A cursor for the Array virtual table
 #[repr(C)]
 struct ArrayTabCursor<'vtab> {
@@ -133,16 +321,19 @@ struct ArrayTabCursor<'vtab> {
     /// The rowid
     row_id: i64,
     /// Pointer to the array of values ("pointer")
-    ptr: Option<Array>,
+    ptr: Option<ArrayData>,
+    /// Column number of the pointer column
+    pointer_column: c_int,
     phantom: PhantomData<&'vtab ArrayTab>,
 }
 
 impl ArrayTabCursor<'_> {
-    fn new<'vtab>() -> ArrayTabCursor<'vtab> {
+    fn new<'vtab>(pointer_column: c_int) -> ArrayTabCursor<'vtab> {
         ArrayTabCursor {
             base: ffi::sqlite3_vtab_cursor::default(),
             row_id: 0,
             ptr: None,
+            pointer_column,
             phantom: PhantomData,
         }
     }
@@ -175,16 +366,13 @@ unsafe impl VTabCursor for ArrayTabCursor<'_> {
     }
 
     fn column(&self, ctx: &mut Context, i: c_int) -> Result<()> {
-        match i {
-            CARRAY_COLUMN_POINTER => Ok(()),
-            _ => {
-                if let Some(ref array) = self.ptr {
-                    let value = &array[(self.row_id - 1) as usize];
-                    ctx.set_result(&value)
-                } else {
-                    Ok(())
-                }
-            }
+        if i == self.pointer_column {
+            return Ok(());
+        }
+        if let Some(ref array) = self.ptr {
+            array.set_result(ctx, (self.row_id - 1) as usize, i as usize)
+        } else {
+            Ok(())
         }
     }
 
@@ -223,4 +411,61 @@ mod test {
         assert_eq!(1, Rc::strong_count(&ptr));
         Ok(())
     }
+
+    #[test]
+    fn test_typed_arrays() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        array::load_module(&db)?;
+
+        let ids: Rc<[i64]> = (1..=1000).collect();
+        let (count, sum): (i64, i64) = db.query_row(
+            "SELECT count(*), sum(value) FROM rarray(?) WHERE value > 500",
+            [&ids],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )?;
+        assert_eq!((500, 375_250), (count, sum));
+        assert_eq!(1, Rc::strong_count(&ids));
+
+        let reals: Rc<[f64]> = Rc::from(vec![0.5, 1.5]);
+        let sum: f64 = db.query_row("SELECT sum(value) FROM rarray(?)", [reals], |r| r.get(0))?;
+        assert_eq!(2.0, sum);
+
+        let names: Rc<[String]> = Rc::from(vec!["a".to_owned(), "b".to_owned()]);
+        let found: bool = db.query_row("SELECT 'b' IN rarray(?)", [names], |r| r.get(0))?;
+        assert!(found);
+        Ok(())
+    }
+
+    #[test]
+    fn test_row_array() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        array::load_module(&db)?;
+        db.execute_batch(
+            "CREATE TABLE person(id INTEGER PRIMARY KEY, name TEXT);
+             INSERT INTO person VALUES (1, 'one'), (2, NULL), (3, 'three');
+             CREATE VIRTUAL TABLE temp.batch USING rarray_rows(id, \"new name\");",
+        )?;
+        let rows = Rc::new(vec![
+            vec![Value::from(1), Value::from("un".to_owned())],
+            vec![Value::from(2)],
+        ]);
+        let mut stmt = db.prepare(
+            "SELECT p.id, b.\"new name\" FROM person p JOIN batch(?) b ON p.id = b.id ORDER BY 1",
+        )?;
+        let result = stmt
+            .query_map([&rows], |r| Ok((r.get(0)?, r.get(1)?)))?
+            .collect::<Result<Vec<(i64, Option<String>)>>>()?;
+        assert_eq!(vec![(1, Some("un".to_owned())), (2, None)], result);
+        let count: i64 = db.query_row(
+            "SELECT count(*) FROM batch WHERE pointer = ?",
+            [&rows],
+            |r| r.get(0),
+        )?;
+        assert_eq!(2, count);
+
+        assert!(db
+            .execute_batch("CREATE VIRTUAL TABLE temp.empty USING rarray_rows")
+            .is_err());
+        Ok(())
+    }
 }
@@ -18,22 +18,30 @@
 //!     ";
 //!     db.execute_batch(schema)?;
 //!     // Now the `my_csv_data` (virtual) table can be queried as normal...
+//!     // ... and rows can be appended to the file (immediately, even inside
+//!     // a transaction which is rolled back).
+//!     db.execute("INSERT INTO my_csv_data VALUES ('a', 'b')", [])?;
+//!     // Any query result can be exported.
+//!     let file = std::fs::File::create("export.csv").expect("cannot create file");
+//!     db.export_csv("SELECT * FROM my_csv_data", [], file)?;
 //!     Ok(())
 //! }
 //! ```
-use std::fs::File;
+use std::borrow::Cow;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::marker::PhantomData;
 use std::os::raw::c_int;
 use std::path::Path;
 use std::str;
 
 use crate::ffi;
-use crate::types::Null;
+use crate::types::{Null, ValueRef};
 use crate::vtab::{
-    dequote, escape_double_quote, parse_boolean, read_only_module, Context, CreateVTab, IndexInfo,
-    VTab, VTabConnection, VTabCursor, Values,
+    dequote, escape_double_quote, parse_boolean, update_module, Context, CreateVTab, IndexInfo,
+    UpdateVTab, VTab, VTabConnection, VTabCursor, Values,
 };
-use crate::{Connection, Error, Result};
+use crate::{Connection, Error, Params, Result};
 
 /// `feature = "csvtab"` Register the "csv" module.
 /// ```sql
@@ -42,13 +50,25 @@ use crate::{Connection, Error, Result};
 ///   [, schema=SCHEMA] -- Alternative CSV schema. 'CREATE TABLE x(col1 TEXT NOT NULL, col2 INT, ...);'
 ///   [, header=YES|NO] -- First row of CSV defines the names of columns if "yes". Default "no".
 ///   [, columns=N] -- Assume the CSV file contains N columns.
-///   [, delimiter=C] -- CSV delimiter. Default ','.
+///   [, delimiter=C] -- CSV delimiter. Default ','. '\t' or 'tab' for TSV.
 ///   [, quote=C] -- CSV quote. Default '"'. 0 means no quote.
+///   [, escape=C] -- Character escaping quotes inside quoted fields, instead of doubling them.
+///   [, comment=C] -- Lines starting with this character are ignored.
+///   [, encoding=ENC] -- 'utf-8' (default) or 'latin1' (ISO-8859-1).
+///   [, skip=N] -- Ignore the first N rows of the file, before the header.
+///   [, infer_types=YES|NO] -- Declare columns as INTEGER, REAL or TEXT depending on
+///                             the first rows, and return typed values. Default "no".
 /// );
 /// ```
+///
+/// Rows inserted into the table are appended to the file. `UPDATE` and
+/// `DELETE` are not supported.
+///
+/// Inserts are not transactional: each row is written to the file as soon as
+/// it is inserted, and stays there if the transaction is rolled back.
 pub fn load_module(conn: &Connection) -> Result<()> {
     let aux: Option<()> = None;
-    conn.create_module("csv", read_only_module::<CSVTab>(), aux)
+    conn.create_module("csv", update_module::<CSVTab>(), aux)
 }
 
 /// An instance of the CSV virtual table
@@ -61,19 +81,149 @@ struct CSVTab {
     has_headers: bool,
     delimiter: u8,
     quote: u8,
+    escape: Option<u8>,
+    comment: Option<u8>,
+    encoding: Encoding,
+    /// Number of rows skipped before the header
+    skip: u64,
+    /// Type of each column, empty if types are not inferred
+    types: Vec<ColumnType>,
     /// Offset to start of data
     offset_first_row: csv::Position,
+    /// Number of rows and length of the file when they were last counted, by
+    /// an insert, so that changes made outside the table are detected
+    row_count: Option<(i64, u64)>,
+}
+
+/// Encoding of the CSV file
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Encoding {
+    Utf8,
+    /// ISO-8859-1, each byte is the Unicode code point
+    Latin1,
+}
+
+impl Encoding {
+    fn decode(self, field: &[u8]) -> Result<Cow<'_, str>> {
+        match self {
+            Encoding::Utf8 => Ok(Cow::Borrowed(str::from_utf8(field)?)),
+            Encoding::Latin1 => Ok(Cow::Owned(field.iter().map(|&b| b as char).collect())),
+        }
+    }
+
+    fn encode(self, s: &str) -> Result<Cow<'_, [u8]>> {
+        match self {
+            Encoding::Utf8 => Ok(Cow::Borrowed(s.as_bytes())),
+            Encoding::Latin1 => s
+                .chars()
+                .map(|c| {
+                    if (c as u32) < 0x100 {
+                        Ok(c as u8)
+                    } else {
+                        Err(Error::ModuleError(format!(
+                            "character '{}' cannot be encoded in latin1",
+                            c
+                        )))
+                    }
+                })
+                .collect::<Result<Vec<u8>>>()
+                .map(Cow::Owned),
+        }
+    }
+}
+
+/// Type of a column, inferred from its first values
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ColumnType {
+    Integer,
+    Real,
+    Text,
+}
+
+impl ColumnType {
+    /// Number of rows sampled to infer the column types
+    const SAMPLE_SIZE: usize = 100;
+
+    fn infer(field: &str) -> Option<ColumnType> {
+        let field = field.trim();
+        if field.is_empty() {
+            None
+        } else if field.parse::<i64>().is_ok() {
+            Some(ColumnType::Integer)
+        } else if field
+            .bytes()
+            .all(|b| b.is_ascii_digit() || b"+-.eE".contains(&b))
+            && field.parse::<f64>().is_ok()
+        {
+            Some(ColumnType::Real)
+        } else {
+            Some(ColumnType::Text)
+        }
+    }
+
+    /// Widen the type of a column so that it can hold `other` values too.
+    fn merge(self, other: ColumnType) -> ColumnType {
+        match (self, other) {
+            (ColumnType::Integer, ColumnType::Integer) => ColumnType::Integer,
+            (ColumnType::Integer, ColumnType::Real)
+            | (ColumnType::Real, ColumnType::Integer)
+            | (ColumnType::Real, ColumnType::Real) => ColumnType::Real,
+            _ => ColumnType::Text,
+        }
+    }
+
+    fn sql(self) -> &'static str {
+        match self {
+            ColumnType::Integer => "INTEGER",
+            ColumnType::Real => "REAL",
+            ColumnType::Text => "TEXT",
+        }
+    }
 }
 
 impl CSVTab {
     fn reader(&self) -> Result<csv::Reader<File>, csv::Error> {
+        // Headers are read by `connect`, after the skipped rows.
         csv::ReaderBuilder::new()
-            .has_headers(self.has_headers)
+            .has_headers(false)
+            .flexible(true)
             .delimiter(self.delimiter)
+            .quoting(self.quote != 0)
             .quote(self.quote)
+            .escape(self.escape)
+            .double_quote(self.escape.is_none())
+            .comment(self.comment)
             .from_path(&self.filename)
     }
 
+    fn writer(&self) -> Result<csv::Writer<File>> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(&self.filename)
+            .map_err(csv::Error::from)?;
+        // Make sure the new record does not end up on the last line.
+        if file.seek(SeekFrom::End(0)).map_err(csv::Error::from)? > 0 {
+            let mut last = [0u8];
+            file.seek(SeekFrom::End(-1)).map_err(csv::Error::from)?;
+            file.read_exact(&mut last).map_err(csv::Error::from)?;
+            if last[0] != b'\n' {
+                file.write_all(b"\n").map_err(csv::Error::from)?;
+            }
+        }
+        let mut builder = csv::WriterBuilder::new();
+        builder.has_headers(false).delimiter(self.delimiter);
+        if self.quote == 0 {
+            builder.quote_style(csv::QuoteStyle::Never);
+        } else {
+            builder.quote(self.quote);
+        }
+        if let Some(escape) = self.escape {
+            builder.escape(escape).double_quote(false);
+        }
+        Ok(builder.from_writer(file))
+    }
+
     fn parameter(c_slice: &[u8]) -> Result<(&str, &str)> {
         let arg = str::from_utf8(c_slice)?.trim();
         let mut split = arg.split('=');
@@ -88,7 +238,9 @@ impl CSVTab {
     }
 
     fn parse_byte(arg: &str) -> Option<u8> {
-        if arg.len() == 1 {
+        if arg == "\\t" || arg.eq_ignore_ascii_case("tab") {
+            Some(b'\t')
+        } else if arg.len() == 1 {
             arg.bytes().next()
         } else {
             None
@@ -115,10 +267,17 @@ unsafe impl<'vtab> VTab<'vtab> for CSVTab {
             has_headers: false,
             delimiter: b',',
             quote: b'"',
+            escape: None,
+            comment: None,
+            encoding: Encoding::Utf8,
+            skip: 0,
+            types: Vec::new(),
             offset_first_row: csv::Position::new(),
+            row_count: None,
         };
         let mut schema = None;
         let mut n_col = None;
+        let mut infer_types = false;
 
         let args = &args[3..];
         for c_slice in args {
@@ -189,6 +348,58 @@ unsafe impl<'vtab> VTab<'vtab> for CSVTab {
                         )));
                     }
                 }
+                "escape" => {
+                    if let Some(b) = CSVTab::parse_byte(value) {
+                        vtab.escape = Some(b);
+                    } else {
+                        return Err(Error::ModuleError(format!(
+                            "unrecognized argument to 'escape': {}",
+                            value
+                        )));
+                    }
+                }
+                "comment" => {
+                    if let Some(b) = CSVTab::parse_byte(value) {
+                        vtab.comment = Some(b);
+                    } else {
+                        return Err(Error::ModuleError(format!(
+                            "unrecognized argument to 'comment': {}",
+                            value
+                        )));
+                    }
+                }
+                "encoding" => {
+                    vtab.encoding = match value.to_ascii_lowercase().as_str() {
+                        "utf8" | "utf-8" => Encoding::Utf8,
+                        "latin1" | "iso-8859-1" | "iso8859-1" => Encoding::Latin1,
+                        _ => {
+                            return Err(Error::ModuleError(format!(
+                                "unrecognized argument to 'encoding': {}",
+                                value
+                            )));
+                        }
+                    }
+                }
+                "skip" => {
+                    if let Ok(n) = value.parse::<u64>() {
+                        vtab.skip = n;
+                    } else {
+                        return Err(Error::ModuleError(format!(
+                            "unrecognized argument to 'skip': {}",
+                            value
+                        )));
+                    }
+                }
+                "infer_types" => {
+                    if let Some(b) = parse_boolean(value) {
+                        infer_types = b;
+                    } else {
+                        return Err(Error::ModuleError(format!(
+                            "unrecognized argument to 'infer_types': {}",
+                            value
+                        )));
+                    }
+                }
                 _ => {
                     return Err(Error::ModuleError(format!(
                         "unrecognized parameter '{}'",
@@ -202,33 +413,71 @@ unsafe impl<'vtab> VTab<'vtab> for CSVTab {
             return Err(Error::ModuleError("no CSV file specified".to_owned()));
         }
 
+        let mut reader = vtab.reader()?;
+        let mut record = csv::ByteRecord::new();
+        for _ in 0..vtab.skip {
+            if !reader.read_byte_record(&mut record)? {
+                break;
+            }
+        }
         let mut cols: Vec<String> = Vec::new();
-        if vtab.has_headers || (n_col.is_none() && schema.is_none()) {
-            let mut reader = vtab.reader()?;
-            if vtab.has_headers {
-                {
-                    let headers = reader.headers()?;
-                    // headers ignored if cols is not empty
-                    if n_col.is_none() && schema.is_none() {
-                        cols = headers
-                            .into_iter()
-                            .map(|header| escape_double_quote(&header).into_owned())
-                            .collect();
-                    }
+        if vtab.has_headers && reader.read_byte_record(&mut record)? {
+            // headers ignored if cols is not empty
+            if n_col.is_none() && schema.is_none() {
+                for header in record.iter() {
+                    let header = vtab.encoding.decode(header)?;
+                    cols.push(escape_double_quote(&header).into_owned());
                 }
-                vtab.offset_first_row = reader.position().clone();
+            }
+        }
+        vtab.offset_first_row = reader.position().clone();
+
+        // Sample the first rows to count the columns and/or infer their types.
+        let count_cols = !vtab.has_headers && n_col.is_none() && schema.is_none();
+        let mut sample = Vec::new();
+        if count_cols || infer_types {
+            let sample_size = if infer_types {
+                ColumnType::SAMPLE_SIZE
             } else {
-                let mut record = csv::ByteRecord::new();
-                if reader.read_byte_record(&mut record)? {
-                    for (i, _) in record.iter().enumerate() {
-                        cols.push(format!("c{}", i));
-                    }
-                }
+                1
+            };
+            while sample.len() < sample_size && reader.read_byte_record(&mut record)? {
+                sample.push(record.clone());
             }
-        } else if let Some(n_col) = n_col {
+        }
+        if let Some(n_col) = n_col {
             for i in 0..n_col {
                 cols.push(format!("c{}", i));
             }
+        } else if count_cols {
+            if let Some(first) = sample.first() {
+                for i in 0..first.len() {
+                    cols.push(format!("c{}", i));
+                }
+            }
+        }
+        if infer_types {
+            let n = if cols.is_empty() {
+                sample.iter().map(csv::ByteRecord::len).max().unwrap_or(0)
+            } else {
+                cols.len()
+            };
+            let mut types: Vec<Option<ColumnType>> = vec![None; n];
+            for record in &sample {
+                for (t, field) in types.iter_mut().zip(record.iter()) {
+                    let field = vtab.encoding.decode(field)?;
+                    if let Some(inferred) = ColumnType::infer(&field) {
+                        *t = Some(match *t {
+                            Some(t) => t.merge(inferred),
+                            None => inferred,
+                        });
+                    }
+                }
+            }
+            vtab.types = types
+                .into_iter()
+                .map(|t| t.unwrap_or(ColumnType::Text))
+                .collect();
         }
 
         if cols.is_empty() && schema.is_none() {
@@ -240,7 +489,8 @@ unsafe impl<'vtab> VTab<'vtab> for CSVTab {
             for (i, col) in cols.iter().enumerate() {
                 sql.push('"');
                 sql.push_str(col);
-                sql.push_str("\" TEXT");
+                sql.push_str("\" ");
+                sql.push_str(vtab.types.get(i).map_or("TEXT", |t| t.sql()));
                 if i == cols.len() - 1 {
                     sql.push_str(");");
                 } else {
@@ -266,6 +516,96 @@ unsafe impl<'vtab> VTab<'vtab> for CSVTab {
 
 impl CreateVTab<'_> for CSVTab {}
 
+impl UpdateVTab<'_> for CSVTab {
+    fn delete(&mut self, _: ValueRef<'_>) -> Result<()> {
+        Err(Error::ModuleError(
+            "cannot delete from a CSV virtual table".to_owned(),
+        ))
+    }
+
+    fn insert(&mut self, args: &Values<'_>) -> Result<i64> {
+        if args.get::<Option<i64>>(1)?.is_some() {
+            return Err(Error::ModuleError(
+                "cannot set the rowid of a CSV virtual table row".to_owned(),
+            ));
+        }
+        let len = file_len(&self.filename)?;
+        let row_count = match self.row_count {
+            Some((n, counted_len)) if counted_len == len => n,
+            _ => {
+                let mut reader = self.reader()?;
+                reader.seek(self.offset_first_row.clone())?;
+                let mut record = csv::ByteRecord::new();
+                let mut n = 0;
+                while reader.read_byte_record(&mut record)? {
+                    n += 1;
+                }
+                n
+            }
+        };
+        let mut writer = self.writer()?;
+        let mut record = csv::ByteRecord::new();
+        for (i, value) in args.iter().enumerate().skip(2) {
+            match value {
+                ValueRef::Text(s) => {
+                    record.push_field(&self.encoding.encode(str::from_utf8(s)?)?);
+                }
+                ValueRef::Blob(s) => record.push_field(s),
+                // like the column of a normal table converted to text
+                _ => record.push_field(args.get_text(i)?.unwrap_or_default()),
+            }
+        }
+        writer.write_byte_record(&record)?;
+        writer.flush().map_err(csv::Error::from)?;
+        self.row_count = Some((row_count + 1, file_len(&self.filename)?));
+        Ok(row_count + 1)
+    }
+
+    fn update(&mut self, _: &Values<'_>) -> Result<()> {
+        Err(Error::ModuleError(
+            "cannot update a CSV virtual table".to_owned(),
+        ))
+    }
+}
+
+fn file_len(path: &str) -> Result<u64> {
+    let metadata = std::fs::metadata(path).map_err(csv::Error::from)?;
+    Ok(metadata.len())
+}
+
+impl Connection {
+    /// `feature = "csvtab"` Write the result of `sql` to `writer` as CSV,
+    /// preceded by a header record with the column names.
+    ///
+    /// `NULL` values are written as empty fields, and numbers are converted
+    /// to text like SQLite does (`CAST(x AS TEXT)`). Returns the number of
+    /// rows written, not counting the header.
+    pub fn export_csv<P: Params, W: io::Write>(
+        &self,
+        sql: &str,
+        params: P,
+        writer: W,
+    ) -> Result<usize> {
+        let mut stmt = self.prepare(sql)?;
+        let mut writer = csv::Writer::from_writer(writer);
+        writer.write_record(stmt.column_names())?;
+        let column_count = stmt.column_count();
+        let mut rows = stmt.query(params)?;
+        let mut record = csv::ByteRecord::new();
+        let mut count = 0;
+        while let Some(row) = rows.next()? {
+            record.clear();
+            for i in 0..column_count {
+                record.push_field(row.stmt.value_text(i)?.unwrap_or_default());
+            }
+            writer.write_byte_record(&record)?;
+            count += 1;
+        }
+        writer.flush().map_err(csv::Error::from)?;
+        Ok(count)
+    }
+}
+
 /// A cursor for the CSV virtual table
 #[repr(C)]
 struct CSVTabCursor<'vtab> {
@@ -276,7 +616,7 @@ struct CSVTabCursor<'vtab> {
     /// Current cursor position used as rowid
     row_number: usize,
     /// Values of the current row
-    cols: csv::StringRecord,
+    cols: csv::ByteRecord,
     eof: bool,
     phantom: PhantomData<&'vtab CSVTab>,
 }
@@ -287,7 +627,7 @@ impl CSVTabCursor<'_> {
             base: ffi::sqlite3_vtab_cursor::default(),
             reader,
             row_number: 0,
-            cols: csv::StringRecord::new(),
+            cols: csv::ByteRecord::new(),
             eof: false,
             phantom: PhantomData,
         }
@@ -323,7 +663,7 @@ unsafe impl VTabCursor for CSVTabCursor<'_> {
                 return Ok(());
             }
 
-            self.eof = !self.reader.read_record(&mut self.cols)?;
+            self.eof = !self.reader.read_byte_record(&mut self.cols)?;
         }
 
         self.row_number += 1;
@@ -335,17 +675,33 @@ unsafe impl VTabCursor for CSVTabCursor<'_> {
     }
 
     fn column(&self, ctx: &mut Context, col: c_int) -> Result<()> {
-        if col < 0 || col as usize >= self.cols.len() {
+        if col < 0 {
             return Err(Error::ModuleError(format!(
                 "column index out of bounds: {}",
                 col
             )));
         }
-        if self.cols.is_empty() {
-            return ctx.set_result(&Null);
+        // Missing fields of short rows are NULL.
+        let field = match self.cols.get(col as usize) {
+            Some(field) => field,
+            None => return ctx.set_result(&Null),
+        };
+        let vtab = self.vtab();
+        let field = vtab.encoding.decode(field)?;
+        match vtab.types.get(col as usize) {
+            Some(ColumnType::Integer) | Some(ColumnType::Real) if field.trim().is_empty() => {
+                ctx.set_result(&Null)
+            }
+            Some(ColumnType::Integer) => match field.trim().parse::<i64>() {
+                Ok(i) => ctx.set_result(&i),
+                Err(_) => ctx.set_result(&field),
+            },
+            Some(ColumnType::Real) => match field.trim().parse::<f64>() {
+                Ok(r) => ctx.set_result(&r),
+                Err(_) => ctx.set_result(&field),
+            },
+            _ => ctx.set_result(&field),
         }
-        // TODO Affinity
-        ctx.set_result(&self.cols[col as usize].to_owned())
     }
 
     fn rowid(&self) -> Result<i64> {
@@ -363,8 +719,9 @@ impl From<csv::Error> for Error {
 #[cfg(test)]
 mod test {
     use crate::vtab::csvtab;
-    use crate::{Connection, Result};
+    use crate::{params, Connection, Result};
     use fallible_iterator::FallibleIterator;
+    use std::io::Write;
 
     #[test]
     fn test_csv_module() -> Result<()> {
@@ -404,4 +761,132 @@ mod test {
         }
         db.execute_batch("DROP TABLE vtab")
     }
+
+    #[test]
+    fn test_csv_insert() -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("insert.csv");
+        // no trailing newline
+        std::fs::write(&path, "a,b\n1,2").unwrap();
+        let db = Connection::open_in_memory()?;
+        csvtab::load_module(&db)?;
+        db.execute_batch(&format!(
+            "CREATE VIRTUAL TABLE vtab USING csv(filename='{}', header=yes)",
+            path.display()
+        ))?;
+        db.execute("INSERT INTO vtab VALUES (?, ?)", params![3, "with, comma"])?;
+        assert_eq!(2, db.last_insert_rowid());
+        db.execute("INSERT INTO vtab(b) VALUES (4.0)", [])?;
+        assert_eq!(
+            "a,b\n1,2\n3,\"with, comma\"\n,4.0\n",
+            std::fs::read_to_string(&path).unwrap()
+        );
+        let count: i64 = db.query_row("SELECT count(*) FROM vtab", [], |r| r.get(0))?;
+        assert_eq!(3, count);
+
+        // rows appended by someone else
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"5,6\n7,8\n").unwrap();
+        db.execute("INSERT INTO vtab VALUES (9, 10)", [])?;
+        assert_eq!(6, db.last_insert_rowid());
+
+        // not transactional
+        db.execute_batch("BEGIN; INSERT INTO vtab VALUES (11, 12); ROLLBACK;")?;
+        let count: i64 = db.query_row("SELECT count(*) FROM vtab", [], |r| r.get(0))?;
+        assert_eq!(7, count);
+
+        assert!(db.execute("DELETE FROM vtab", []).is_err());
+        assert!(db.execute("UPDATE vtab SET a = 0", []).is_err());
+        db.execute_batch("DROP TABLE vtab")
+    }
+
+    #[test]
+    fn test_csv_options() -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vendor.tsv");
+        std::fs::write(
+            &path,
+            "generated by vendor\n\
+             id\tprice\tname\tnote\n\
+             # discontinued\n\
+             9\t1.5\tx\n\
+             10\t2\t\"y \\\" z\"\t\n\
+             11\t\t12\t\n",
+        )
+        .unwrap();
+        let db = Connection::open_in_memory()?;
+        csvtab::load_module(&db)?;
+        db.execute_batch(&format!(
+            "CREATE VIRTUAL TABLE vtab USING csv(filename='{}', delimiter='\\t', header=yes, \
+             skip=1, comment='#', escape='\\', infer_types=yes)",
+            path.display()
+        ))?;
+        let types = db
+            .prepare("SELECT type FROM pragma_table_info('vtab')")?
+            .query_map([], |r| r.get(0))?
+            .collect::<Result<Vec<String>>>()?;
+        assert_eq!(vec!["INTEGER", "REAL", "TEXT", "TEXT"], types);
+
+        let rows = db
+            .prepare("SELECT id, price, name, note FROM vtab WHERE id > 9 ORDER BY id")?
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))?
+            .collect::<Result<Vec<(i64, Option<f64>, String, String)>>>()?;
+        assert_eq!(
+            vec![
+                (10, Some(2.0), "y \" z".to_owned(), "".to_owned()),
+                (11, None, "12".to_owned(), "".to_owned()),
+            ],
+            rows
+        );
+        // short row
+        let note: Option<String> =
+            db.query_row("SELECT note FROM vtab WHERE id = 9", [], |r| r.get(0))?;
+        assert_eq!(None, note);
+        db.execute_batch("DROP TABLE vtab")
+    }
+
+    #[test]
+    fn test_csv_latin1() -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("latin1.csv");
+        std::fs::write(&path, b"caf\xe9;1\n").unwrap();
+        let db = Connection::open_in_memory()?;
+        csvtab::load_module(&db)?;
+        db.execute_batch(&format!(
+            "CREATE VIRTUAL TABLE vtab USING csv(filename='{}', delimiter=';', encoding=latin1)",
+            path.display()
+        ))?;
+        let name: String = db.query_row("SELECT c0 FROM vtab", [], |r| r.get(0))?;
+        assert_eq!("caf\u{e9}", name);
+        db.execute("INSERT INTO vtab VALUES ('na\u{ef}ve', 2)", [])?;
+        assert_eq!(
+            b"caf\xe9;1\nna\xefve;2\n".to_vec(),
+            std::fs::read(&path).unwrap()
+        );
+        assert!(db
+            .execute("INSERT INTO vtab VALUES ('\u{20ac}', 3)", [])
+            .is_err());
+        db.execute_batch("DROP TABLE vtab")
+    }
+
+    #[test]
+    fn test_export_csv() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        db.execute_batch(
+            "CREATE TABLE foo(x INTEGER, y TEXT, z REAL);
+             INSERT INTO foo VALUES (1, 'a\"b', NULL), (2, NULL, 0.5), (3, 'c', 1.0),
+                                    (4, 'd', 1e300);",
+        )?;
+        let mut out = Vec::new();
+        let count = db.export_csv("SELECT * FROM foo WHERE x > ?", [0], &mut out)?;
+        assert_eq!(4, count);
+        assert_eq!(
+            "x,y,z\n1,\"a\"\"b\",\n2,,0.5\n3,c,1.0\n4,d,1.0e+300\n",
+            String::from_utf8(out).unwrap()
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,647 @@
+//! `feature = "jsonltab"` Newline-delimited JSON Virtual Table.
+//!
+//! Each non-empty line of the file is a JSON value, usually an object. Its
+//! members are exposed as columns:
+//! * `null` as `NULL`,
+//! * booleans as `0` or `1`,
+//! * numbers as `INTEGER` or `REAL`,
+//! * strings as `TEXT`,
+//! * arrays and objects as JSON `TEXT`.
+//!
+//! The rowid of a row is its line number, and looking a row up by rowid does
+//! not scan the file.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use rusqlite::{Connection, Result};
+//! fn example() -> Result<()> {
+//!     // Note: This should be done once (usually when opening the DB).
+//!     let db = Connection::open_in_memory()?;
+//!     rusqlite::vtab::jsonltab::load_module(&db)?;
+//!     // Columns are discovered from the first records...
+//!     db.execute_batch("CREATE VIRTUAL TABLE logs USING jsonl(filename = 'app.log')")?;
+//!     // ... or declared with a JSON path.
+//!     db.execute_batch(
+//!         "CREATE VIRTUAL TABLE requests USING jsonl(
+//!             filename = 'app.log', ts, user '$.user.name', status '$.http.status'
+//!         )",
+//!     )?;
+//!     let errors: i64 =
+//!         db.query_row("SELECT count(*) FROM requests WHERE status >= 500", [], |r| r.get(0))?;
+//!     Ok(())
+//! }
+//! ```
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::marker::PhantomData;
+use std::os::raw::c_int;
+use std::path::Path;
+use std::str;
+
+use serde_json::Value;
+
+use crate::ffi;
+use crate::types::{Null, ValueRef};
+use crate::vtab::{
+    dequote, escape_double_quote, read_only_module, Context, CreateVTab, IndexConstraintOp,
+    IndexInfo, VTab, VTabConnection, VTabCursor, Values,
+};
+use crate::{Connection, Error, Result};
+
+/// `feature = "jsonltab"` Register the "jsonl" module.
+/// ```sql
+/// CREATE VIRTUAL TABLE vtab USING jsonl(
+///   filename=FILENAME -- Name of file containing one JSON value per line
+///   [, sample=N] -- Number of records read to discover the columns. Default 100.
+///   [, NAME ['PATH']]... -- Column NAME, extracted from PATH ('$.NAME' by default).
+/// );
+/// ```
+///
+/// Paths are made of `.key`, `."quoted key"` and `[index]` steps, after a
+/// leading `$`. If no column is declared, there is one column for each
+/// top-level member of the sampled objects, in order of appearance (the
+/// members of an object being sorted by name).
+pub fn load_module(conn: &Connection) -> Result<()> {
+    let aux: Option<()> = None;
+    conn.create_module("jsonl", read_only_module::<JsonlTab>(), aux)
+}
+
+/// Default number of records read to discover the columns
+const DEFAULT_SAMPLE_SIZE: usize = 100;
+
+/// One step of a JSON path
+#[derive(Debug, PartialEq)]
+enum PathElement {
+    Key(String),
+    Index(usize),
+}
+
+/// Parses `$.a."b c"[0]`.
+fn parse_path(path: &str) -> Result<Vec<PathElement>> {
+    let invalid = || Error::ModuleError(format!("invalid JSON path: '{}'", path));
+    let rest = path.strip_prefix('$').ok_or_else(invalid)?;
+    let mut elements = Vec::new();
+    let mut chars = rest.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '.' => {
+                let mut key = String::new();
+                if let Some(&(_, '"')) = chars.peek() {
+                    chars.next();
+                    loop {
+                        match chars.next() {
+                            Some((_, '"')) => break,
+                            Some((_, c)) => key.push(c),
+                            None => return Err(invalid()),
+                        }
+                    }
+                } else {
+                    while let Some(&(_, c)) = chars.peek() {
+                        if c == '.' || c == '[' {
+                            break;
+                        }
+                        key.push(c);
+                        chars.next();
+                    }
+                }
+                if key.is_empty() {
+                    return Err(invalid());
+                }
+                elements.push(PathElement::Key(key));
+            }
+            '[' => {
+                let mut index = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, ']')) => break,
+                        Some((_, c)) => index.push(c),
+                        None => return Err(invalid()),
+                    }
+                }
+                elements.push(PathElement::Index(
+                    index.trim().parse().map_err(|_| invalid())?,
+                ));
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(elements)
+}
+
+fn lookup<'a>(mut value: &'a Value, path: &[PathElement]) -> Option<&'a Value> {
+    for element in path {
+        value = match element {
+            PathElement::Key(key) => value.get(key)?,
+            PathElement::Index(i) => value.get(i)?,
+        };
+    }
+    Some(value)
+}
+
+/// Byte offsets of the lines of the file, to seek by rowid
+#[derive(Default)]
+struct LineIndex {
+    /// Length of the file when it was indexed
+    file_len: u64,
+    /// Offset of the start of each line
+    offsets: Vec<u64>,
+}
+
+/// An instance of the JSONL virtual table
+#[repr(C)]
+struct JsonlTab {
+    /// Base class. Must be first
+    base: ffi::sqlite3_vtab,
+    /// Name of the JSONL file
+    filename: String,
+    /// Path of each column
+    paths: Vec<Vec<PathElement>>,
+    /// Built on the first lookup by rowid, and rebuilt when the file length
+    /// changes.
+    line_index: RefCell<LineIndex>,
+}
+
+impl JsonlTab {
+    fn reader(&self) -> Result<BufReader<File>> {
+        File::open(&self.filename)
+            .map(BufReader::new)
+            .map_err(|err| Error::ModuleError(format!("{}: {}", self.filename, err)))
+    }
+
+    fn parameter(arg: &str) -> Option<(&str, &str)> {
+        let mut split = arg.splitn(2, '=');
+        let key = split.next()?.trim();
+        let value = split.next()?;
+        match key {
+            "filename" | "sample" => Some((key, dequote(value.trim()))),
+            _ => None,
+        }
+    }
+
+    /// Returns the offset of the start of line `line_number` (1-based).
+    fn line_offset(&self, line_number: i64) -> Result<Option<u64>> {
+        if line_number < 1 {
+            return Ok(None);
+        }
+        let mut reader = self.reader()?;
+        let file_len = reader
+            .get_ref()
+            .metadata()
+            .map_err(|err| Error::ModuleError(err.to_string()))?
+            .len();
+        let mut index = self.line_index.borrow_mut();
+        if index.offsets.is_empty() || index.file_len != file_len {
+            let mut offsets = vec![0];
+            let mut offset = 0;
+            let mut buf = [0u8; 8192];
+            loop {
+                let n = reader
+                    .read(&mut buf)
+                    .map_err(|err| Error::ModuleError(err.to_string()))?;
+                if n == 0 {
+                    break;
+                }
+                for (i, b) in buf[..n].iter().enumerate() {
+                    if *b == b'\n' {
+                        offsets.push(offset + i as u64 + 1);
+                    }
+                }
+                offset += n as u64;
+            }
+            *index = LineIndex { file_len, offsets };
+        }
+        Ok(index
+            .offsets
+            .get(line_number as usize - 1)
+            .copied()
+            .filter(|offset| *offset < file_len))
+    }
+}
+
+unsafe impl<'vtab> VTab<'vtab> for JsonlTab {
+    type Aux = ();
+    type Cursor = JsonlTabCursor<'vtab>;
+
+    fn connect(
+        _: &mut VTabConnection,
+        _aux: Option<&()>,
+        args: &[&[u8]],
+    ) -> Result<(String, JsonlTab)> {
+        if args.len() < 4 {
+            return Err(Error::ModuleError("no JSONL file specified".to_owned()));
+        }
+
+        let mut vtab = JsonlTab {
+            base: ffi::sqlite3_vtab::default(),
+            filename: "".to_owned(),
+            paths: Vec::new(),
+            line_index: RefCell::default(),
+        };
+        let mut sample_size = DEFAULT_SAMPLE_SIZE;
+        let mut cols: Vec<String> = Vec::new();
+
+        for c_slice in &args[3..] {
+            let arg = str::from_utf8(c_slice)?.trim();
+            match JsonlTab::parameter(arg) {
+                Some(("filename", value)) => {
+                    if !Path::new(value).exists() {
+                        return Err(Error::ModuleError(format!(
+                            "file '{}' does not exist",
+                            value
+                        )));
+                    }
+                    vtab.filename = value.to_owned();
+                }
+                Some((_, value)) => {
+                    if let Ok(n) = value.parse::<usize>() {
+                        sample_size = n;
+                    } else {
+                        return Err(Error::ModuleError(format!(
+                            "unrecognized argument to 'sample': {}",
+                            value
+                        )));
+                    }
+                }
+                None => {
+                    let (name, path) = match arg.find(char::is_whitespace) {
+                        Some(i) => (dequote(&arg[..i]), dequote(arg[i..].trim())),
+                        None => (dequote(arg), ""),
+                    };
+                    let path = if path.is_empty() {
+                        vec![PathElement::Key(name.to_owned())]
+                    } else {
+                        parse_path(path)?
+                    };
+                    cols.push(name.to_owned());
+                    vtab.paths.push(path);
+                }
+            }
+        }
+
+        if vtab.filename.is_empty() {
+            return Err(Error::ModuleError("no JSONL file specified".to_owned()));
+        }
+
+        if cols.is_empty() {
+            let mut reader = vtab.reader()?;
+            let mut line = String::new();
+            let mut line_number = 0;
+            let mut sampled = 0;
+            while sampled < sample_size && read_line(&mut reader, &mut line)? {
+                line_number += 1;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                sampled += 1;
+                if let Value::Object(object) = parse_line(&line, line_number)? {
+                    for key in object.keys() {
+                        if !cols.contains(key) {
+                            cols.push(key.clone());
+                            vtab.paths.push(vec![PathElement::Key(key.clone())]);
+                        }
+                    }
+                }
+            }
+        }
+
+        if cols.is_empty() {
+            return Err(Error::ModuleError("no column specified".to_owned()));
+        }
+
+        let mut sql = String::from("CREATE TABLE x(");
+        for (i, col) in cols.iter().enumerate() {
+            sql.push('"');
+            sql.push_str(&escape_double_quote(col));
+            sql.push('"');
+            if i == cols.len() - 1 {
+                sql.push_str(");");
+            } else {
+                sql.push_str(", ");
+            }
+        }
+        Ok((sql, vtab))
+    }
+
+    // A full table scan, or a lookup by rowid.
+    fn best_index(&self, info: &mut IndexInfo) -> Result<()> {
+        let mut rowid_idx = None;
+        for (i, constraint) in info.constraints().enumerate() {
+            if constraint.is_usable()
+                && constraint.column() == -1
+                && constraint.operator() == IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_EQ
+            {
+                rowid_idx = Some(i);
+            }
+        }
+        if let Some(rowid_idx) = rowid_idx {
+            let mut constraint_usage = info.constraint_usage(rowid_idx);
+            constraint_usage.set_argv_index(1);
+            constraint_usage.set_omit(true);
+            info.set_estimated_cost(1.);
+            #[cfg(feature = "modern_sqlite")]
+            info.set_estimated_rows(1);
+            info.set_idx_num(1);
+        } else {
+            info.set_estimated_cost(1_000_000.);
+        }
+        Ok(())
+    }
+
+    fn open(&self) -> Result<JsonlTabCursor<'_>> {
+        Ok(JsonlTabCursor::new(self.reader()?))
+    }
+}
+
+impl CreateVTab<'_> for JsonlTab {}
+
+fn read_line(reader: &mut BufReader<File>, line: &mut String) -> Result<bool> {
+    line.clear();
+    reader
+        .read_line(line)
+        .map(|n| n > 0)
+        .map_err(|err| Error::ModuleError(err.to_string()))
+}
+
+fn parse_line(line: &str, line_number: i64) -> Result<Value> {
+    serde_json::from_str(line)
+        .map_err(|err| Error::ModuleError(format!("line {}: {}", line_number, err)))
+}
+
+/// Converts the value compared to the rowid like SQLite would, `None` if it
+/// cannot be equal to any rowid.
+fn rowid_arg(value: ValueRef<'_>) -> Option<i64> {
+    let real = match value {
+        ValueRef::Integer(i) => return Some(i),
+        ValueRef::Real(f) => f,
+        ValueRef::Text(s) => {
+            let s = str::from_utf8(s).ok()?.trim();
+            if let Ok(i) = s.parse() {
+                return Some(i);
+            }
+            s.parse().ok()?
+        }
+        ValueRef::Null | ValueRef::Blob(_) => return None,
+    };
+    if real.fract() == 0.0 && real >= i64::MIN as f64 && real < i64::MAX as f64 {
+        Some(real as i64)
+    } else {
+        None
+    }
+}
+
+/// A cursor for the JSONL virtual table
+#[repr(C)]
+struct JsonlTabCursor<'vtab> {
+    /// Base class. Must be first
+    base: ffi::sqlite3_vtab_cursor,
+    reader: BufReader<File>,
+    line: String,
+    /// Line number of the current row, used as rowid
+    line_number: i64,
+    /// Value of the current row
+    value: Value,
+    /// Only one row is visited by a lookup by rowid
+    single_row: bool,
+    eof: bool,
+    phantom: PhantomData<&'vtab JsonlTab>,
+}
+
+impl JsonlTabCursor<'_> {
+    fn new<'vtab>(reader: BufReader<File>) -> JsonlTabCursor<'vtab> {
+        JsonlTabCursor {
+            base: ffi::sqlite3_vtab_cursor::default(),
+            reader,
+            line: String::new(),
+            line_number: 0,
+            value: Value::Null,
+            single_row: false,
+            eof: false,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Accessor to the associated virtual table.
+    fn vtab(&self) -> &JsonlTab {
+        unsafe { &*(self.base.pVtab as *const JsonlTab) }
+    }
+
+    fn seek(&mut self, offset: u64) -> Result<()> {
+        self.reader
+            .seek(SeekFrom::Start(offset))
+            .map(|_| ())
+            .map_err(|err| Error::ModuleError(err.to_string()))
+    }
+}
+
+unsafe impl VTabCursor for JsonlTabCursor<'_> {
+    fn filter(&mut self, idx_num: c_int, _idx_str: Option<&str>, args: &Values<'_>) -> Result<()> {
+        self.single_row = idx_num == 1;
+        if self.single_row {
+            self.eof = true;
+            let rowid = match args.iter().next().and_then(rowid_arg) {
+                Some(rowid) => rowid,
+                // Like for a normal table, no row matches.
+                None => return Ok(()),
+            };
+            if let Some(offset) = self.vtab().line_offset(rowid)? {
+                self.seek(offset)?;
+                // A blank line is not a row.
+                if read_line(&mut self.reader, &mut self.line)? && !self.line.trim().is_empty() {
+                    self.line_number = rowid;
+                    self.value = parse_line(&self.line, rowid)?;
+                    self.eof = false;
+                }
+            }
+            return Ok(());
+        }
+        self.seek(0)?;
+        self.line_number = 0;
+        self.eof = false;
+        self.next()
+    }
+
+    fn next(&mut self) -> Result<()> {
+        if self.single_row {
+            self.eof = true;
+            return Ok(());
+        }
+        loop {
+            if !read_line(&mut self.reader, &mut self.line)? {
+                self.eof = true;
+                return Ok(());
+            }
+            self.line_number += 1;
+            if !self.line.trim().is_empty() {
+                break;
+            }
+        }
+        self.value = parse_line(&self.line, self.line_number)?;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.eof
+    }
+
+    fn column(&self, ctx: &mut Context, col: c_int) -> Result<()> {
+        let path = match self.vtab().paths.get(col as usize) {
+            Some(path) if col >= 0 => path,
+            _ => {
+                return Err(Error::ModuleError(format!(
+                    "column index out of bounds: {}",
+                    col
+                )))
+            }
+        };
+        match lookup(&self.value, path) {
+            None | Some(Value::Null) => ctx.set_result(&Null),
+            Some(Value::Bool(b)) => ctx.set_result(b),
+            Some(Value::Number(n)) => match n.as_i64() {
+                Some(i) => ctx.set_result(&i),
+                None => ctx.set_result(&n.as_f64()),
+            },
+            Some(Value::String(s)) => ctx.set_result(s),
+            Some(value) => ctx.set_result(value),
+        }
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.line_number)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_path, PathElement};
+    use crate::vtab::jsonltab;
+    use crate::{Connection, Result};
+
+    fn write_log(dir: &tempfile::TempDir) -> String {
+        let path = dir.path().join("app.log");
+        std::fs::write(
+            &path,
+            r#"{"ts": 1, "level": "info", "user": {"name": "alice"}, "tags": ["a"]}
+{"ts": 2, "level": "error", "ok": false, "latency": 1.5}
+
+{"ts": 3, "level": "info", "user": {"name": "bob"}}
+"#,
+        )
+        .unwrap();
+        path.display().to_string()
+    }
+
+    #[test]
+    fn test_parse_path() -> Result<()> {
+        assert_eq!(
+            vec![
+                PathElement::Key("a".to_owned()),
+                PathElement::Key("b c".to_owned()),
+                PathElement::Index(0),
+            ],
+            parse_path(r#"$.a."b c"[0]"#)?
+        );
+        assert!(parse_path("$").unwrap().is_empty());
+        assert!(parse_path("a").is_err());
+        assert!(parse_path("$.").is_err());
+        assert!(parse_path("$[x]").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_discovered_columns() -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let filename = write_log(&dir);
+        let db = Connection::open_in_memory()?;
+        jsonltab::load_module(&db)?;
+        db.execute_batch(&format!(
+            "CREATE VIRTUAL TABLE logs USING jsonl(filename='{}')",
+            filename
+        ))?;
+        {
+            let s = db.prepare("SELECT * FROM logs")?;
+            assert_eq!(
+                vec!["level", "tags", "ts", "user", "latency", "ok"],
+                s.column_names()
+            );
+        }
+        let rows = db
+            .prepare("SELECT rowid, ts, user, tags, ok, latency FROM logs")?
+            .query_map([], |r| {
+                Ok((
+                    r.get(0)?,
+                    r.get(1)?,
+                    r.get(2)?,
+                    r.get(3)?,
+                    r.get(4)?,
+                    r.get(5)?,
+                ))
+            })?
+            .collect::<Result<
+                Vec<(
+                    i64,
+                    i64,
+                    Option<String>,
+                    Option<String>,
+                    Option<bool>,
+                    Option<f64>,
+                )>,
+            >>()?;
+        assert_eq!(
+            vec![
+                (
+                    1,
+                    1,
+                    Some(r#"{"name":"alice"}"#.to_owned()),
+                    Some(r#"["a"]"#.to_owned()),
+                    None,
+                    None
+                ),
+                (2, 2, None, None, Some(false), Some(1.5)),
+                (4, 3, Some(r#"{"name":"bob"}"#.to_owned()), None, None, None),
+            ],
+            rows
+        );
+        db.execute_batch("DROP TABLE logs")
+    }
+
+    #[test]
+    fn test_declared_columns() -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let filename = write_log(&dir);
+        let db = Connection::open_in_memory()?;
+        jsonltab::load_module(&db)?;
+        db.execute_batch(&format!(
+            "CREATE VIRTUAL TABLE logs USING jsonl(filename='{}', level, user '$.user.name', \
+             tag '$.tags[0]')",
+            filename
+        ))?;
+        let rows = db
+            .prepare("SELECT level, user, tag FROM logs WHERE level = 'info'")?
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))?
+            .collect::<Result<Vec<(String, String, Option<String>)>>>()?;
+        assert_eq!(
+            vec![
+                ("info".to_owned(), "alice".to_owned(), Some("a".to_owned())),
+                ("info".to_owned(), "bob".to_owned(), None),
+            ],
+            rows
+        );
+
+        // lookup by line number
+        let mut stmt = db.prepare("SELECT user FROM logs WHERE rowid = ?")?;
+        let user: String = stmt.query_row([4], |r| r.get(0))?;
+        assert_eq!("bob", user);
+        assert!(stmt.query([3])?.next()?.is_none());
+        assert!(stmt.query([5])?.next()?.is_none());
+        assert!(stmt.query([0])?.next()?.is_none());
+        let user: String = stmt.query_row(["4"], |r| r.get(0))?;
+        assert_eq!("bob", user);
+        let user: String = stmt.query_row([4.0], |r| r.get(0))?;
+        assert_eq!("bob", user);
+        assert!(stmt.query([4.5])?.next()?.is_none());
+        assert!(stmt.query(["bob"])?.next()?.is_none());
+        db.execute_batch("DROP TABLE logs")
+    }
+}
@@ -122,6 +122,42 @@ pub fn read_only_module<'vtab, T: CreateVTab<'vtab>>() -> &'static Module<'vtab,
     }
 }
 
+/// `feature = "vtab"` Create a writable virtual table implementation.
+///
+/// Step 2 of [Creating New Virtual Table Implementations](https://sqlite.org/vtab.html#creating_new_virtual_table_implementations).
+pub fn update_module<'vtab, T: UpdateVTab<'vtab>>() -> &'static Module<'vtab, T> {
+    &Module {
+        base: ffi::sqlite3_module {
+            // We don't use V3
+            iVersion: 2,
+            xCreate: Some(rust_create::<T>),
+            xConnect: Some(rust_connect::<T>),
+            xBestIndex: Some(rust_best_index::<T>),
+            xDisconnect: Some(rust_disconnect::<T>),
+            xDestroy: Some(rust_destroy::<T>),
+            xOpen: Some(rust_open::<T>),
+            xClose: Some(rust_close::<T::Cursor>),
+            xFilter: Some(rust_filter::<T::Cursor>),
+            xNext: Some(rust_next::<T::Cursor>),
+            xEof: Some(rust_eof::<T::Cursor>),
+            xColumn: Some(rust_column::<T::Cursor>),
+            xRowid: Some(rust_rowid::<T::Cursor>),
+            xUpdate: Some(rust_update::<T>),
+            xBegin: None,
+            xSync: None,
+            xCommit: None,
+            xRollback: None,
+            xFindFunction: None,
+            xRename: None,
+            xSavepoint: None,
+            xRelease: None,
+            xRollbackTo: None,
+            ..ZERO_MODULE
+        },
+        phantom: PhantomData::<&'vtab T>,
+    }
+}
+
 /// `feature = "vtab"` Create an eponymous only virtual table implementation.
 ///
 /// Step 2 of [Creating New Virtual Table Implementations](https://sqlite.org/vtab.html#creating_new_virtual_table_implementations).
@@ -257,6 +293,29 @@ pub trait CreateVTab<'vtab>: VTab<'vtab> {
     }
 }
 
+/// `feature = "vtab"` Writable virtual table instance trait.
+///
+/// (See [SQLite doc](https://sqlite.org/vtab.html#xupdate))
+pub trait UpdateVTab<'vtab>: CreateVTab<'vtab> {
+    /// Delete the row whose rowid is `arg`.
+    fn delete(&mut self, arg: ValueRef<'_>) -> Result<()>;
+
+    /// Insert a new row:
+    /// - `args[0]` is `NULL`,
+    /// - `args[1]` is the rowid of the new row, or `NULL` to let the virtual
+    ///   table choose one,
+    /// - `args[2..]` are the values of the columns.
+    ///
+    /// Return the rowid of the new row.
+    fn insert(&mut self, args: &Values<'_>) -> Result<i64>;
+
+    /// Update a row:
+    /// - `args[0]` is the rowid of the row to update,
+    /// - `args[1]` is its new rowid, which may be the same as `args[0]`,
+    /// - `args[2..]` are the new values of the columns.
+    fn update(&mut self, args: &Values<'_>) -> Result<()>;
+}
+
 /// `feature = "vtab"` Index constraint operator.
 /// See [Virtual Table Constraint Operator Codes](https://sqlite.org/c3ref/c_index_constraint_eq.html) for details.
 #[derive(Debug, PartialEq)]
@@ -568,23 +627,18 @@ impl Values<'_> {
         })
     }
 
+    /// Returns the value at `idx` converted to text like SQLite does, `None`
+    /// if it is `NULL`.
+    #[cfg(feature = "csvtab")]
+    pub(crate) fn get_text(&self, idx: usize) -> Result<Option<&[u8]>> {
+        unsafe { ValueRef::text_from_value(self.args[idx]) }
+    }
+
     // `sqlite3_value_type` returns `SQLITE_NULL` for pointer.
     // So it seems not possible to enhance `ValueRef::from_value`.
     #[cfg(feature = "array")]
-    fn get_array(&self, idx: usize) -> Result<Option<array::Array>> {
-        use crate::types::Value;
-        let arg = self.args[idx];
-        let ptr = unsafe { ffi::sqlite3_value_pointer(arg, array::ARRAY_TYPE) };
-        if ptr.is_null() {
-            Ok(None)
-        } else {
-            Ok(Some(unsafe {
-                let rc = array::Array::from_raw(ptr as *const Vec<Value>);
-                let array = rc.clone();
-                array::Array::into_raw(rc); // don't consume it
-                array
-            }))
-        }
+    fn get_array(&self, idx: usize) -> Result<Option<array::ArrayData>> {
+        Ok(unsafe { array::ArrayData::from_value(self.args[idx]) })
     }
 
     /// Turns `Values` into an iterator.
@@ -1001,6 +1055,41 @@ where
     }
 }
 
+unsafe extern "C" fn rust_update<'vtab, T>(
+    vtab: *mut ffi::sqlite3_vtab,
+    argc: c_int,
+    argv: *mut *mut ffi::sqlite3_value,
+    p_rowid: *mut ffi::sqlite3_int64,
+) -> c_int
+where
+    T: UpdateVTab<'vtab>,
+{
+    let args = slice::from_raw_parts_mut(argv, argc as usize);
+    let vt = vtab as *mut T;
+    let r = if args.len() == 1 {
+        (*vt).delete(ValueRef::from_value(args[0]))
+    } else if ffi::sqlite3_value_type(args[0]) == ffi::SQLITE_NULL {
+        let values = Values { args };
+        (*vt).insert(&values).map(|rowid| *p_rowid = rowid)
+    } else {
+        let values = Values { args };
+        (*vt).update(&values)
+    };
+    match r {
+        Ok(_) => ffi::SQLITE_OK,
+        Err(Error::SqliteFailure(err, s)) => {
+            if let Some(err_msg) = s {
+                set_err_msg(vtab, &err_msg);
+            }
+            err.extended_code
+        }
+        Err(err) => {
+            set_err_msg(vtab, &err.to_string());
+            ffi::SQLITE_ERROR
+        }
+    }
+}
+
 /// Virtual table cursors can set an error message by assigning a string to
 /// `zErrMsg`.
 #[cold]
@@ -1073,6 +1162,8 @@ fn alloc(s: &str) -> *mut c_char {
 pub mod array;
 #[cfg(feature = "csvtab")]
 pub mod csvtab;
+#[cfg(feature = "jsonltab")]
+pub mod jsonltab;
 #[cfg(feature = "series")]
 pub mod series; // SQLite >= 3.9.0
 
@@ -3,22 +3,59 @@
 //! Port of C [generate series
 //! "function"](http://www.sqlite.org/cgi/src/finfo?name=ext/misc/series.c):
 //! `https://www.sqlite.org/series.html`
+//!
+//! Two variants are also provided:
+//! * `generate_date_series(START, STOP, STEP)` generates dates or timestamps
+//!   between two ISO-8601 bounds, every `STEP` which is a number of
+//!   `seconds`, `minutes`, `hours`, `days` (the default), `weeks`, `months`
+//!   or `years`, like `'15 minutes'`. Values are `YYYY-MM-DD` dates if `START`
+//!   is a date and `STEP` a number of days or more, `YYYY-MM-DD HH:MM:SS`
+//!   timestamps otherwise. Adding months or years to the 29th, 30th or 31st
+//!   day of a month gives the last day of shorter months.
+//! * `generate_float_series(START, STOP, STEP)` generates reals, `STEP`
+//!   defaulting to 1.0.
+//!
+//! Unlike `generate_series`, both need `START` and `STOP`.
+//!
+//! ```rust,no_run
+//! # use rusqlite::{Connection, Result};
+//! fn example(db: &Connection) -> Result<()> {
+//!     rusqlite::vtab::series::load_module(&db)?;
+//!     let mut stmt = db.prepare(
+//!         "SELECT d.value, count(o.id) FROM generate_date_series('2021-01-01', '2021-01-31') d
+//!          LEFT JOIN orders o ON date(o.created) = d.value
+//!          GROUP BY d.value",
+//!     )?;
+//!     Ok(())
+//! }
+//! ```
 use std::default::Default;
 use std::marker::PhantomData;
 use std::os::raw::c_int;
 
 use crate::ffi;
-use crate::types::Type;
+use crate::types::{civil_from_days, days_from_civil, parse_timestamp, Type, Value};
 use crate::vtab::{
     eponymous_only_module, Context, IndexConstraintOp, IndexInfo, VTab, VTabConnection, VTabCursor,
     Values,
 };
-use crate::{Connection, Result};
+use crate::{Connection, Error, Result};
 
-/// `feature = "series"` Register the "generate_series" module.
+/// `feature = "series"` Register the "generate_series", "generate_date_series"
+/// and "generate_float_series" modules.
 pub fn load_module(conn: &Connection) -> Result<()> {
     let aux: Option<()> = None;
-    conn.create_module("generate_series", eponymous_only_module::<SeriesTab>(), aux)
+    conn.create_module("generate_series", eponymous_only_module::<SeriesTab>(), aux)?;
+    conn.create_module(
+        "generate_date_series",
+        eponymous_only_module::<RangeSeriesTab>(),
+        Some(RangeKind::Date),
+    )?;
+    conn.create_module(
+        "generate_float_series",
+        eponymous_only_module::<RangeSeriesTab>(),
+        Some(RangeKind::Real),
+    )
 }
 
 // Column numbers
@@ -43,6 +80,92 @@ bitflags::bitflags! {
     }
 }
 
+/// Push down the start=, stop= and step= constraints, shared by all the
+/// series modules.
+fn best_index(info: &mut IndexInfo) -> Result<()> {
+    // The query plan bitmask
+    let mut idx_num: QueryPlanFlags = QueryPlanFlags::empty();
+    // Index of the start= constraint
+    let mut start_idx = None;
+    // Index of the stop= constraint
+    let mut stop_idx = None;
+    // Index of the step= constraint
+    let mut step_idx = None;
+    for (i, constraint) in info.constraints().enumerate() {
+        if !constraint.is_usable() {
+            continue;
+        }
+        if constraint.operator() != IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_EQ {
+            continue;
+        }
+        match constraint.column() {
+            SERIES_COLUMN_START => {
+                start_idx = Some(i);
+                idx_num |= QueryPlanFlags::START;
+            }
+            SERIES_COLUMN_STOP => {
+                stop_idx = Some(i);
+                idx_num |= QueryPlanFlags::STOP;
+            }
+            SERIES_COLUMN_STEP => {
+                step_idx = Some(i);
+                idx_num |= QueryPlanFlags::STEP;
+            }
+            _ => {}
+        };
+    }
+
+    let mut num_of_arg = 0;
+    if let Some(start_idx) = start_idx {
+        num_of_arg += 1;
+        let mut constraint_usage = info.constraint_usage(start_idx);
+        constraint_usage.set_argv_index(num_of_arg);
+        constraint_usage.set_omit(true);
+    }
+    if let Some(stop_idx) = stop_idx {
+        num_of_arg += 1;
+        let mut constraint_usage = info.constraint_usage(stop_idx);
+        constraint_usage.set_argv_index(num_of_arg);
+        constraint_usage.set_omit(true);
+    }
+    if let Some(step_idx) = step_idx {
+        num_of_arg += 1;
+        let mut constraint_usage = info.constraint_usage(step_idx);
+        constraint_usage.set_argv_index(num_of_arg);
+        constraint_usage.set_omit(true);
+    }
+    if idx_num.contains(QueryPlanFlags::BOTH) {
+        // Both start= and stop= boundaries are available.
+        info.set_estimated_cost(f64::from(
+            2 - if idx_num.contains(QueryPlanFlags::STEP) {
+                1
+            } else {
+                0
+            },
+        ));
+        info.set_estimated_rows(1000);
+        let order_by_consumed = {
+            let mut order_bys = info.order_bys();
+            if let Some(order_by) = order_bys.next() {
+                if order_by.is_order_by_desc() {
+                    idx_num |= QueryPlanFlags::DESC;
+                }
+                true
+            } else {
+                false
+            }
+        };
+        if order_by_consumed {
+            info.set_order_by_consumed(true);
+        }
+    } else {
+        info.set_estimated_cost(2_147_483_647f64);
+        info.set_estimated_rows(2_147_483_647);
+    }
+    info.set_idx_num(idx_num.bits());
+    Ok(())
+}
+
 /// An instance of the Series virtual table
 #[repr(C)]
 struct SeriesTab {
@@ -69,87 +192,7 @@ unsafe impl<'vtab> VTab<'vtab> for SeriesTab {
     }
 
     fn best_index(&self, info: &mut IndexInfo) -> Result<()> {
-        // The query plan bitmask
-        let mut idx_num: QueryPlanFlags = QueryPlanFlags::empty();
-        // Index of the start= constraint
-        let mut start_idx = None;
-        // Index of the stop= constraint
-        let mut stop_idx = None;
-        // Index of the step= constraint
-        let mut step_idx = None;
-        for (i, constraint) in info.constraints().enumerate() {
-            if !constraint.is_usable() {
-                continue;
-            }
-            if constraint.operator() != IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_EQ {
-                continue;
-            }
-            match constraint.column() {
-                SERIES_COLUMN_START => {
-                    start_idx = Some(i);
-                    idx_num |= QueryPlanFlags::START;
-                }
-                SERIES_COLUMN_STOP => {
-                    stop_idx = Some(i);
-                    idx_num |= QueryPlanFlags::STOP;
-                }
-                SERIES_COLUMN_STEP => {
-                    step_idx = Some(i);
-                    idx_num |= QueryPlanFlags::STEP;
-                }
-                _ => {}
-            };
-        }
-
-        let mut num_of_arg = 0;
-        if let Some(start_idx) = start_idx {
-            num_of_arg += 1;
-            let mut constraint_usage = info.constraint_usage(start_idx);
-            constraint_usage.set_argv_index(num_of_arg);
-            constraint_usage.set_omit(true);
-        }
-        if let Some(stop_idx) = stop_idx {
-            num_of_arg += 1;
-            let mut constraint_usage = info.constraint_usage(stop_idx);
-            constraint_usage.set_argv_index(num_of_arg);
-            constraint_usage.set_omit(true);
-        }
-        if let Some(step_idx) = step_idx {
-            num_of_arg += 1;
-            let mut constraint_usage = info.constraint_usage(step_idx);
-            constraint_usage.set_argv_index(num_of_arg);
-            constraint_usage.set_omit(true);
-        }
-        if idx_num.contains(QueryPlanFlags::BOTH) {
-            // Both start= and stop= boundaries are available.
-            info.set_estimated_cost(f64::from(
-                2 - if idx_num.contains(QueryPlanFlags::STEP) {
-                    1
-                } else {
-                    0
-                },
-            ));
-            info.set_estimated_rows(1000);
-            let order_by_consumed = {
-                let mut order_bys = info.order_bys();
-                if let Some(order_by) = order_bys.next() {
-                    if order_by.is_order_by_desc() {
-                        idx_num |= QueryPlanFlags::DESC;
-                    }
-                    true
-                } else {
-                    false
-                }
-            };
-            if order_by_consumed {
-                info.set_order_by_consumed(true);
-            }
-        } else {
-            info.set_estimated_cost(2_147_483_647f64);
-            info.set_estimated_rows(2_147_483_647);
-        }
-        info.set_idx_num(idx_num.bits());
-        Ok(())
+        best_index(info)
     }
 
     fn open(&self) -> Result<SeriesTabCursor<'_>> {
@@ -269,6 +312,340 @@ unsafe impl VTabCursor for SeriesTabCursor<'_> {
     }
 }
 
+/// Kind of values generated by a [`RangeSeriesTab`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RangeKind {
+    Date,
+    Real,
+}
+
+/// Step of a date series
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DateStep {
+    Seconds(i64),
+    Months(i64),
+}
+
+impl DateStep {
+    fn parse(s: &str) -> Option<(DateStep, bool)> {
+        let mut words = s.split_whitespace();
+        let n = words.next()?.parse::<i64>().ok().filter(|n| *n > 0)?;
+        let unit = words.next()?.to_ascii_lowercase();
+        if words.next().is_some() {
+            return None;
+        }
+        let unit = unit.strip_suffix('s').unwrap_or(&unit);
+        // (step, whole number of days)
+        Some(match unit {
+            "second" => (DateStep::Seconds(n), false),
+            "minute" => (DateStep::Seconds(n.checked_mul(60)?), false),
+            "hour" => (DateStep::Seconds(n.checked_mul(3600)?), false),
+            "day" => (DateStep::Seconds(n.checked_mul(SECS_PER_DAY)?), true),
+            "week" => (DateStep::Seconds(n.checked_mul(7 * SECS_PER_DAY)?), true),
+            "month" => (DateStep::Months(n), true),
+            "year" => (DateStep::Months(n.checked_mul(12)?), true),
+            _ => return None,
+        })
+    }
+}
+
+const SECS_PER_DAY: i64 = 86_400;
+
+/// Terms of a date or real series, computed from their index so that
+/// rounding errors do not accumulate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Range {
+    Date {
+        /// Seconds since the Unix epoch
+        start: i64,
+        step: DateStep,
+        date_only: bool,
+    },
+    Real {
+        start: f64,
+        step: f64,
+    },
+}
+
+impl Range {
+    fn parse_date(value: &Value) -> Result<i64> {
+        match value {
+            Value::Text(s) => parse_timestamp(s).map(|(secs, _)| secs),
+            _ => None,
+        }
+        .ok_or_else(|| Error::ModuleError(format!("invalid date: {:?}", value)))
+    }
+
+    fn too_many_terms() -> Error {
+        Error::ModuleError("too many terms in series".to_owned())
+    }
+
+    fn parse_real(value: &Value) -> Result<f64> {
+        match *value {
+            Value::Integer(i) => Ok(i as f64),
+            Value::Real(r) if r.is_finite() => Ok(r),
+            _ => Err(Error::ModuleError(format!("invalid number: {:?}", value))),
+        }
+    }
+
+    /// Returns the range and its number of terms.
+    fn new(kind: RangeKind, start: &Value, stop: &Value, step: &Value) -> Result<(Range, i64)> {
+        match kind {
+            RangeKind::Date => {
+                let (step, whole_days) = match step {
+                    Value::Text(s) => DateStep::parse(s),
+                    _ => None,
+                }
+                .ok_or_else(|| Error::ModuleError(format!("invalid step: {:?}", step)))?;
+                let date_only = whole_days && matches!(start, Value::Text(s) if s.len() == 10);
+                let range = Range::Date {
+                    start: Range::parse_date(start)?,
+                    step,
+                    date_only,
+                };
+                let stop = Range::parse_date(stop)?;
+                let count = match step {
+                    DateStep::Seconds(n) => match stop.checked_sub(range.date(0)?) {
+                        Some(d) if d >= 0 => {
+                            (d / n).checked_add(1).ok_or_else(Range::too_many_terms)?
+                        }
+                        _ => 0,
+                    },
+                    DateStep::Months(n) => {
+                        let months = |secs: i64| {
+                            let (y, m, _) = civil_from_days(secs.div_euclid(SECS_PER_DAY));
+                            y * 12 + i64::from(m)
+                        };
+                        let mut last = (months(stop) - months(range.date(0)?)).div_euclid(n);
+                        while last >= 0 && range.date(last)? > stop {
+                            last -= 1;
+                        }
+                        last + 1
+                    }
+                };
+                Ok((range, count))
+            }
+            RangeKind::Real => {
+                let start = Range::parse_real(start)?;
+                let stop = Range::parse_real(stop)?;
+                let step = Range::parse_real(step)?;
+                if step <= 0.0 {
+                    return Err(Error::ModuleError(format!("invalid step: {}", step)));
+                }
+                let count = if stop >= start {
+                    // Tolerate rounding errors on the last term.
+                    let last = ((stop - start) / step * (1.0 + 1e-12)).floor();
+                    if !last.is_finite() || last >= i64::MAX as f64 {
+                        return Err(Range::too_many_terms());
+                    }
+                    (last as i64)
+                        .checked_add(1)
+                        .ok_or_else(Range::too_many_terms)?
+                } else {
+                    0
+                };
+                Ok((Range::Real { start, step }, count))
+            }
+        }
+    }
+
+    /// Returns the `k`-th term of a date series, in seconds since the Unix
+    /// epoch.
+    fn date(&self, k: i64) -> Result<i64> {
+        match *self {
+            Range::Date {
+                start,
+                step: DateStep::Seconds(n),
+                ..
+            } => Ok(start.saturating_add(k.saturating_mul(n))),
+            Range::Date {
+                start,
+                step: DateStep::Months(n),
+                ..
+            } => {
+                let time = start.rem_euclid(SECS_PER_DAY);
+                let (y, m, d) = civil_from_days(start.div_euclid(SECS_PER_DAY));
+                let months = k
+                    .checked_mul(n)
+                    .and_then(|months| (y * 12 + i64::from(m) - 1).checked_add(months))
+                    .ok_or_else(Range::too_many_terms)?;
+                let (y, m) = (months.div_euclid(12), months.rem_euclid(12) + 1);
+                let first = days_from_civil(y, m, 1);
+                let days_in_month = if m == 12 {
+                    days_from_civil(y + 1, 1, 1)
+                } else {
+                    days_from_civil(y, m + 1, 1)
+                } - first;
+                Ok((first + i64::from(d).min(days_in_month) - 1) * SECS_PER_DAY + time)
+            }
+            Range::Real { .. } => unreachable!(),
+        }
+    }
+
+    fn set_result(&self, ctx: &mut Context, k: i64) -> Result<()> {
+        match *self {
+            Range::Date { date_only, .. } => {
+                let secs = self.date(k)?;
+                let (y, m, d) = civil_from_days(secs.div_euclid(SECS_PER_DAY));
+                if date_only {
+                    ctx.set_result(&format!("{:04}-{:02}-{:02}", y, m, d))
+                } else {
+                    let time = secs.rem_euclid(SECS_PER_DAY);
+                    ctx.set_result(&format!(
+                        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                        y,
+                        m,
+                        d,
+                        time / 3600,
+                        time / 60 % 60,
+                        time % 60
+                    ))
+                }
+            }
+            Range::Real { start, step } => ctx.set_result(&(start + k as f64 * step)),
+        }
+    }
+}
+
+/// An instance of the date or real Series virtual table
+#[repr(C)]
+struct RangeSeriesTab {
+    /// Base class. Must be first
+    base: ffi::sqlite3_vtab,
+    kind: RangeKind,
+}
+
+unsafe impl<'vtab> VTab<'vtab> for RangeSeriesTab {
+    type Aux = RangeKind;
+    type Cursor = RangeSeriesTabCursor<'vtab>;
+
+    fn connect(
+        _: &mut VTabConnection,
+        aux: Option<&RangeKind>,
+        _args: &[&[u8]],
+    ) -> Result<(String, RangeSeriesTab)> {
+        let vtab = RangeSeriesTab {
+            base: ffi::sqlite3_vtab::default(),
+            kind: *aux.unwrap(),
+        };
+        Ok((
+            "CREATE TABLE x(value,start hidden,stop hidden,step hidden)".to_owned(),
+            vtab,
+        ))
+    }
+
+    fn best_index(&self, info: &mut IndexInfo) -> Result<()> {
+        best_index(info)
+    }
+
+    fn open(&self) -> Result<RangeSeriesTabCursor<'_>> {
+        Ok(RangeSeriesTabCursor::new(self.kind))
+    }
+}
+
+/// A cursor for the date or real Series virtual table
+#[repr(C)]
+struct RangeSeriesTabCursor<'vtab> {
+    /// Base class. Must be first
+    base: ffi::sqlite3_vtab_cursor,
+    kind: RangeKind,
+    /// True to count down rather than up
+    is_desc: bool,
+    /// The rowid
+    row_id: i64,
+    /// Index of the current term
+    index: i64,
+    /// Number of terms
+    count: i64,
+    range: Option<Range>,
+    /// Arguments ("start", "stop" and "step")
+    start: Value,
+    stop: Value,
+    step: Value,
+    phantom: PhantomData<&'vtab RangeSeriesTab>,
+}
+
+impl RangeSeriesTabCursor<'_> {
+    fn new<'vtab>(kind: RangeKind) -> RangeSeriesTabCursor<'vtab> {
+        RangeSeriesTabCursor {
+            base: ffi::sqlite3_vtab_cursor::default(),
+            kind,
+            is_desc: false,
+            row_id: 0,
+            index: 0,
+            count: 0,
+            range: None,
+            start: Value::Null,
+            stop: Value::Null,
+            step: Value::Null,
+            phantom: PhantomData,
+        }
+    }
+}
+
+unsafe impl VTabCursor for RangeSeriesTabCursor<'_> {
+    fn filter(&mut self, idx_num: c_int, _idx_str: Option<&str>, args: &Values<'_>) -> Result<()> {
+        let idx_num = QueryPlanFlags::from_bits_truncate(idx_num);
+        if !idx_num.contains(QueryPlanFlags::BOTH) {
+            return Err(Error::ModuleError(
+                "start and stop must be specified".to_owned(),
+            ));
+        }
+        self.start = args.get(0)?;
+        self.stop = args.get(1)?;
+        self.step = if idx_num.contains(QueryPlanFlags::STEP) {
+            args.get(2)?
+        } else if self.kind == RangeKind::Date {
+            Value::Text("1 day".to_owned())
+        } else {
+            Value::Real(1.0)
+        };
+        if args.iter().any(|arg| arg.data_type() == Type::Null) {
+            // If any of the constraints have a NULL value, then return no rows.
+            self.range = None;
+            self.count = 0;
+        } else {
+            let (range, count) = Range::new(self.kind, &self.start, &self.stop, &self.step)?;
+            self.range = Some(range);
+            self.count = count;
+        }
+        self.is_desc = idx_num.contains(QueryPlanFlags::DESC);
+        self.index = if self.is_desc { self.count - 1 } else { 0 };
+        self.row_id = 1;
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        if self.is_desc {
+            self.index -= 1;
+        } else {
+            self.index += 1;
+        }
+        self.row_id += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.index < 0 || self.index >= self.count
+    }
+
+    fn column(&self, ctx: &mut Context, i: c_int) -> Result<()> {
+        match i {
+            SERIES_COLUMN_START => ctx.set_result(&self.start),
+            SERIES_COLUMN_STOP => ctx.set_result(&self.stop),
+            SERIES_COLUMN_STEP => ctx.set_result(&self.step),
+            _ => match self.range {
+                Some(ref range) => range.set_result(ctx, self.index),
+                None => Ok(()),
+            },
+        }
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.row_id)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::ffi;
@@ -296,4 +673,101 @@ mod test {
         }
         Ok(())
     }
+
+    fn series<T: crate::types::FromSql>(db: &Connection, sql: &str) -> Result<Vec<T>> {
+        db.prepare(sql)?.query_map([], |row| row.get(0))?.collect()
+    }
+
+    #[test]
+    fn test_date_series() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        series::load_module(&db)?;
+
+        let days: Vec<String> = series(
+            &db,
+            "SELECT value FROM generate_date_series('2020-02-27', '2020-03-02')",
+        )?;
+        assert_eq!(
+            vec![
+                "2020-02-27",
+                "2020-02-28",
+                "2020-02-29",
+                "2020-03-01",
+                "2020-03-02"
+            ],
+            days
+        );
+        let times: Vec<String> = series(
+            &db,
+            "SELECT value FROM generate_date_series('2021-01-01 23:30', '2021-01-02', '15 minutes') \
+             ORDER BY value DESC",
+        )?;
+        assert_eq!(
+            vec![
+                "2021-01-02 00:00:00",
+                "2021-01-01 23:45:00",
+                "2021-01-01 23:30:00"
+            ],
+            times
+        );
+        let months: Vec<String> = series(
+            &db,
+            "SELECT value FROM generate_date_series('2021-01-31', '2021-05-30', '1 month')",
+        )?;
+        assert_eq!(
+            vec!["2021-01-31", "2021-02-28", "2021-03-31", "2021-04-30"],
+            months
+        );
+        let empty: Vec<String> = series(
+            &db,
+            "SELECT value FROM generate_date_series('2021-01-02', '2021-01-01')",
+        )?;
+        assert!(empty.is_empty());
+        let months: Vec<String> = series(
+            &db,
+            "SELECT value FROM generate_date_series('2021-01-01', '9999-12-31', \
+             '9223372036854775807 months')",
+        )?;
+        assert_eq!(vec!["2021-01-01"], months);
+        assert!(series::<String>(
+            &db,
+            "SELECT value FROM generate_date_series('2021-01-01', '2021-01-02', '1 fortnight')"
+        )
+        .is_err());
+        assert!(
+            series::<String>(&db, "SELECT value FROM generate_date_series('2021-01-01')").is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_float_series() -> Result<()> {
+        let db = Connection::open_in_memory()?;
+        series::load_module(&db)?;
+
+        let values: Vec<f64> = series(&db, "SELECT value FROM generate_float_series(0, 1, 0.1)")?;
+        assert_eq!(11, values.len());
+        assert!((values[10] - 1.0).abs() < 1e-9);
+        assert!((values[3] - 0.3).abs() < 1e-9);
+        let values: Vec<f64> = series(
+            &db,
+            "SELECT value FROM generate_float_series(0.5, 2) ORDER BY value DESC",
+        )?;
+        assert_eq!(vec![1.5, 0.5], values);
+        let values: Vec<f64> =
+            series(&db, "SELECT value FROM generate_float_series(0, NULL, 0.5)")?;
+        assert!(values.is_empty());
+        assert!(series::<f64>(&db, "SELECT value FROM generate_float_series(0, 1, 0)").is_err());
+        assert!(series::<f64>(
+            &db,
+            "SELECT value FROM generate_float_series(0, 1e300, 1) LIMIT 1"
+        )
+        .is_err());
+        assert!(series::<f64>(
+            &db,
+            "SELECT value FROM generate_float_series(-1e308, 1e308, 1e-300) LIMIT 1"
+        )
+        .is_err());
+        Ok(())
+    }
 }